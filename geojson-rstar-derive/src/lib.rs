@@ -0,0 +1,457 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `#[derive(GeoJsonFeature)]`, re-exported by `geojson-rstar` with the
+//! `derive` feature.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Data, DeriveInput, Error, Field, Fields,
+    GenericArgument, Ident, LitStr, Path, PathArguments, Type,
+};
+
+/// Maps GeoJSON features onto a struct that can be put in an `RTree`.
+///
+/// The struct needs a geometry field, named `geometry` or marked
+/// `#[geojson(geometry)]`, whose type is one of `geojson`'s `PointType`,
+/// `LineStringType` and `PolygonType`, or a `Vec` of one of them for the
+/// multi geometries. When the type is an alias the kind can be given as
+/// `#[geojson(geometry = "MultiPolygon")]`. It also needs a `[f64; 4]` or
+/// `[f32; 4]` bbox field, named `bbox` or marked `#[geojson(bbox)]`, which
+/// sets the scalar type. An `Option<Id>` field named `id` or marked
+/// `#[geojson(id)]` keeps the id of the feature.
+///
+/// Every other field is read from the property of the same name, or of the
+/// name given by `#[geojson(rename = "...")]`, with `serde_json`, so a
+/// missing property is read as null. A field marked `#[geojson(skip)]` isn't
+/// read or written and is set to its `Default`.
+///
+/// This implements `GenericFeature`, `TryFrom<geojson::Feature>`,
+/// `TryFrom<Self> for geojson::Feature`, `RTreeObject` and `PointDistance`,
+/// checking the geometry and computing the bbox just like the feature types
+/// of `geojson-rstar`. Writing a feature fails with `InvalidProperty` when a
+/// field can't be converted to JSON, such as a map whose keys aren't strings.
+///
+/// The generated code refers to `::geojson_rstar`. When the dependency is
+/// renamed in `Cargo.toml`, give its path with
+/// `#[geojson_rstar(crate = "...")]` on the struct.
+#[proc_macro_derive(GeoJsonFeature, attributes(geojson, geojson_rstar))]
+pub fn derive_geojson_feature(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Clone, Copy)]
+enum GeometryKind {
+    Point,
+    LineString,
+    Polygon,
+    MultiPoint,
+    MultiLineString,
+    MultiPolygon,
+}
+
+impl GeometryKind {
+    fn from_name(name: &str) -> Option<GeometryKind> {
+        match name {
+            "Point" => Some(GeometryKind::Point),
+            "LineString" => Some(GeometryKind::LineString),
+            "Polygon" => Some(GeometryKind::Polygon),
+            "MultiPoint" => Some(GeometryKind::MultiPoint),
+            "MultiLineString" => Some(GeometryKind::MultiLineString),
+            "MultiPolygon" => Some(GeometryKind::MultiPolygon),
+            _ => None,
+        }
+    }
+
+    /// The kind from the type of the geometry field, such as `PolygonType`
+    /// or `Vec<PointType>`.
+    fn from_type(ty: &Type) -> Option<GeometryKind> {
+        let name = last_segment(ty)?;
+        match name.0.as_str() {
+            "PointType" => Some(GeometryKind::Point),
+            "LineStringType" => Some(GeometryKind::LineString),
+            "PolygonType" => Some(GeometryKind::Polygon),
+            "Vec" => match last_segment(name.1?)?.0.as_str() {
+                "PointType" => Some(GeometryKind::MultiPoint),
+                "LineStringType" => Some(GeometryKind::MultiLineString),
+                "PolygonType" => Some(GeometryKind::MultiPolygon),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            GeometryKind::Point => "Point",
+            GeometryKind::LineString => "LineString",
+            GeometryKind::Polygon => "Polygon",
+            GeometryKind::MultiPoint => "MultiPoint",
+            GeometryKind::MultiLineString => "MultiLineString",
+            GeometryKind::MultiPolygon => "MultiPolygon",
+        }
+    }
+
+    /// The feature type of `geojson-rstar` with the same geometry, whose
+    /// conversion steps are reused.
+    fn feature_type(self, krate: &Path, scalar: &Type) -> TokenStream2 {
+        let feature = Ident::new(&format!("{}Feature", self.name()), Span::call_site());
+        quote!(#krate::#feature<#scalar>)
+    }
+
+    fn geometry_type(self, krate: &Path) -> TokenStream2 {
+        let geojson = quote!(#krate::__private::geojson);
+        match self {
+            GeometryKind::Point => quote!(#geojson::PointType),
+            GeometryKind::LineString => quote!(#geojson::LineStringType),
+            GeometryKind::Polygon => quote!(#geojson::PolygonType),
+            GeometryKind::MultiPoint => quote!(::std::vec::Vec<#geojson::PointType>),
+            GeometryKind::MultiLineString => quote!(::std::vec::Vec<#geojson::LineStringType>),
+            GeometryKind::MultiPolygon => quote!(::std::vec::Vec<#geojson::PolygonType>),
+        }
+    }
+
+    fn create_geo(self, krate: &Path) -> TokenStream2 {
+        let function = match self {
+            GeometryKind::Point => "create_geo_point",
+            GeometryKind::LineString => "create_geo_line_string",
+            GeometryKind::Polygon => "create_geo_polygon",
+            GeometryKind::MultiPoint => "create_geo_multi_point",
+            GeometryKind::MultiLineString => "create_geo_multi_line_string",
+            GeometryKind::MultiPolygon => "create_geo_multi_polygon",
+        };
+        let function = Ident::new(function, Span::call_site());
+        quote!(#krate::conversion::#function)
+    }
+}
+
+/// The name of the last path segment of a type, with its first type
+/// argument if it has one.
+fn last_segment(ty: &Type) -> Option<(String, Option<&Type>)> {
+    let path = match ty {
+        Type::Path(path) => path,
+        _ => return None,
+    };
+    let segment = path.path.segments.last()?;
+    let argument = match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => {
+            arguments.args.iter().find_map(|argument| match argument {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+        }
+        _ => None,
+    };
+    Some((segment.ident.to_string(), argument))
+}
+
+enum Role {
+    Geometry(Option<GeometryKind>),
+    Id,
+    Bbox,
+    Skip,
+    Property(String),
+}
+
+fn field_role(field: &Field) -> Result<Role, Error> {
+    let ident = field.ident.as_ref().expect("Only named fields are read");
+    let mut role = None;
+    let mut rename = None;
+    for attribute in field.attrs.iter().filter(|a| a.path().is_ident("geojson")) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("geometry") {
+                let kind = if meta.input.peek(syn::Token![=]) {
+                    let name: LitStr = meta.value()?.parse()?;
+                    let kind = GeometryKind::from_name(&name.value()).ok_or_else(|| {
+                        Error::new(name.span(), "expected a GeoJSON geometry type")
+                    })?;
+                    Some(kind)
+                } else {
+                    None
+                };
+                role = Some(Role::Geometry(kind));
+            } else if meta.path.is_ident("id") {
+                role = Some(Role::Id);
+            } else if meta.path.is_ident("bbox") {
+                role = Some(Role::Bbox);
+            } else if meta.path.is_ident("skip") {
+                role = Some(Role::Skip);
+            } else if meta.path.is_ident("rename") {
+                let name: LitStr = meta.value()?.parse()?;
+                rename = Some(name.value());
+            } else {
+                return Err(meta.error("unknown geojson attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(match (role, rename) {
+        (Some(role), _) => role,
+        (None, Some(key)) => Role::Property(key),
+        (None, None) if ident == "geometry" => Role::Geometry(None),
+        (None, None) if ident == "id" => Role::Id,
+        (None, None) if ident == "bbox" => Role::Bbox,
+        (None, None) => Role::Property(ident.to_string()),
+    })
+}
+
+/// The path of `geojson-rstar`, which is `::geojson_rstar` unless it is
+/// given by `#[geojson_rstar(crate = "...")]`.
+fn crate_path(input: &DeriveInput) -> Result<Path, Error> {
+    let mut path = None;
+    for attribute in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("geojson_rstar"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                let name: LitStr = meta.value()?.parse()?;
+                path = Some(name.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown geojson_rstar attribute"))
+            }
+        })?;
+    }
+    Ok(path.unwrap_or_else(|| parse_quote!(::geojson_rstar)))
+}
+
+/// The element type of a `[T; 4]` bbox field.
+fn bbox_scalar(field: &Field) -> Result<&Type, Error> {
+    match &field.ty {
+        Type::Array(array) => Ok(&array.elem),
+        ty => Err(Error::new(ty.span(), "the bbox is a [f64; 4] or [f32; 4]")),
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "GeoJsonFeature can't be derived for a generic struct",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    name.span(),
+                    "GeoJsonFeature needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                name.span(),
+                "GeoJsonFeature can only be derived for a struct",
+            ))
+        }
+    };
+
+    let mut geometry = None;
+    let mut id = None;
+    let mut bbox = None;
+    let mut skipped = vec![];
+    let mut properties = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().expect("The fields are named");
+        match field_role(field)? {
+            Role::Geometry(kind) => {
+                let kind = kind
+                    .or_else(|| GeometryKind::from_type(&field.ty))
+                    .ok_or_else(|| {
+                        Error::new(
+                            field.ty.span(),
+                            "can't tell the geometry type, give it with #[geojson(geometry = \"...\")]",
+                        )
+                    })?;
+                geometry = Some((ident, kind));
+            }
+            Role::Id => id = Some(ident),
+            Role::Bbox => bbox = Some((ident, bbox_scalar(field)?)),
+            Role::Skip => skipped.push(ident),
+            Role::Property(key) => properties.push((ident, &field.ty, key)),
+        }
+    }
+    let (geometry, kind) = geometry.ok_or_else(|| {
+        Error::new(
+            name.span(),
+            "GeoJsonFeature needs a geometry field, named `geometry` or marked #[geojson(geometry)]",
+        )
+    })?;
+    let (bbox, scalar) = bbox.ok_or_else(|| {
+        Error::new(
+            name.span(),
+            "GeoJsonFeature needs a bbox field, named `bbox` or marked #[geojson(bbox)]",
+        )
+    })?;
+
+    let krate = crate_path(input)?;
+    let private = quote!(#krate::__private);
+    let error = quote!(#krate::GeoJsonConversionError);
+    let generic = quote!(#krate::generic::GenericFeature);
+    let geometry_type = kind.geometry_type(&krate);
+    let feature_type = kind.feature_type(&krate, scalar);
+    let variant = Ident::new(kind.name(), Span::call_site());
+    let create_geo = kind.create_geo(&krate);
+
+    let read_properties = properties.iter().map(|(ident, ty, key)| {
+        quote! {
+            let #ident = #private::serde_json::from_value::<#ty>(
+                properties
+                    .get(#key)
+                    .cloned()
+                    .unwrap_or(#private::serde_json::Value::Null),
+            )
+            .map_err(|e| #error::InvalidProperty {
+                id: feature.id.clone(),
+                key: #key.into(),
+                message: e.to_string(),
+            })?;
+        }
+    });
+    let id_field = id.map(|id| quote!(#id: feature.id,));
+    let id_value = match id {
+        Some(id) => quote!(feature.#id),
+        None => quote!(::std::option::Option::None),
+    };
+    let id_clone = match id {
+        Some(id) => quote!(feature.#id.clone()),
+        None => quote!(::std::option::Option::None),
+    };
+    let write_properties = properties.iter().map(|(ident, _, key)| {
+        quote! {
+            let value = #private::serde_json::to_value(&feature.#ident).map_err(|e| {
+                #error::InvalidProperty {
+                    id: #id_clone,
+                    key: #key.into(),
+                    message: e.to_string(),
+                }
+            })?;
+            properties.insert(#key.into(), value);
+        }
+    });
+    let property_fields = properties.iter().map(|(ident, _, _)| ident);
+
+    Ok(quote! {
+        impl #generic<#name, #geometry_type> for #name {
+            fn take_geometry_type(
+                feature: &mut #private::geojson::Feature,
+            ) -> ::std::result::Result<#geometry_type, #error> {
+                <#feature_type as #generic<#feature_type, #geometry_type>>::take_geometry_type(feature)
+            }
+
+            fn check_geometry(
+                geometry: &#geometry_type,
+                feature: &#private::geojson::Feature,
+            ) -> ::std::result::Result<(), #error> {
+                <#feature_type as #generic<#feature_type, #geometry_type>>::check_geometry(
+                    geometry, feature,
+                )
+            }
+
+            fn compute_bbox(
+                feature: &mut #private::geojson::Feature,
+                geometry: &#geometry_type,
+            ) -> #private::geojson::Bbox {
+                <#feature_type as #generic<#feature_type, #geometry_type>>::compute_bbox(
+                    feature, geometry,
+                )
+            }
+
+            fn create_self(
+                feature: #private::geojson::Feature,
+                bbox: #private::geojson::Bbox,
+                geometry: #geometry_type,
+            ) -> ::std::result::Result<#name, #error> {
+                let bbox = #krate::generic::create_bbox::<#scalar>(&bbox, &feature)?;
+                let properties = feature.properties.clone().unwrap_or_default();
+                #(#read_properties)*
+                ::std::result::Result::Ok(#name {
+                    #geometry: geometry,
+                    #bbox: bbox,
+                    #id_field
+                    #(#property_fields,)*
+                    #(#skipped: ::std::default::Default::default(),)*
+                })
+            }
+        }
+
+        impl ::std::convert::TryFrom<#private::geojson::Feature> for #name {
+            type Error = #error;
+
+            fn try_from(
+                feature: #private::geojson::Feature,
+            ) -> ::std::result::Result<#name, #error> {
+                <#name as #generic<#name, #geometry_type>>::try_from(feature)
+            }
+        }
+
+        impl ::std::convert::TryFrom<#name> for #private::geojson::Feature {
+            type Error = #error;
+
+            fn try_from(
+                feature: #name,
+            ) -> ::std::result::Result<#private::geojson::Feature, #error> {
+                let mut properties = #private::serde_json::Map::new();
+                #(#write_properties)*
+                ::std::result::Result::Ok(#private::geojson::Feature {
+                    bbox: ::std::option::Option::Some(
+                        feature.#bbox.iter().map(|c| f64::from(*c)).collect(),
+                    ),
+                    geometry: ::std::option::Option::Some(#private::geojson::Geometry::new(
+                        #private::geojson::Value::#variant(feature.#geometry),
+                    )),
+                    id: #id_value,
+                    properties: ::std::option::Option::Some(properties),
+                    foreign_members: ::std::option::Option::None,
+                })
+            }
+        }
+
+        impl #private::rstar::RTreeObject for #name {
+            type Envelope = #private::rstar::AABB<[#scalar; 2]>;
+
+            fn envelope(&self) -> Self::Envelope {
+                let bbox = &self.#bbox;
+                #private::rstar::AABB::from_corners([bbox[0], bbox[1]], [bbox[2], bbox[3]])
+            }
+        }
+
+        impl #private::rstar::PointDistance for #name {
+            fn distance_2(&self, point: &[#scalar; 2]) -> #scalar {
+                let geometry = #create_geo::<f64, #scalar>(&self.#geometry)
+                    .expect("The geometry was checked when it was converted");
+                let distance = #krate::distance::geometry_distance(
+                    &#private::geo::Geometry::#variant(geometry),
+                    &#private::geo::Geometry::Point(#private::geo::Point::new(
+                        point[0], point[1],
+                    )),
+                );
+                distance * distance
+            }
+        }
+    })
+}
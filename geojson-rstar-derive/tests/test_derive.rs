@@ -0,0 +1,173 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+extern crate serde_json;
+
+use geojson::{feature::Id, PointType, PolygonType};
+use geojson_rstar::{GeoJsonConversionError, GeoJsonFeature};
+use rstar::{PointDistance, RTree, RTreeObject};
+use std::{
+    collections::BTreeMap,
+    convert::{TryFrom, TryInto},
+};
+
+#[derive(Debug, Clone, PartialEq, GeoJsonFeature)]
+struct Station {
+    geometry: PointType,
+    bbox: [f64; 4],
+    id: Option<Id>,
+    name: String,
+    #[geojson(rename = "riders")]
+    daily_riders: Option<u32>,
+    #[geojson(skip)]
+    visited: bool,
+}
+
+type Footprint = Vec<PolygonType>;
+
+#[derive(Debug, Clone, PartialEq, GeoJsonFeature)]
+struct Building {
+    #[geojson(geometry = "MultiPolygon")]
+    footprint: Footprint,
+    #[geojson(bbox)]
+    extent: [f32; 4],
+    height: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, GeoJsonFeature)]
+struct Survey {
+    geometry: PointType,
+    bbox: [f64; 4],
+    id: Option<Id>,
+    readings: Option<BTreeMap<Vec<u8>, f64>>,
+}
+
+/// `geojson-rstar` by another path, as it is when the dependency is renamed.
+mod renamed {
+    pub use geojson_rstar::{
+        __private, conversion, distance, generic, GeoJsonConversionError, PointFeature,
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, GeoJsonFeature)]
+#[geojson_rstar(crate = "self::renamed")]
+struct Stop {
+    geometry: PointType,
+    bbox: [f64; 4],
+    name: String,
+}
+
+fn station(id: u64, x: f64, y: f64, name: &str) -> geojson::Feature {
+    serde_json::from_str::<geojson::Feature>(&format!(
+        r#"{{ "type": "Feature", "id": {}, "properties": {{ "name": "{}", "riders": 1200, "line": "red" }}, "geometry": {{ "type": "Point", "coordinates": [{}, {}] }} }}"#,
+        id, name, x, y
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_derived_point_feature() {
+    let station: Station = station(7, 1.0, 2.0, "Central").try_into().unwrap();
+    assert_eq!(
+        station,
+        Station {
+            geometry: vec![1.0, 2.0],
+            bbox: [1.0, 2.0, 1.0, 2.0],
+            id: Some(Id::Number(7.into())),
+            name: "Central".into(),
+            daily_riders: Some(1200),
+            visited: false,
+        }
+    );
+
+    let feature: geojson::Feature = station.try_into().unwrap();
+    assert_eq!(feature.id, Some(Id::Number(7.into())));
+    assert_eq!(feature.bbox, Some(vec![1.0, 2.0, 1.0, 2.0]));
+    let properties = feature.properties.expect("The properties are written");
+    assert_eq!(properties.len(), 2);
+    assert_eq!(properties["name"], "Central");
+    assert_eq!(properties["riders"], 1200);
+}
+
+#[test]
+fn test_derived_feature_errors() {
+    let missing_name = serde_json::from_str::<geojson::Feature>(
+        r#"{ "type": "Feature", "id": 3, "properties": {}, "geometry": { "type": "Point", "coordinates": [1.0, 2.0] } }"#,
+    )
+    .unwrap();
+    match Station::try_from(missing_name) {
+        Err(GeoJsonConversionError::InvalidProperty { id, key, .. }) => {
+            assert_eq!(id, Some(Id::Number(3.into())));
+            assert_eq!(key, "name");
+        }
+        other => panic!("Expected an invalid property, got {:?}", other),
+    }
+
+    let line = serde_json::from_str::<geojson::Feature>(
+        r#"{ "type": "Feature", "properties": { "name": "x" }, "geometry": { "type": "LineString", "coordinates": [[1.0, 2.0], [3.0, 4.0]] } }"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        Station::try_from(line),
+        Err(GeoJsonConversionError::IncorrectGeometryValue(_))
+    ));
+
+    let infinite = serde_json::from_str::<geojson::Feature>(
+        r#"{ "type": "Feature", "properties": { "height": 3.0 }, "geometry": { "type": "MultiPolygon", "coordinates": [[[[0.0, 0.0], [1e300, 0.0], [1e300, 1.0], [0.0, 0.0]]]] } }"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        Building::try_from(infinite),
+        Err(GeoJsonConversionError::NonFiniteCoordinate(_))
+    ));
+
+    // JSON object keys have to be strings, so the readings can't be written.
+    let survey = Survey {
+        geometry: vec![1.0, 2.0],
+        bbox: [1.0, 2.0, 1.0, 2.0],
+        id: Some(Id::String("s".into())),
+        readings: Some(vec![(vec![1, 2], 0.5)].into_iter().collect()),
+    };
+    match geojson::Feature::try_from(survey) {
+        Err(GeoJsonConversionError::InvalidProperty { id, key, .. }) => {
+            assert_eq!(id, Some(Id::String("s".into())));
+            assert_eq!(key, "readings");
+        }
+        other => panic!("Expected an invalid property, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_derived_feature_with_crate_path() {
+    let stop: Stop = station(4, 3.0, 4.0, "Depot").try_into().unwrap();
+    assert_eq!(stop.name, "Depot");
+    assert_eq!(stop.distance_2(&[0.0, 0.0]), 25.0);
+    let feature: geojson::Feature = stop.try_into().unwrap();
+    assert_eq!(feature.properties.unwrap()["name"], "Depot");
+}
+
+#[test]
+fn test_derived_features_in_tree() {
+    let stations: Vec<Station> = vec![
+        station(1, 0.0, 0.0, "West").try_into().unwrap(),
+        station(2, 10.0, 0.0, "East").try_into().unwrap(),
+    ];
+    let tree = RTree::bulk_load(stations);
+    let nearest = tree
+        .nearest_neighbor(&[7.0, 4.0])
+        .expect("The tree isn't empty");
+    assert_eq!(nearest.name, "East");
+    assert_eq!(nearest.distance_2(&[7.0, 4.0]), 25.0);
+
+    let building: Building = serde_json::from_str::<geojson::Feature>(
+        r#"{ "type": "Feature", "properties": { "height": 12.5 }, "geometry": { "type": "MultiPolygon", "coordinates": [[[[0.0, 0.0], [4.0, 0.0], [4.0, 3.0], [0.0, 3.0], [0.0, 0.0]]], [[[6.0, 0.0], [8.0, 0.0], [8.0, 2.0], [6.0, 0.0]]]] } }"#,
+    )
+    .unwrap()
+    .try_into()
+    .unwrap();
+    assert_eq!(building.extent, [0.0, 0.0, 8.0, 3.0]);
+    assert_eq!(building.height, 12.5);
+    assert_eq!(building.envelope().upper(), [8.0, 3.0]);
+    assert_eq!(building.distance_2(&[2.0, 1.0]), 0.0);
+    assert_eq!(building.distance_2(&[5.0, 1.0]), 1.0);
+}
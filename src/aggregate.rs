@@ -0,0 +1,335 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Aggregating features into square or hexagonal cells, giving a polygon
+//! per cell with a count of the features in it and optionally the sum and
+//! mean of a numeric property.
+
+use crate::{
+    coordinates::position_to_point_type, distance::geometry_distance, error::AggregateError,
+    generic::create_feature, json::JsonObject, Feature, PolygonFeature,
+};
+use geo::{Geometry, LineString, Point, Polygon};
+use geojson::{FeatureCollection, Value};
+use num_traits::Float;
+use rstar::{primitives::PointWithData, Envelope, RTree, RTreeNum, AABB};
+use serde_json::Value as JsonValue;
+use std::convert::TryFrom;
+
+/// The most cells an extent can be covered with, counting the hexagons
+/// that are left out for being outside the extent.
+pub const MAX_CELLS: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellShape {
+    /// Squares with sides of `cell_size`, the first with its lower left
+    /// corner at the lower left of the extent.
+    Square,
+    /// Hexagons with a corner at the top, `cell_size` from their centre to
+    /// each corner. The first is centred on the lower left of the extent and
+    /// every other row is shifted right by half a hexagon.
+    Hexagon,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregation<T = f64>
+where
+    T: RTreeNum,
+{
+    pub shape: CellShape,
+    pub cell_size: T,
+    /// The area to cover with cells.
+    pub extent: AABB<[T; 2]>,
+    /// A numeric property to add up in each cell, as `sum` and `mean`.
+    /// Features where it is missing or not a number are only counted.
+    pub property: Option<String>,
+    /// Whether to give cells without any features.
+    pub include_empty: bool,
+}
+
+/// Aggregates the features in `tree` into cells covering the extent.
+///
+/// Each cell is a `PolygonFeature` with the properties `column`, `row` and
+/// `count`, along with `sum` and `mean` when a property is given. A point
+/// inside the extent is counted in the cell with the nearest centre, so a
+/// point on the edge between cells is only counted once. Any other feature
+/// is counted in every cell it touches.
+///
+/// No cells are given if the cell size isn't positive. The extent has to be
+/// finite and take at most `MAX_CELLS` cells to cover.
+pub fn aggregate<T>(
+    tree: &RTree<Feature<T>>,
+    aggregation: &Aggregation<T>,
+) -> Result<Vec<PolygonFeature<T>>, AggregateError>
+where
+    T: Float + RTreeNum,
+{
+    let cells = create_cells(aggregation)?;
+    let centres = RTree::bulk_load(
+        cells
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| PointWithData::new(index, cell.centre))
+            .collect(),
+    );
+
+    Ok(cells
+        .iter()
+        .enumerate()
+        .filter_map(|(index, cell)| {
+            let mut summary = Summary::default();
+            for feature in tree.locate_in_envelope_intersecting(&cell.envelope) {
+                let geometry: Geometry<T> = feature.into();
+                let counted = match &geometry {
+                    Geometry::Point(point) => {
+                        point_cell(&aggregation.extent, &centres, *point) == Some(index)
+                    }
+                    Geometry::MultiPoint(points) => points.0.iter().any(|point| {
+                        point_cell(&aggregation.extent, &centres, *point) == Some(index)
+                    }),
+                    _ => geometry_distance(&cell.geometry(), &geometry) == T::zero(),
+                };
+                if counted {
+                    summary.add(feature, aggregation.property.as_deref());
+                }
+            }
+            if summary.count == 0 && !aggregation.include_empty {
+                return None;
+            }
+            Some(cell.feature(summary.properties(aggregation.property.is_some())))
+        })
+        .collect())
+}
+
+/// Like `aggregate`, but gives the cells as a `FeatureCollection`.
+pub fn aggregate_to_collection<T>(
+    tree: &RTree<Feature<T>>,
+    aggregation: &Aggregation<T>,
+) -> Result<FeatureCollection, AggregateError>
+where
+    T: Float + RTreeNum,
+{
+    Ok(FeatureCollection {
+        bbox: None,
+        features: aggregate(tree, aggregation)?
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        foreign_members: None,
+    })
+}
+
+struct Cell<T>
+where
+    T: Float + RTreeNum,
+{
+    column: usize,
+    row: usize,
+    centre: [T; 2],
+    /// The corners counter clockwise, with the first repeated at the end.
+    ring: Vec<[T; 2]>,
+    envelope: AABB<[T; 2]>,
+}
+
+impl<T> Cell<T>
+where
+    T: Float + RTreeNum,
+{
+    fn new(column: usize, row: usize, centre: [T; 2], ring: Vec<[T; 2]>) -> Self {
+        Cell {
+            column,
+            row,
+            centre,
+            envelope: AABB::from_points(ring.iter()),
+            ring,
+        }
+    }
+
+    fn geometry(&self) -> Geometry<T> {
+        Geometry::Polygon(Polygon::new(
+            LineString::from(
+                self.ring
+                    .iter()
+                    .map(|position| (position[0], position[1]))
+                    .collect::<Vec<_>>(),
+            ),
+            vec![],
+        ))
+    }
+
+    fn feature(&self, mut properties: JsonObject) -> PolygonFeature<T> {
+        properties.insert("column".into(), self.column.into());
+        properties.insert("row".into(), self.row.into());
+        let value = Value::Polygon(vec![self.ring.iter().map(position_to_point_type).collect()]);
+        PolygonFeature::try_from(create_feature(value, None, Some(properties)))
+            .expect("A cell is a valid polygon")
+    }
+}
+
+fn create_cells<T>(aggregation: &Aggregation<T>) -> Result<Vec<Cell<T>>, AggregateError>
+where
+    T: Float + RTreeNum,
+{
+    let size = aggregation.cell_size;
+    if size.is_nan() || size <= T::zero() {
+        return Ok(vec![]);
+    }
+    let min = aggregation.extent.lower();
+    let max = aggregation.extent.upper();
+    let constant = |value: f64| T::from(value).expect("A constant fits in T");
+    // The number of cells along one axis, checking that the cells up to one
+    // past the last still have finite coordinates.
+    let count = |axis: usize, step: T, extra: usize| {
+        let length = max[axis] - min[axis];
+        if !min[axis].is_finite() || !length.is_finite() || !step.is_finite() {
+            return Err(AggregateError::NonFiniteExtent);
+        }
+        let steps = (length / step)
+            .ceil()
+            .to_usize()
+            .and_then(|steps| steps.checked_add(extra))
+            .ok_or(AggregateError::TooManyCells)?
+            .max(1);
+        if steps > MAX_CELLS {
+            return Err(AggregateError::TooManyCells);
+        }
+        if !(min[axis] + constant(steps as f64 + 1.0) * step).is_finite() {
+            return Err(AggregateError::NonFiniteExtent);
+        }
+        Ok(steps)
+    };
+    let total = |columns: usize, rows: usize| match columns.checked_mul(rows) {
+        Some(total) if total <= MAX_CELLS => Ok(total),
+        _ => Err(AggregateError::TooManyCells),
+    };
+
+    match aggregation.shape {
+        CellShape::Square => {
+            let columns = count(0, size, 0)?;
+            let rows = count(1, size, 0)?;
+            let mut cells = Vec::with_capacity(total(columns, rows)?);
+            for row in 0..rows {
+                for column in 0..columns {
+                    let x = min[0] + constant(column as f64) * size;
+                    let y = min[1] + constant(row as f64) * size;
+                    let ring = vec![
+                        [x, y],
+                        [x + size, y],
+                        [x + size, y + size],
+                        [x, y + size],
+                        [x, y],
+                    ];
+                    let half = size / constant(2.0);
+                    cells.push(Cell::new(column, row, [x + half, y + half], ring));
+                }
+            }
+            Ok(cells)
+        }
+        CellShape::Hexagon => {
+            let width = constant(3.0).sqrt() * size;
+            let height = constant(1.5) * size;
+            let columns = count(0, width, 1)?;
+            let rows = count(1, height, 1)?;
+            total(columns, rows)?;
+            let corners: Vec<[T; 2]> = (0..6)
+                .map(|corner| {
+                    let angle = constant(30.0 + 60.0 * corner as f64).to_radians();
+                    [size * angle.cos(), size * angle.sin()]
+                })
+                .collect();
+
+            let mut cells = vec![];
+            for row in 0..rows {
+                let shift = if row % 2 == 1 {
+                    width / constant(2.0)
+                } else {
+                    T::zero()
+                };
+                for column in 0..columns {
+                    let centre = [
+                        min[0] + constant(column as f64) * width + shift,
+                        min[1] + constant(row as f64) * height,
+                    ];
+                    let mut ring: Vec<[T; 2]> = corners
+                        .iter()
+                        .map(|corner| [centre[0] + corner[0], centre[1] + corner[1]])
+                        .collect();
+                    ring.push(ring[0]);
+                    let cell = Cell::new(column, row, centre, ring);
+                    if cell.envelope.intersects(&aggregation.extent) {
+                        cells.push(cell);
+                    }
+                }
+            }
+            Ok(cells)
+        }
+    }
+}
+
+/// The index of the cell a point is counted in, which is the one with the
+/// nearest centre, or `None` when the point is outside the extent.
+fn point_cell<T>(
+    extent: &AABB<[T; 2]>,
+    centres: &RTree<PointWithData<usize, [T; 2]>>,
+    point: Point<T>,
+) -> Option<usize>
+where
+    T: Float + RTreeNum,
+{
+    let position = [point.x(), point.y()];
+    if !extent.contains_point(&position) {
+        return None;
+    }
+    centres
+        .nearest_neighbor(&position)
+        .map(|centre| centre.data)
+}
+
+#[derive(Default)]
+struct Summary {
+    count: usize,
+    sum: f64,
+    values: usize,
+}
+
+impl Summary {
+    fn add<T>(&mut self, feature: &Feature<T>, property: Option<&str>)
+    where
+        T: Float,
+    {
+        self.count += 1;
+        let value = property
+            .and_then(|key| feature.properties()?.get(key))
+            .and_then(JsonValue::as_f64);
+        if let Some(value) = value {
+            self.sum += value;
+            self.values += 1;
+        }
+    }
+
+    fn properties(&self, with_property: bool) -> JsonObject {
+        let mut properties = JsonObject::new();
+        properties.insert("count".into(), self.count.into());
+        if with_property {
+            properties.insert("sum".into(), self.sum.into());
+            let mean = if self.values == 0 {
+                JsonValue::Null
+            } else {
+                (self.sum / self.values as f64).into()
+            };
+            properties.insert("mean".into(), mean);
+        }
+        properties
+    }
+}
@@ -0,0 +1,589 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Clipping features to a rectangle or a polygon, for extracts that cut
+//! geometries at the edge of an area rather than just selecting them.
+//!
+//! The clipped features keep their id and properties, and their bbox is
+//! computed from the clipped geometry. Parts of a geometry that fall outside
+//! are dropped, as are parts that would be left with no length or area.
+//!
+//! A polygon cut apart by clipping becomes a multi polygon.
+
+use crate::{
+    coordinates::{cross, geo_line_string_to_positions, position_to_point_type, signed_area},
+    distance::geometry_envelope,
+    error::{ClipError, GeoJsonConversionError},
+    generic::create_feature,
+    overlay::{Overlay, Rings},
+    Feature,
+};
+use geo::{contains::Contains, Geometry, LineString, Point, Polygon};
+use geojson::{PolygonType, Value};
+use num_traits::Float;
+use rstar::{RTree, RTreeNum, AABB};
+use std::convert::TryFrom;
+
+/// Returns the features in `tree` clipped to `rect`. Lines are cut with
+/// Liang-Barsky and polygons with Sutherland-Hodgman.
+pub fn clip_to_rect<T>(
+    tree: &RTree<Feature<T>>,
+    rect: &AABB<[T; 2]>,
+) -> Result<Vec<Feature<T>>, ClipError>
+where
+    T: Float + RTreeNum,
+{
+    let mut clipped = vec![];
+    for feature in tree.locate_in_envelope_intersecting(rect) {
+        clipped.extend(clip_feature_to_rect(feature, rect)?);
+    }
+    Ok(clipped)
+}
+
+/// Returns the features in `tree` clipped to `polygon`, which can be of any
+/// shape and have holes. Polygons are clipped by cutting both boundaries
+/// where they cross and keeping the pieces inside the other polygon.
+pub fn clip_to_polygon<T>(
+    tree: &RTree<Feature<T>>,
+    polygon: &Polygon<T>,
+) -> Result<Vec<Feature<T>>, ClipError>
+where
+    T: Float + RTreeNum,
+{
+    let envelope = match geometry_envelope(&Geometry::Polygon(polygon.clone())) {
+        Some(envelope) => envelope,
+        None => return Ok(vec![]),
+    };
+    let clipper = PolygonClipper::new(polygon);
+    let mut clipped = vec![];
+    for feature in tree.locate_in_envelope_intersecting(&envelope) {
+        clipped.extend(clip_feature(feature, &clipper)?);
+    }
+    Ok(clipped)
+}
+
+/// Clips one feature to `rect`, or returns `None` if nothing of it is left.
+pub fn clip_feature_to_rect<T>(
+    feature: &Feature<T>,
+    rect: &AABB<[T; 2]>,
+) -> Result<Option<Feature<T>>, ClipError>
+where
+    T: Float + RTreeNum,
+{
+    let clipper = RectClipper {
+        min: rect.lower(),
+        max: rect.upper(),
+    };
+    clip_feature(feature, &clipper)
+}
+
+/// Clips one feature to `polygon`, or returns `None` if nothing of it is
+/// left.
+pub fn clip_feature_to_polygon<T>(
+    feature: &Feature<T>,
+    polygon: &Polygon<T>,
+) -> Result<Option<Feature<T>>, ClipError>
+where
+    T: Float + RTreeNum,
+{
+    clip_feature(feature, &PolygonClipper::new(polygon))
+}
+
+fn clip_feature<T, C>(feature: &Feature<T>, clipper: &C) -> Result<Option<Feature<T>>, ClipError>
+where
+    T: Float,
+    C: Clipper<T>,
+{
+    let value = match clip_geometry(&feature.into(), clipper) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let clipped = create_feature(value, feature.id().cloned(), feature.properties().cloned());
+    // A part can survive clipping yet be too small to pass the checks done
+    // when converting, and then there is nothing left worth keeping.
+    match Feature::try_from(clipped) {
+        Ok(feature) => Ok(Some(feature)),
+        Err(GeoJsonConversionError::MalformedGeometry(_)) => Ok(None),
+        Err(error) => Err(ClipError::Conversion(error)),
+    }
+}
+
+fn clip_geometry<T, C>(geometry: &Geometry<T>, clipper: &C) -> Option<Value>
+where
+    T: Float,
+    C: Clipper<T>,
+{
+    match geometry {
+        Geometry::Point(p) => clip_point(p, clipper).map(Value::Point),
+        Geometry::MultiPoint(points) => non_empty(
+            points
+                .0
+                .iter()
+                .filter_map(|p| clip_point(p, clipper))
+                .collect(),
+            Value::MultiPoint,
+        ),
+        Geometry::Line(line) => {
+            let line_string = LineString(vec![line.start, line.end]);
+            clip_line_string(&line_string, clipper)
+        }
+        Geometry::LineString(line_string) => clip_line_string(line_string, clipper),
+        Geometry::MultiLineString(line_strings) => non_empty(
+            line_strings
+                .0
+                .iter()
+                .flat_map(|line_string| clip_line_parts(line_string, clipper))
+                .collect(),
+            Value::MultiLineString,
+        ),
+        Geometry::Polygon(polygon) => polygon_value(clip_polygon(polygon, clipper)),
+        Geometry::Rect(rect) => polygon_value(clip_polygon(&rect.to_polygon(), clipper)),
+        Geometry::Triangle(triangle) => {
+            polygon_value(clip_polygon(&triangle.to_polygon(), clipper))
+        }
+        Geometry::MultiPolygon(polygons) => non_empty(
+            polygons
+                .0
+                .iter()
+                .flat_map(|polygon| clip_polygon(polygon, clipper))
+                .collect(),
+            Value::MultiPolygon,
+        ),
+        Geometry::GeometryCollection(collection) => non_empty(
+            collection
+                .0
+                .iter()
+                .filter_map(|geometry| clip_geometry(geometry, clipper))
+                .map(geojson::Geometry::new)
+                .collect(),
+            Value::GeometryCollection,
+        ),
+    }
+}
+
+fn non_empty<V>(parts: Vec<V>, value: fn(Vec<V>) -> Value) -> Option<Value> {
+    if parts.is_empty() {
+        None
+    } else {
+        Some(value(parts))
+    }
+}
+
+fn clip_point<T, C>(point: &Point<T>, clipper: &C) -> Option<Vec<f64>>
+where
+    T: Float,
+    C: Clipper<T>,
+{
+    let position = [point.x(), point.y()];
+    if clipper.contains(position) {
+        Some(position_to_point_type(&position))
+    } else {
+        None
+    }
+}
+
+/// A line cut into several parts becomes a multi line string.
+fn clip_line_string<T, C>(line_string: &LineString<T>, clipper: &C) -> Option<Value>
+where
+    T: Float,
+    C: Clipper<T>,
+{
+    let mut parts = clip_line_parts(line_string, clipper);
+    if parts.len() == 1 {
+        parts.pop().map(Value::LineString)
+    } else {
+        non_empty(parts, Value::MultiLineString)
+    }
+}
+
+fn clip_line_parts<T, C>(line_string: &LineString<T>, clipper: &C) -> Vec<Vec<Vec<f64>>>
+where
+    T: Float,
+    C: Clipper<T>,
+{
+    clipper
+        .clip_line(&geo_line_string_to_positions(line_string))
+        .iter()
+        .map(|part| part.iter().map(position_to_point_type).collect())
+        .collect()
+}
+
+/// A polygon cut into several parts becomes a multi polygon.
+fn polygon_value(mut polygons: Vec<PolygonType>) -> Option<Value> {
+    if polygons.len() == 1 {
+        polygons.pop().map(Value::Polygon)
+    } else {
+        non_empty(polygons, Value::MultiPolygon)
+    }
+}
+
+fn clip_polygon<T, C>(polygon: &Polygon<T>, clipper: &C) -> Vec<PolygonType>
+where
+    T: Float,
+    C: Clipper<T>,
+{
+    clipper
+        .clip_polygon(&polygon_rings(polygon))
+        .iter()
+        .map(|rings| {
+            rings
+                .iter()
+                .map(|ring| ring.iter().map(position_to_point_type).collect())
+                .collect()
+        })
+        .collect()
+}
+
+fn polygon_rings<T>(polygon: &Polygon<T>) -> Rings<T>
+where
+    T: Float,
+{
+    std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .map(geo_line_string_to_positions)
+        .collect()
+}
+
+fn has_area<T>(ring: &[[T; 2]]) -> bool
+where
+    T: Float,
+{
+    ring.len() >= 4 && signed_area(ring) != T::zero()
+}
+
+/// Something geometries can be clipped to.
+trait Clipper<T> {
+    fn contains(&self, position: [T; 2]) -> bool;
+
+    fn clip_line(&self, line: &[[T; 2]]) -> Vec<Vec<[T; 2]>>;
+
+    /// The polygons making up the part inside of the polygon with `rings`,
+    /// the exterior first.
+    fn clip_polygon(&self, rings: &[Vec<[T; 2]>]) -> Vec<Rings<T>>;
+}
+
+struct RectClipper<T> {
+    min: [T; 2],
+    max: [T; 2],
+}
+
+impl<T> Clipper<T> for RectClipper<T>
+where
+    T: Float,
+{
+    fn contains(&self, position: [T; 2]) -> bool {
+        inside(position, self.min, self.max)
+    }
+
+    fn clip_line(&self, line: &[[T; 2]]) -> Vec<Vec<[T; 2]>> {
+        clip_line(line, self.min, self.max)
+    }
+
+    /// Each ring is clipped with Sutherland-Hodgman, and the polygon is
+    /// dropped when nothing is left of its exterior.
+    fn clip_polygon(&self, rings: &[Vec<[T; 2]>]) -> Vec<Rings<T>> {
+        let mut clipped = vec![];
+        for ring in rings {
+            let mut ring = clip_ring(ring, self.min, self.max);
+            // A corner on the clipping edge comes out twice.
+            ring.dedup();
+            if has_area(&ring) {
+                clipped.push(ring);
+            } else if clipped.is_empty() {
+                return vec![];
+            }
+        }
+        if clipped.is_empty() {
+            vec![]
+        } else {
+            vec![clipped]
+        }
+    }
+}
+
+struct PolygonClipper<'a, T>
+where
+    T: Float + RTreeNum,
+{
+    polygon: &'a Polygon<T>,
+    overlay: Overlay<T>,
+}
+
+impl<'a, T> PolygonClipper<'a, T>
+where
+    T: Float + RTreeNum,
+{
+    fn new(polygon: &'a Polygon<T>) -> Self {
+        PolygonClipper {
+            polygon,
+            overlay: Overlay::new(&polygon_rings(polygon)),
+        }
+    }
+
+    /// Every polygon ring edge, for splitting lines where they cross.
+    fn edges(&self) -> impl Iterator<Item = ([T; 2], [T; 2])> + '_ {
+        std::iter::once(self.polygon.exterior())
+            .chain(self.polygon.interiors())
+            .flat_map(|ring| ring.lines())
+            .map(|line| ([line.start.x, line.start.y], [line.end.x, line.end.y]))
+    }
+}
+
+impl<'a, T> Clipper<T> for PolygonClipper<'a, T>
+where
+    T: Float + RTreeNum,
+{
+    /// Positions on the boundary are inside.
+    fn contains(&self, position: [T; 2]) -> bool {
+        let point = Point::new(position[0], position[1]);
+        self.polygon.contains(&point)
+            || std::iter::once(self.polygon.exterior())
+                .chain(self.polygon.interiors())
+                .any(|ring| ring.contains(&point))
+    }
+
+    /// Each segment is split where it crosses the polygon's edges, and the
+    /// pieces whose middle is inside are kept.
+    fn clip_line(&self, line: &[[T; 2]]) -> Vec<Vec<[T; 2]>> {
+        let mut pieces = vec![];
+        for segment in line.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let mut cuts = vec![T::zero(), T::one()];
+            for edge in self.edges() {
+                cuts.extend(crossings(a, b, edge));
+            }
+            cuts.sort_by(|x, y| x.partial_cmp(y).expect("A cut is a number"));
+            cuts.dedup();
+
+            let at = |t: T| {
+                if t == T::zero() {
+                    a
+                } else if t == T::one() {
+                    b
+                } else {
+                    [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])]
+                }
+            };
+            let two = T::one() + T::one();
+            for pair in cuts.windows(2) {
+                if self.contains(at((pair[0] + pair[1]) / two)) {
+                    pieces.push((at(pair[0]), at(pair[1])));
+                }
+            }
+        }
+        join_pieces(pieces)
+    }
+
+    fn clip_polygon(&self, rings: &[Vec<[T; 2]>]) -> Vec<Rings<T>> {
+        self.overlay.intersection(rings)
+    }
+}
+
+/// Joins pieces of a line that follow on from each other into parts.
+fn join_pieces<T>(pieces: Vec<([T; 2], [T; 2])>) -> Vec<Vec<[T; 2]>>
+where
+    T: Float,
+{
+    let mut parts: Vec<Vec<[T; 2]>> = vec![];
+    for (start, end) in pieces {
+        if start == end {
+            continue;
+        }
+        match parts.last_mut() {
+            Some(part) if part.last() == Some(&start) => part.push(end),
+            _ => parts.push(vec![start, end]),
+        }
+    }
+    parts
+}
+
+/// Where along the segment from `a` to `b`, from 0 to 1, it crosses `edge`.
+/// Where they overlap, both ends of the overlap are given.
+fn crossings<T>(a: [T; 2], b: [T; 2], edge: ([T; 2], [T; 2])) -> Vec<T>
+where
+    T: Float,
+{
+    let (c, d) = edge;
+    let r = [b[0] - a[0], b[1] - a[1]];
+    let s = [d[0] - c[0], d[1] - c[1]];
+    let ac = [c[0] - a[0], c[1] - a[1]];
+    let denominator = cross(r, s);
+    let within = |t: &T| *t >= T::zero() && *t <= T::one();
+
+    if denominator == T::zero() {
+        if cross(ac, r) != T::zero() {
+            return vec![];
+        }
+        let length_2 = r[0] * r[0] + r[1] * r[1];
+        let along = |p: [T; 2]| ((p[0] - a[0]) * r[0] + (p[1] - a[1]) * r[1]) / length_2;
+        return vec![along(c), along(d)]
+            .into_iter()
+            .filter(within)
+            .collect();
+    }
+    let t = cross(ac, s) / denominator;
+    let u = cross(ac, r) / denominator;
+    if within(&t) && within(&u) {
+        vec![t]
+    } else {
+        vec![]
+    }
+}
+
+/// The parts of `line` inside the rectangle from `min` to `max`. A line that
+/// leaves and comes back is cut into several parts.
+pub(crate) fn clip_line<T>(line: &[[T; 2]], min: [T; 2], max: [T; 2]) -> Vec<Vec<[T; 2]>>
+where
+    T: Float,
+{
+    let mut parts: Vec<Vec<[T; 2]>> = vec![];
+    let mut current: Vec<[T; 2]> = vec![];
+    for segment in line.windows(2) {
+        let clipped = clip_segment(segment[0], segment[1], min, max);
+        // An unclipped start is copied exactly, so it equals the end of the
+        // previous segment while the line stays inside.
+        let continues = clipped.is_some_and(|(start, _)| current.last() == Some(&start));
+        if !continues {
+            if current.len() > 1 {
+                parts.push(current);
+            }
+            current = vec![];
+        }
+        if let Some((start, end)) = clipped {
+            if current.is_empty() {
+                current.push(start);
+            }
+            current.push(end);
+        }
+    }
+    if current.len() > 1 {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Liang-Barsky clipping of the segment from `a` to `b`.
+fn clip_segment<T>(a: [T; 2], b: [T; 2], min: [T; 2], max: [T; 2]) -> Option<([T; 2], [T; 2])>
+where
+    T: Float,
+{
+    let delta = [b[0] - a[0], b[1] - a[1]];
+    let mut enter = T::zero();
+    let mut exit = T::one();
+    for axis in 0..2 {
+        for (p, q) in [
+            (-delta[axis], a[axis] - min[axis]),
+            (delta[axis], max[axis] - a[axis]),
+        ] {
+            if p == T::zero() {
+                if q < T::zero() {
+                    return None;
+                }
+                continue;
+            }
+            let t = q / p;
+            if p < T::zero() {
+                if t > exit {
+                    return None;
+                }
+                enter = enter.max(t);
+            } else {
+                if t < enter {
+                    return None;
+                }
+                exit = exit.min(t);
+            }
+        }
+    }
+    let at = |t: T| {
+        if t == T::zero() {
+            a
+        } else if t == T::one() {
+            b
+        } else {
+            [a[0] + t * delta[0], a[1] + t * delta[1]]
+        }
+    };
+    Some((at(enter), at(exit)))
+}
+
+/// Sutherland-Hodgman clipping of a closed ring. The result is closed, or
+/// empty when the ring lies outside the rectangle.
+///
+/// Parts of the ring outside the rectangle become runs along its edges, which
+/// is fine for drawing but can leave zero width spikes.
+pub(crate) fn clip_ring<T>(ring: &[[T; 2]], min: [T; 2], max: [T; 2]) -> Vec<[T; 2]>
+where
+    T: Float,
+{
+    let mut output = ring.to_vec();
+    if output.len() > 1 && output.first() == output.last() {
+        output.pop();
+    }
+
+    let edges = [
+        (0, min[0], true),
+        (0, max[0], false),
+        (1, min[1], true),
+        (1, max[1], false),
+    ];
+    for (axis, bound, keep_above) in edges {
+        let input = std::mem::take(&mut output);
+        let is_inside = |p: &[T; 2]| {
+            if keep_above {
+                p[axis] >= bound
+            } else {
+                p[axis] <= bound
+            }
+        };
+        let mut previous = match input.last() {
+            Some(last) => *last,
+            None => break,
+        };
+        for current in input {
+            match (is_inside(&previous), is_inside(&current)) {
+                (true, true) => output.push(current),
+                (true, false) => output.push(intersect(previous, current, axis, bound)),
+                (false, true) => {
+                    output.push(intersect(previous, current, axis, bound));
+                    output.push(current);
+                }
+                (false, false) => {}
+            }
+            previous = current;
+        }
+    }
+
+    if let Some(first) = output.first().copied() {
+        output.push(first);
+    }
+    output
+}
+
+fn intersect<T>(a: [T; 2], b: [T; 2], axis: usize, bound: T) -> [T; 2]
+where
+    T: Float,
+{
+    let t = (bound - a[axis]) / (b[axis] - a[axis]);
+    let mut position = [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])];
+    position[axis] = bound;
+    position
+}
+
+pub(crate) fn inside<T>(position: [T; 2], min: [T; 2], max: [T; 2]) -> bool
+where
+    T: Float,
+{
+    position[0] >= min[0] && position[0] <= max[0] && position[1] >= min[1] && position[1] <= max[1]
+}
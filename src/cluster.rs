@@ -0,0 +1,235 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! DBSCAN clustering of the points in an `RTree`, using the tree to find the
+//! neighbours of each point.
+
+use crate::PointFeature;
+use geo::haversine_distance::HaversineDistance;
+use num_traits::{Float, FromPrimitive};
+use rstar::{RTree, RTreeNum, AABB};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, VecDeque};
+
+/// The mean radius of the earth in meters, as used by `geo`'s haversine
+/// distance.
+const MEAN_EARTH_RADIUS: f64 = 6_371_008.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClusterDistance {
+    /// Euclidean distance, in the units of the coordinates.
+    Planar,
+    /// Haversine distance in meters, with coordinates taken as longitude and
+    /// latitude in degrees.
+    Geodesic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dbscan<T = f64> {
+    /// How far apart points can be to be neighbours.
+    pub eps: T,
+    /// How many points, itself included, a point needs within `eps` to be
+    /// the core of a cluster.
+    pub min_points: usize,
+    pub distance: ClusterDistance,
+}
+
+/// The cluster a point was put in, numbered from 0, or `Noise` for a point
+/// that isn't near enough to any cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClusterLabel {
+    Noise,
+    Cluster(usize),
+}
+
+impl From<ClusterLabel> for JsonValue {
+    fn from(label: ClusterLabel) -> JsonValue {
+        match label {
+            ClusterLabel::Noise => JsonValue::Null,
+            ClusterLabel::Cluster(cluster) => cluster.into(),
+        }
+    }
+}
+
+/// Clusters the points in `tree`, returning each point with its label in the
+/// order of `RTree::iter`.
+///
+/// Clusters are numbered in the order their first point is met, so the labels
+/// are the same each time for the same tree.
+pub fn dbscan<'a, T>(
+    tree: &'a RTree<PointFeature<T>>,
+    dbscan: &Dbscan<T>,
+) -> Vec<(&'a PointFeature<T>, ClusterLabel)>
+where
+    T: Float + RTreeNum + FromPrimitive,
+{
+    let points: Vec<&PointFeature<T>> = tree.iter().collect();
+    let labels = cluster_labels(tree, &points, dbscan);
+    points.into_iter().zip(labels).collect()
+}
+
+/// Clusters the points in `tree` and writes each label into the properties
+/// under `key`, as the cluster number or null for noise. Returns how many
+/// clusters were found.
+pub fn label_clusters<T>(tree: &mut RTree<PointFeature<T>>, dbscan: &Dbscan<T>, key: &str) -> usize
+where
+    T: Float + RTreeNum + FromPrimitive,
+{
+    let labels = {
+        let points: Vec<&PointFeature<T>> = tree.iter().collect();
+        cluster_labels(tree, &points, dbscan)
+    };
+    let clusters = labels
+        .iter()
+        .filter_map(|label| match label {
+            ClusterLabel::Cluster(cluster) => Some(cluster + 1),
+            ClusterLabel::Noise => None,
+        })
+        .max()
+        .unwrap_or(0);
+
+    // `iter_mut` walks the tree in the same order as `iter`.
+    for (point, label) in tree.iter_mut().zip(labels) {
+        point
+            .properties
+            .get_or_insert_with(Default::default)
+            .insert(key.to_string(), label.into());
+    }
+    clusters
+}
+
+fn cluster_labels<T>(
+    tree: &RTree<PointFeature<T>>,
+    points: &[&PointFeature<T>],
+    dbscan: &Dbscan<T>,
+) -> Vec<ClusterLabel>
+where
+    T: Float + RTreeNum + FromPrimitive,
+{
+    let index: HashMap<*const PointFeature<T>, usize> = points
+        .iter()
+        .enumerate()
+        .map(|(i, point)| (*point as *const PointFeature<T>, i))
+        .collect();
+    let neighbours = |point: &PointFeature<T>| -> Vec<usize> {
+        neighbourhood(tree, point, dbscan)
+            .into_iter()
+            .map(|neighbour| index[&(neighbour as *const PointFeature<T>)])
+            .collect()
+    };
+
+    let mut labels: Vec<Option<ClusterLabel>> = vec![None; points.len()];
+    let mut clusters = 0;
+    for (i, point) in points.iter().enumerate() {
+        if labels[i].is_some() {
+            continue;
+        }
+        let seeds = neighbours(point);
+        if seeds.len() < dbscan.min_points {
+            labels[i] = Some(ClusterLabel::Noise);
+            continue;
+        }
+
+        let label = ClusterLabel::Cluster(clusters);
+        clusters += 1;
+        labels[i] = Some(label);
+        let mut queue: VecDeque<usize> = seeds.into();
+        while let Some(j) = queue.pop_front() {
+            match labels[j] {
+                // Noise near a core point is on the border of its cluster.
+                Some(ClusterLabel::Noise) => labels[j] = Some(label),
+                Some(ClusterLabel::Cluster(_)) => {}
+                None => {
+                    labels[j] = Some(label);
+                    let reached = neighbours(points[j]);
+                    if reached.len() >= dbscan.min_points {
+                        queue.extend(reached);
+                    }
+                }
+            }
+        }
+    }
+
+    labels
+        .into_iter()
+        .map(|label| label.expect("Every point is labelled"))
+        .collect()
+}
+
+/// The points within `eps` of `point`, itself included.
+fn neighbourhood<'a, T>(
+    tree: &'a RTree<PointFeature<T>>,
+    point: &PointFeature<T>,
+    dbscan: &Dbscan<T>,
+) -> Vec<&'a PointFeature<T>>
+where
+    T: Float + RTreeNum + FromPrimitive,
+{
+    let centre = point.geo_point();
+    match dbscan.distance {
+        ClusterDistance::Planar => tree
+            .locate_within_distance([centre.x(), centre.y()], dbscan.eps * dbscan.eps)
+            .collect(),
+        ClusterDistance::Geodesic => geodesic_envelopes(centre.x(), centre.y(), dbscan.eps)
+            .iter()
+            .flat_map(|envelope| tree.locate_in_envelope(envelope))
+            .filter(|other| centre.haversine_distance(&other.geo_point()) <= dbscan.eps)
+            .collect(),
+    }
+}
+
+/// Longitude and latitude boxes that hold every position within `eps` meters
+/// of `lon`, `lat`. There are two when the circle crosses the antimeridian.
+fn geodesic_envelopes<T>(lon: T, lat: T, eps: T) -> Vec<AABB<[T; 2]>>
+where
+    T: Float + RTreeNum + FromPrimitive,
+{
+    let degrees = |value: f64| T::from_f64(value).expect("Degrees fit in T");
+    let angle = eps / T::from_f64(MEAN_EARTH_RADIUS).expect("The earth's radius fits in T");
+    let min_lat = lat - angle.to_degrees();
+    let max_lat = lat + angle.to_degrees();
+    let whole_earth = |min_lat: T, max_lat: T| {
+        vec![AABB::from_corners(
+            [degrees(-180.0), min_lat.max(degrees(-90.0))],
+            [degrees(180.0), max_lat.min(degrees(90.0))],
+        )]
+    };
+
+    // Near a pole the circle can take in every longitude.
+    let sin_lon = angle.sin() / lat.to_radians().cos();
+    if max_lat >= degrees(90.0) || min_lat <= degrees(-90.0) || sin_lon >= T::one() {
+        return whole_earth(min_lat, max_lat);
+    }
+    let lon_delta = sin_lon.asin().to_degrees();
+    let min_lon = lon - lon_delta;
+    let max_lon = lon + lon_delta;
+
+    let mut envelopes = vec![AABB::from_corners(
+        [min_lon.max(degrees(-180.0)), min_lat],
+        [max_lon.min(degrees(180.0)), max_lat],
+    )];
+    if min_lon < degrees(-180.0) {
+        envelopes.push(AABB::from_corners(
+            [min_lon + degrees(360.0), min_lat],
+            [degrees(180.0), max_lat],
+        ));
+    }
+    if max_lon > degrees(180.0) {
+        envelopes.push(AABB::from_corners(
+            [degrees(-180.0), min_lat],
+            [max_lon - degrees(360.0), max_lat],
+        ));
+    }
+    envelopes
+}
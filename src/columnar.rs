@@ -0,0 +1,418 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `ColumnarTree` keeps the properties of its features column-wise in a
+//! `PropertyTable`, so each key is stored once rather than in every
+//! feature's object, and a property can be filtered on by scanning one
+//! typed column.
+
+use crate::{json::JsonObject, Feature};
+use num_traits::Float;
+use rstar::{PointDistance, RTree, RTreeNum, RTreeObject, AABB};
+use serde_json::{Number, Value as JsonValue};
+use std::{collections::HashMap, sync::Arc};
+
+/// The values of one property key, a row per feature. `None` is a null,
+/// or a feature that doesn't have the key, which the table tells apart.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    /// A key that is null in every feature that has it.
+    Null,
+    String(Vec<Option<String>>),
+    Number(Vec<Option<Number>>),
+    Bool(Vec<Option<bool>>),
+    /// A key with values of more than one type, or with arrays or objects.
+    Mixed(Vec<JsonValue>),
+}
+
+impl Column {
+    /// The value in `row`, which is null for a feature that doesn't have the
+    /// key.
+    pub fn get(&self, row: usize) -> JsonValue {
+        let value = match self {
+            Column::Null => None,
+            Column::String(values) => values[row].clone().map(JsonValue::String),
+            Column::Number(values) => values[row].clone().map(JsonValue::Number),
+            Column::Bool(values) => values[row].map(JsonValue::Bool),
+            Column::Mixed(values) => Some(values[row].clone()),
+        };
+        value.unwrap_or(JsonValue::Null)
+    }
+
+    /// The value in `row` without copying it.
+    pub fn value(&self, row: usize) -> ColumnValue<'_> {
+        let value = match self {
+            Column::Null => None,
+            Column::String(values) => values[row].as_deref().map(ColumnValue::String),
+            Column::Number(values) => values[row].as_ref().map(ColumnValue::Number),
+            Column::Bool(values) => values[row].map(ColumnValue::Bool),
+            Column::Mixed(values) => Some(match &values[row] {
+                JsonValue::Null => ColumnValue::Null,
+                JsonValue::String(s) => ColumnValue::String(s),
+                JsonValue::Number(n) => ColumnValue::Number(n),
+                JsonValue::Bool(b) => ColumnValue::Bool(*b),
+                value => ColumnValue::Other(value),
+            }),
+        };
+        value.unwrap_or(ColumnValue::Null)
+    }
+
+    /// Adds the value of the next row, changing the column to `Mixed` when
+    /// the value is of another type. `rows` is how many rows there are
+    /// already.
+    fn push(&mut self, rows: usize, value: JsonValue) {
+        match (&mut *self, value) {
+            (Column::Null, JsonValue::Null) => {}
+            (Column::String(values), JsonValue::String(s)) => values.push(Some(s)),
+            (Column::Number(values), JsonValue::Number(n)) => values.push(Some(n)),
+            (Column::Bool(values), JsonValue::Bool(b)) => values.push(Some(b)),
+            (Column::String(values), JsonValue::Null) => values.push(None),
+            (Column::Number(values), JsonValue::Null) => values.push(None),
+            (Column::Bool(values), JsonValue::Null) => values.push(None),
+            (Column::Mixed(values), value) => values.push(value),
+            (Column::Null, JsonValue::String(s)) => *self = Column::String(nulls_then(rows, s)),
+            (Column::Null, JsonValue::Number(n)) => *self = Column::Number(nulls_then(rows, n)),
+            (Column::Null, JsonValue::Bool(b)) => *self = Column::Bool(nulls_then(rows, b)),
+            (_, value) => {
+                let mut values: Vec<JsonValue> = (0..rows).map(|row| self.get(row)).collect();
+                values.push(value);
+                *self = Column::Mixed(values);
+            }
+        }
+    }
+}
+
+/// A value borrowed from a `Column`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnValue<'a> {
+    Null,
+    String(&'a str),
+    Number(&'a Number),
+    Bool(bool),
+    /// An array or object.
+    Other(&'a JsonValue),
+}
+
+/// A bit per row.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Bitmap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitmap {
+    /// `len` unset bits.
+    fn unset(len: usize) -> Self {
+        Bitmap {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    fn push(&mut self, bit: bool) {
+        if self.len % 64 == 0 {
+            self.words.push(0);
+        }
+        if bit {
+            self.words[self.len / 64] |= 1 << (self.len % 64);
+        }
+        self.len += 1;
+    }
+
+    fn get(&self, index: usize) -> bool {
+        index < self.len && self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+}
+
+fn nulls_then<V>(rows: usize, value: V) -> Vec<Option<V>> {
+    let mut values: Vec<Option<V>> = std::iter::repeat_with(|| None).take(rows).collect();
+    values.push(Some(value));
+    values
+}
+
+/// The properties of a set of features, stored as a column per key.
+///
+/// Rebuilding a row gives back the same object the feature had: a key the
+/// feature didn't have is left out rather than given as null, and a feature
+/// without properties gets `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PropertyTable {
+    keys: Vec<String>,
+    index: HashMap<String, usize>,
+    columns: Vec<Column>,
+    /// The rows that have each key, for each column.
+    present: Vec<Bitmap>,
+    /// The rows of features that have properties at all.
+    with_properties: Bitmap,
+    rows: usize,
+}
+
+impl PropertyTable {
+    /// Adds the properties of a feature as the next row, returning its index.
+    pub fn push(&mut self, properties: Option<JsonObject>) -> usize {
+        let row = self.rows;
+        self.with_properties.push(properties.is_some());
+        let mut properties = properties.unwrap_or_default();
+
+        for (column, key) in self.keys.iter().enumerate() {
+            let value = properties.remove(key);
+            self.present[column].push(value.is_some());
+            self.columns[column].push(row, value.unwrap_or(JsonValue::Null));
+        }
+        // Keys not seen before get a new column, absent from the rows before.
+        for (key, value) in properties {
+            let mut column = Column::Null;
+            column.push(row, value);
+            self.index.insert(key.clone(), self.keys.len());
+            self.keys.push(key);
+            self.columns.push(column);
+            let mut present = Bitmap::unset(row);
+            present.push(true);
+            self.present.push(present);
+        }
+
+        self.rows += 1;
+        row
+    }
+
+    /// Every key that any feature has, in the order they were first seen.
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub fn column(&self, key: &str) -> Option<&Column> {
+        self.index.get(key).map(|column| &self.columns[*column])
+    }
+
+    /// The value of `key` in `row`, or `None` if that feature doesn't have
+    /// the key.
+    pub fn get(&self, row: usize, key: &str) -> Option<JsonValue> {
+        let column = *self.index.get(key)?;
+        if !self.present[column].get(row) {
+            return None;
+        }
+        Some(self.columns[column].get(row))
+    }
+
+    /// The rows that have `key` with a value that `predicate` accepts, found
+    /// by scanning that one column.
+    pub fn rows_where<P>(&self, key: &str, predicate: P) -> Vec<usize>
+    where
+        P: Fn(ColumnValue<'_>) -> bool,
+    {
+        let matches = self.matches(key, predicate);
+        (0..self.rows).filter(|row| matches.get(*row)).collect()
+    }
+
+    fn matches<P>(&self, key: &str, predicate: P) -> Bitmap
+    where
+        P: Fn(ColumnValue<'_>) -> bool,
+    {
+        let column = match self.index.get(key) {
+            Some(column) => *column,
+            None => return Bitmap::unset(self.rows),
+        };
+        let present = &self.present[column];
+        let values = &self.columns[column];
+        let mut matches = Bitmap::default();
+        for row in 0..self.rows {
+            matches.push(present.get(row) && predicate(values.value(row)));
+        }
+        matches
+    }
+
+    /// Rebuilds the properties of the feature in `row`.
+    pub fn properties(&self, row: usize) -> Option<JsonObject> {
+        if !self.with_properties.get(row) {
+            return None;
+        }
+        Some(
+            self.keys
+                .iter()
+                .zip(&self.columns)
+                .zip(&self.present)
+                .filter(|(_, present)| present.get(row))
+                .map(|((key, column), _)| (key.clone(), column.get(row)))
+                .collect(),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+}
+
+/// An entry of a `ColumnarTree`: a feature without its properties, and the
+/// row of the table they are in.
+#[derive(Debug, Clone)]
+pub struct PropertyRow<T = f64> {
+    feature: Feature<T>,
+    row: usize,
+    table: Arc<PropertyTable>,
+}
+
+impl<T> PropertyRow<T> {
+    /// The feature, whose `properties` are `None`.
+    pub fn feature(&self) -> &Feature<T> {
+        &self.feature
+    }
+
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn property(&self, key: &str) -> Option<JsonValue> {
+        self.table.get(self.row, key)
+    }
+
+    pub fn properties(&self) -> Option<JsonObject> {
+        self.table.properties(self.row)
+    }
+}
+
+impl<T> RTreeObject for PropertyRow<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.feature.envelope()
+    }
+}
+
+impl<T> PointDistance for PropertyRow<T>
+where
+    T: Float + RTreeNum,
+{
+    fn distance_2(&self, point: &[T; 2]) -> T {
+        self.feature.distance_2(point)
+    }
+}
+
+impl<T> From<PropertyRow<T>> for geojson::Feature
+where
+    T: Float,
+{
+    fn from(row: PropertyRow<T>) -> geojson::Feature {
+        let properties = row.properties();
+        let mut feature: geojson::Feature = row.feature.into();
+        feature.properties = properties;
+        feature
+    }
+}
+
+impl<T> From<&PropertyRow<T>> for geojson::Feature
+where
+    T: Float,
+{
+    fn from(row: &PropertyRow<T>) -> geojson::Feature {
+        row.clone().into()
+    }
+}
+
+/// An `RTree` of `PropertyRow`s, sharing one `PropertyTable`.
+pub struct ColumnarTree<T = f64>
+where
+    T: Float + RTreeNum,
+{
+    tree: RTree<PropertyRow<T>>,
+    table: Arc<PropertyTable>,
+}
+
+impl<T> ColumnarTree<T>
+where
+    T: Float + RTreeNum,
+{
+    /// Moves the properties of each feature into the table and bulk loads
+    /// the features.
+    pub fn bulk_load(features: Vec<Feature<T>>) -> Self {
+        let mut table = PropertyTable::default();
+        let features: Vec<(Feature<T>, usize)> = features
+            .into_iter()
+            .map(|mut feature| {
+                let row = table.push(take_properties(&mut feature));
+                (feature, row)
+            })
+            .collect();
+        let table = Arc::new(table);
+        let rows = features
+            .into_iter()
+            .map(|(feature, row)| PropertyRow {
+                feature,
+                row,
+                table: Arc::clone(&table),
+            })
+            .collect();
+        ColumnarTree {
+            tree: RTree::bulk_load(rows),
+            table,
+        }
+    }
+
+    pub fn tree(&self) -> &RTree<PropertyRow<T>> {
+        &self.tree
+    }
+
+    pub fn table(&self) -> &PropertyTable {
+        &self.table
+    }
+
+    /// The rows that have `key` with a value that `predicate` accepts. The
+    /// column is scanned once, then the tree.
+    pub fn filter<'a, P>(
+        &'a self,
+        key: &str,
+        predicate: P,
+    ) -> impl Iterator<Item = &'a PropertyRow<T>> + 'a
+    where
+        P: Fn(ColumnValue<'_>) -> bool,
+    {
+        let matches = self.table.matches(key, predicate);
+        self.tree.iter().filter(move |row| matches.get(row.row))
+    }
+
+    /// The rows whose envelope intersects `envelope` and that have `key`
+    /// with a value that `predicate` accepts.
+    pub fn locate_in_envelope_intersecting_where<'a, P>(
+        &'a self,
+        envelope: &AABB<[T; 2]>,
+        key: &str,
+        predicate: P,
+    ) -> impl Iterator<Item = &'a PropertyRow<T>> + 'a
+    where
+        P: Fn(ColumnValue<'_>) -> bool,
+    {
+        let matches = self.table.matches(key, predicate);
+        self.tree
+            .locate_in_envelope_intersecting(envelope)
+            .filter(move |row| matches.get(row.row))
+    }
+}
+
+fn take_properties<T>(feature: &mut Feature<T>) -> Option<JsonObject> {
+    match feature {
+        Feature::Point(f) => f.properties.take(),
+        Feature::Polygon(f) => f.properties.take(),
+        Feature::LineString(f) => f.properties.take(),
+        Feature::MultiPoint(f) => f.properties.take(),
+        Feature::MultiLineString(f) => f.properties.take(),
+        Feature::MultiPolygon(f) => f.properties.take(),
+        Feature::GeometryCollection(f) => f.properties.take(),
+    }
+}
@@ -13,22 +13,32 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::error::GeoJsonConversionError;
 use geo::{Coordinate, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon};
 use geojson::{Geometry, LineStringType, PointType, PolygonType, Value};
 use num_traits::Float;
-use std::iter::FromIterator;
 
-pub fn create_point_type<T>(point: &Point<T>) -> PointType
+pub fn create_point_type<T>(point: &Point<T>) -> Result<PointType, GeoJsonConversionError>
 where
     T: Float,
 {
-    let x: f64 = point.x().to_f64().unwrap();
-    let y: f64 = point.y().to_f64().unwrap();
-
-    vec![x, y]
+    let x = point
+        .x()
+        .to_f64()
+        .filter(|x| x.is_finite())
+        .ok_or(GeoJsonConversionError::NonFiniteCoordinate(None))?;
+    let y = point
+        .y()
+        .to_f64()
+        .filter(|y| y.is_finite())
+        .ok_or(GeoJsonConversionError::NonFiniteCoordinate(None))?;
+
+    Ok(vec![x, y])
 }
 
-pub fn create_line_string_type<T>(line_string: &LineString<T>) -> LineStringType
+pub fn create_line_string_type<T>(
+    line_string: &LineString<T>,
+) -> Result<LineStringType, GeoJsonConversionError>
 where
     T: Float,
 {
@@ -40,7 +50,7 @@ where
 
 pub fn create_multi_line_string_type<T>(
     multi_line_string: &MultiLineString<T>,
-) -> Vec<LineStringType>
+) -> Result<Vec<LineStringType>, GeoJsonConversionError>
 where
     T: Float,
 {
@@ -51,140 +61,182 @@ where
         .collect()
 }
 
-pub fn create_polygon_type<T>(polygon: &Polygon<T>) -> PolygonType
+pub fn create_polygon_type<T>(polygon: &Polygon<T>) -> Result<PolygonType, GeoJsonConversionError>
 where
     T: Float,
 {
-    let mut coords = vec![polygon
-        .exterior()
-        .points_iter()
-        .map(|point| create_point_type(&point))
-        .collect()];
+    let mut coords = vec![create_line_string_type(polygon.exterior())?];
 
-    coords.extend(
-        polygon
-            .interiors()
-            .iter()
-            .map(|line_string| create_line_string_type(&line_string)),
-    );
+    for line_string in polygon.interiors() {
+        coords.push(create_line_string_type(line_string)?);
+    }
 
-    coords
+    Ok(coords)
 }
 
-pub fn create_multi_polygon_type<T>(multi_polygon: &MultiPolygon<T>) -> Vec<PolygonType>
+pub fn create_multi_polygon_type<T>(
+    multi_polygon: &MultiPolygon<T>,
+) -> Result<Vec<PolygonType>, GeoJsonConversionError>
 where
     T: Float,
 {
     multi_polygon
         .0
         .iter()
-        .map(|polygon| create_polygon_type(&polygon))
+        .map(|polygon| create_polygon_type(polygon))
         .collect()
 }
 
-#[allow(clippy::ptr_arg)]
-pub fn create_geo_coordinate<T>(point_type: &PointType) -> Coordinate<T>
+fn create_ordinate<S, T>(point_type: &[S], index: usize) -> Result<T, GeoJsonConversionError>
 where
+    S: Float,
     T: Float,
 {
-    Coordinate {
-        x: T::from(point_type[0]).unwrap(),
-        y: T::from(point_type[1]).unwrap(),
-    }
+    let value = point_type
+        .get(index)
+        .ok_or(GeoJsonConversionError::MalformedGeometry(None))?;
+
+    T::from(*value)
+        .filter(|v| value.is_finite() && v.is_finite())
+        .ok_or(GeoJsonConversionError::NonFiniteCoordinate(None))
 }
 
-#[allow(clippy::ptr_arg)]
-pub fn create_geo_point<T>(point_type: &PointType) -> Point<T>
+/// Converts a position between float types, such as the `f64` coordinates of
+/// `GeoJson` and the `T` coordinates stored on a feature.
+pub fn convert_position<S, T>(position: &[S]) -> Result<Vec<T>, GeoJsonConversionError>
 where
+    S: Float,
     T: Float,
 {
-    Point::new(
-        T::from(point_type[0]).unwrap(),
-        T::from(point_type[1]).unwrap(),
-    )
+    (0..position.len())
+        .map(|index| create_ordinate(position, index))
+        .collect()
 }
 
-pub fn create_geo_multi_point<T>(multipoint_type: &[PointType]) -> MultiPoint<T>
+pub fn create_geo_coordinate<S, T>(
+    point_type: &[S],
+) -> Result<Coordinate<T>, GeoJsonConversionError>
 where
+    S: Float,
     T: Float,
 {
-    multipoint_type.iter().map(create_geo_point).collect()
+    Ok(Coordinate {
+        x: create_ordinate(point_type, 0)?,
+        y: create_ordinate(point_type, 1)?,
+    })
 }
 
-#[allow(clippy::ptr_arg)]
-pub fn create_geo_line_string<T>(line_type: &LineStringType) -> LineString<T>
+pub fn create_geo_point<S, T>(point_type: &[S]) -> Result<Point<T>, GeoJsonConversionError>
 where
+    S: Float,
     T: Float,
 {
-    LineString(
-        line_type
-            .iter()
-            .map(|point_type| create_geo_coordinate(point_type))
-            .collect(),
-    )
+    create_geo_coordinate(point_type).map(Point)
 }
 
-pub fn create_geo_multi_line_string<T>(multi_line_type: &[LineStringType]) -> MultiLineString<T>
+pub fn create_geo_multi_point<S, T>(
+    multipoint_type: &[Vec<S>],
+) -> Result<MultiPoint<T>, GeoJsonConversionError>
 where
+    S: Float,
     T: Float,
 {
-    MultiLineString(
-        multi_line_type
-            .iter()
-            .map(|point_type| create_geo_line_string(&point_type))
-            .collect(),
-    )
+    multipoint_type
+        .iter()
+        .map(|point_type| create_geo_point(point_type))
+        .collect::<Result<Vec<_>, _>>()
+        .map(MultiPoint)
 }
 
-#[allow(clippy::ptr_arg)]
-pub fn create_geo_polygon<T>(polygon_type: &PolygonType) -> Polygon<T>
+pub fn create_geo_line_string<S, T>(
+    line_type: &[Vec<S>],
+) -> Result<LineString<T>, GeoJsonConversionError>
 where
+    S: Float,
     T: Float,
 {
-    let exterior = polygon_type
-        .get(0)
-        .map(|e| create_geo_line_string(e))
-        .unwrap_or_else(|| create_geo_line_string(&vec![]));
+    line_type
+        .iter()
+        .map(|point_type| create_geo_coordinate(point_type))
+        .collect::<Result<Vec<_>, _>>()
+        .map(LineString)
+}
+
+pub fn create_geo_multi_line_string<S, T>(
+    multi_line_type: &[Vec<Vec<S>>],
+) -> Result<MultiLineString<T>, GeoJsonConversionError>
+where
+    S: Float,
+    T: Float,
+{
+    multi_line_type
+        .iter()
+        .map(|line_type| create_geo_line_string(line_type))
+        .collect::<Result<Vec<_>, _>>()
+        .map(MultiLineString)
+}
+
+pub fn create_geo_polygon<S, T>(
+    polygon_type: &[Vec<Vec<S>>],
+) -> Result<Polygon<T>, GeoJsonConversionError>
+where
+    S: Float,
+    T: Float,
+{
+    let exterior = match polygon_type.first() {
+        Some(e) => create_geo_line_string(e)?,
+        None => LineString(vec![]),
+    };
 
     let interiors = if polygon_type.len() < 2 {
         vec![]
     } else {
         polygon_type[1..]
             .iter()
-            .map(|line_string_type| create_geo_line_string(line_string_type))
-            .collect()
+            .map(|line_type| create_geo_line_string(line_type))
+            .collect::<Result<Vec<_>, _>>()?
     };
 
-    Polygon::new(exterior, interiors)
+    Ok(Polygon::new(exterior, interiors))
 }
 
-pub fn create_geo_multi_polygon<T>(multi_polygon_type: &[PolygonType]) -> MultiPolygon<T>
+pub fn create_geo_multi_polygon<S, T>(
+    multi_polygon_type: &[Vec<Vec<Vec<S>>>],
+) -> Result<MultiPolygon<T>, GeoJsonConversionError>
 where
+    S: Float,
     T: Float,
 {
-    MultiPolygon(
-        multi_polygon_type
-            .iter()
-            .map(|polygon_type| create_geo_polygon(&polygon_type))
-            .collect(),
-    )
+    multi_polygon_type
+        .iter()
+        .map(|polygon_type| create_geo_polygon(polygon_type))
+        .collect::<Result<Vec<_>, _>>()
+        .map(MultiPolygon)
 }
 
-pub fn create_geo_geometry_collection<T>(geometries: &[Geometry]) -> geo::GeometryCollection<T>
+pub fn create_geo_geometry_collection<T>(
+    geometries: &[Geometry],
+) -> Result<geo::GeometryCollection<T>, GeoJsonConversionError>
 where
     T: Float,
 {
-    geo::GeometryCollection::from_iter(geometries.iter().map(|g| match &g.value {
-        Value::Point(p) => geo::Geometry::Point(create_geo_point(&p)),
-        Value::LineString(l) => geo::Geometry::LineString(create_geo_line_string(&l)),
-        Value::Polygon(p) => geo::Geometry::Polygon(create_geo_polygon(&p)),
-        Value::MultiPoint(p) => geo::Geometry::MultiPoint(create_geo_multi_point(&p)),
-        Value::MultiPolygon(p) => geo::Geometry::MultiPolygon(create_geo_multi_polygon(&p)),
-        Value::MultiLineString(p) => {
-            geo::Geometry::MultiLineString(create_geo_multi_line_string(&p))
-        }
-        Value::GeometryCollection(g) => {
-            geo::Geometry::GeometryCollection(create_geo_geometry_collection(&g))
-        }
-    }))
+    geometries
+        .iter()
+        .map(|g| {
+            Ok(match &g.value {
+                Value::Point(p) => geo::Geometry::Point(create_geo_point(p)?),
+                Value::LineString(l) => geo::Geometry::LineString(create_geo_line_string(l)?),
+                Value::Polygon(p) => geo::Geometry::Polygon(create_geo_polygon(p)?),
+                Value::MultiPoint(p) => geo::Geometry::MultiPoint(create_geo_multi_point(p)?),
+                Value::MultiPolygon(p) => geo::Geometry::MultiPolygon(create_geo_multi_polygon(p)?),
+                Value::MultiLineString(p) => {
+                    geo::Geometry::MultiLineString(create_geo_multi_line_string(p)?)
+                }
+                Value::GeometryCollection(g) => {
+                    geo::Geometry::GeometryCollection(create_geo_geometry_collection(g)?)
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(geo::GeometryCollection)
 }
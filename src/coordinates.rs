@@ -0,0 +1,277 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Flat coordinate storage for the feature types, so a position costs two
+//! scalars instead of a heap allocated `Vec<f64>`.
+
+use crate::error::GeoJsonConversionError;
+use geo::{Coordinate, LineString, Polygon};
+use geojson::{LineStringType, PointType};
+use num_traits::Float;
+
+pub(crate) fn create_position<T>(point_type: &[f64]) -> Result<[T; 2], GeoJsonConversionError>
+where
+    T: Float,
+{
+    let coordinate = crate::conversion::create_geo_coordinate(point_type)?;
+    Ok([coordinate.x, coordinate.y])
+}
+
+pub(crate) fn create_positions<T>(
+    line_type: &[PointType],
+) -> Result<Vec<[T; 2]>, GeoJsonConversionError>
+where
+    T: Float,
+{
+    line_type
+        .iter()
+        .map(|point_type| create_position(point_type))
+        .collect()
+}
+
+pub(crate) fn position_to_point_type<T>(position: &[T; 2]) -> PointType
+where
+    T: Float,
+{
+    position
+        .iter()
+        .map(|c| {
+            c.to_f64()
+                .expect("A stored coordinate can be widened to f64")
+        })
+        .collect()
+}
+
+pub(crate) fn positions_to_line_string_type<T>(positions: &[[T; 2]]) -> LineStringType
+where
+    T: Float,
+{
+    positions.iter().map(position_to_point_type).collect()
+}
+
+pub(crate) fn positions_to_geo_line_string<T>(positions: &[[T; 2]]) -> LineString<T>
+where
+    T: Float,
+{
+    LineString(
+        positions
+            .iter()
+            .map(|p| Coordinate { x: p[0], y: p[1] })
+            .collect(),
+    )
+}
+
+pub(crate) fn geo_line_string_to_positions<T>(line_string: &LineString<T>) -> Vec<[T; 2]>
+where
+    T: Float,
+{
+    line_string.0.iter().map(|c| [c.x, c.y]).collect()
+}
+
+pub(crate) fn rings_to_geo_polygon<'a, T, I>(mut rings: I) -> Polygon<T>
+where
+    T: Float + 'a,
+    I: Iterator<Item = &'a [[T; 2]]>,
+{
+    let exterior = rings
+        .next()
+        .map(positions_to_geo_line_string)
+        .unwrap_or_else(|| LineString(vec![]));
+
+    Polygon::new(exterior, rings.map(positions_to_geo_line_string).collect())
+}
+
+/// The squared distance from `point` to the polygon made of `rings`, the
+/// first being the exterior, measured on the stored positions. It's zero
+/// inside the polygon and on its boundary.
+pub(crate) fn polygon_distance_2<'a, T, I>(rings: I, point: &[T; 2]) -> T
+where
+    T: Float + 'a,
+    I: Iterator<Item = &'a [[T; 2]]>,
+{
+    let mut inside = false;
+    let mut nearest = T::infinity();
+    for (index, ring) in rings.enumerate() {
+        let contains = ring_contains(ring, point);
+        if index == 0 {
+            inside = contains;
+        } else if contains {
+            inside = false;
+        }
+        nearest = nearest.min(line_distance_2(ring, point));
+    }
+    if inside {
+        T::zero()
+    } else {
+        nearest
+    }
+}
+
+/// The squared distance from `point` to the nearest segment of `positions`.
+pub(crate) fn line_distance_2<T>(positions: &[[T; 2]], point: &[T; 2]) -> T
+where
+    T: Float,
+{
+    if let [position] = positions {
+        return segment_distance_2(position, position, point);
+    }
+    positions
+        .windows(2)
+        .map(|segment| segment_distance_2(&segment[0], &segment[1], point))
+        .fold(T::infinity(), T::min)
+}
+
+fn segment_distance_2<T>(start: &[T; 2], end: &[T; 2], point: &[T; 2]) -> T
+where
+    T: Float,
+{
+    let (dx, dy) = (end[0] - start[0], end[1] - start[1]);
+    let length_2 = dx * dx + dy * dy;
+    let along = if length_2 > T::zero() {
+        (((point[0] - start[0]) * dx + (point[1] - start[1]) * dy) / length_2)
+            .max(T::zero())
+            .min(T::one())
+    } else {
+        T::zero()
+    };
+    let (x, y) = (
+        start[0] + along * dx - point[0],
+        start[1] + along * dy - point[1],
+    );
+    x * x + y * y
+}
+
+/// Whether `point` is inside `ring`, by counting the edges a ray to the
+/// right of it crosses.
+pub(crate) fn ring_contains<T>(ring: &[[T; 2]], point: &[T; 2]) -> bool
+where
+    T: Float,
+{
+    let mut inside = false;
+    for segment in ring.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        if (a[1] > point[1]) != (b[1] > point[1]) {
+            let x = a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]);
+            if point[0] < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Twice the signed area of a closed ring, positive when it is counter
+/// clockwise.
+pub(crate) fn signed_area<T>(ring: &[[T; 2]]) -> T
+where
+    T: Float,
+{
+    ring.windows(2).fold(T::zero(), |area, pair| {
+        area + pair[0][0] * pair[1][1] - pair[1][0] * pair[0][1]
+    })
+}
+
+pub(crate) fn cross<T>(a: [T; 2], b: [T; 2]) -> T
+where
+    T: Float,
+{
+    a[0] * b[1] - a[1] * b[0]
+}
+
+/// Positive when `p` is left of the line from `start` to `end`.
+pub(crate) fn side<T>(start: [T; 2], end: [T; 2], p: [T; 2]) -> T
+where
+    T: Float,
+{
+    cross(
+        [end[0] - start[0], end[1] - start[1]],
+        [p[0] - start[0], p[1] - start[1]],
+    )
+}
+
+/// The positions of several parts, such as the rings of a polygon or the
+/// lines of a multi line string, stored end to end in one `Vec`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Parts<T> {
+    positions: Vec<[T; 2]>,
+    ends: Vec<usize>,
+}
+
+impl<T> Parts<T>
+where
+    T: Float,
+{
+    pub(crate) fn new() -> Self {
+        Parts {
+            positions: vec![],
+            ends: vec![],
+        }
+    }
+
+    pub(crate) fn push(&mut self, part: &[PointType]) -> Result<(), GeoJsonConversionError> {
+        for point_type in part {
+            self.positions.push(create_position(point_type)?);
+        }
+        self.ends.push(self.positions.len());
+        Ok(())
+    }
+
+    pub(crate) fn try_from_nested<'a, I>(parts: I) -> Result<Self, GeoJsonConversionError>
+    where
+        I: IntoIterator<Item = &'a LineStringType>,
+    {
+        let mut flat = Parts::new();
+        for part in parts {
+            flat.push(part)?;
+        }
+        Ok(flat)
+    }
+
+    pub(crate) fn from_geo_line_strings<'a, I>(line_strings: I) -> Self
+    where
+        T: 'a,
+        I: IntoIterator<Item = &'a LineString<T>>,
+    {
+        let mut flat = Parts::new();
+        for line_string in line_strings {
+            flat.positions
+                .extend(line_string.0.iter().map(|c| [c.x, c.y]));
+            flat.ends.push(flat.positions.len());
+        }
+        flat
+    }
+
+    /// The number of parts.
+    pub(crate) fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub(crate) fn get(&self, index: usize) -> &[[T; 2]] {
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        &self.positions[start..self.ends[index]]
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &[[T; 2]]> {
+        (0..self.len()).map(move |index| self.get(index))
+    }
+
+    /// The parts in `start..end`.
+    pub(crate) fn range(&self, start: usize, end: usize) -> impl Iterator<Item = &[[T; 2]]> {
+        (start..end).map(move |index| self.get(index))
+    }
+
+    pub(crate) fn to_nested(&self) -> Vec<LineStringType> {
+        self.iter().map(positions_to_line_string_type).collect()
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading `PointFeature`s straight from CSV with x and y columns, such as
+//! longitude and latitude, with the `csv` feature.
+
+use crate::{
+    error::CsvError, generic::create_feature, json::JsonObject, point_feature::PointFeature,
+};
+use csv::{Reader, StringRecord, StringRecordsIntoIter};
+use geojson::Value;
+use num_traits::Float;
+use serde_json::{Number, Value as JsonValue};
+use std::{convert::TryFrom, io::Read, marker::PhantomData};
+
+/// The rows of a CSV as `PointFeature`s, each carrying the columns other than
+/// x and y as its properties.
+///
+/// A row that can't be read or converted is returned as an `Err`, so bad
+/// rows can be skipped or reported by line number, or the whole load can stop
+/// at the first with `collect::<Result<Vec<_>, _>>()`.
+pub struct CsvPoints<T, R> {
+    records: StringRecordsIntoIter<R>,
+    headers: StringRecord,
+    x_index: usize,
+    y_index: usize,
+    scalar: PhantomData<T>,
+}
+
+impl<T, R> CsvPoints<T, R>
+where
+    R: Read,
+{
+    /// Reads comma separated CSV with a header row from `reader`.
+    pub fn new(reader: R, x_column: &str, y_column: &str) -> Result<Self, CsvError> {
+        Self::from_csv_reader(Reader::from_reader(reader), x_column, y_column)
+    }
+
+    /// Reads from a `csv::Reader`, which can be set up with a
+    /// `csv::ReaderBuilder` for other delimiters or quoting. The reader must
+    /// have headers.
+    pub fn from_csv_reader(
+        mut reader: Reader<R>,
+        x_column: &str,
+        y_column: &str,
+    ) -> Result<Self, CsvError> {
+        let headers = reader.headers()?.clone();
+        let column_index = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| CsvError::MissingColumn(name.into()))
+        };
+        let x_index = column_index(x_column)?;
+        let y_index = column_index(y_column)?;
+
+        Ok(CsvPoints {
+            records: reader.into_records(),
+            headers,
+            x_index,
+            y_index,
+            scalar: PhantomData,
+        })
+    }
+
+    fn create_point(&self, record: StringRecord) -> Result<PointFeature<T>, CsvError>
+    where
+        T: Float,
+    {
+        let line = record.position().map_or(0, |position| position.line());
+        let coordinate = |index: usize| {
+            let value = record.get(index).unwrap_or_default();
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| CsvError::InvalidCoordinate {
+                    line,
+                    column: self.headers[index].into(),
+                    value: value.into(),
+                })
+        };
+        let position = vec![coordinate(self.x_index)?, coordinate(self.y_index)?];
+
+        let properties: JsonObject = self
+            .headers
+            .iter()
+            .zip(record.iter())
+            .enumerate()
+            .filter(|(index, _)| *index != self.x_index && *index != self.y_index)
+            .map(|(_, (header, value))| (header.to_string(), infer_value(value)))
+            .collect();
+
+        let feature = create_feature(Value::Point(position), None, Some(properties));
+        PointFeature::try_from(feature).map_err(|error| CsvError::Conversion { line, error })
+    }
+}
+
+impl<T, R> Iterator for CsvPoints<T, R>
+where
+    T: Float,
+    R: Read,
+{
+    type Item = Result<PointFeature<T>, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.records.next()?;
+        Some(
+            record
+                .map_err(CsvError::from)
+                .and_then(|r| self.create_point(r)),
+        )
+    }
+}
+
+/// Infers the JSON type of a CSV value: an empty value is `null`, `true` and
+/// `false` are booleans, and anything that parses as a finite number is a
+/// number, except when it has a leading zero like a ZIP or FIPS code.
+/// Everything else is kept as a string.
+fn infer_value(value: &str) -> JsonValue {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return JsonValue::Null;
+    }
+    match trimmed {
+        "true" => return JsonValue::Bool(true),
+        "false" => return JsonValue::Bool(false),
+        _ => {}
+    }
+
+    let digits = trimmed.trim_start_matches('-');
+    let leading_zero = digits.len() > 1
+        && digits.starts_with('0')
+        && !digits.starts_with("0.")
+        && !digits.starts_with("0e")
+        && !digits.starts_with("0E");
+    if !leading_zero {
+        if let Ok(integer) = trimmed.parse::<i64>() {
+            return JsonValue::Number(integer.into());
+        }
+        if let Some(number) = trimmed.parse::<f64>().ok().and_then(Number::from_f64) {
+            return JsonValue::Number(number);
+        }
+    }
+    JsonValue::String(value.into())
+}
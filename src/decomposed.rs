@@ -0,0 +1,269 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `DecomposedTree` indexes the parts of each `Feature` separately, so a
+//! `MultiPolygon` of islands or a long `LineString` isn't a candidate for
+//! every query that touches its overall bounding box.
+
+use crate::{
+    conversion::create_geo_geometry_collection,
+    coordinates::{line_distance_2, polygon_distance_2},
+    distance::{geometry_distance, geometry_envelope},
+    Feature,
+};
+use num_traits::Float;
+use rstar::{PointDistance, RTree, RTreeNum, RTreeObject, AABB};
+use std::{collections::HashSet, sync::Arc};
+
+/// Where a part is in its parent's coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PartIndex {
+    /// A point of a `Point` or `MultiPoint`.
+    Point(usize),
+    /// Positions `start..end` of a line, the line being 0 for a `LineString`.
+    Segments {
+        line: usize,
+        start: usize,
+        end: usize,
+    },
+    /// A polygon, 0 for a `Polygon`.
+    Polygon(usize),
+    /// A member geometry of a `GeometryCollection`.
+    Member(usize),
+}
+
+/// One part of a `Feature`, such as one polygon of a `MultiPolygon` or a
+/// run of segments of a `LineString`, that points back to the whole feature.
+///
+/// The part is kept as indices into the parent's coordinates rather than a
+/// copy of them.
+#[derive(Clone, Debug)]
+pub struct FeaturePart<T = f64>
+where
+    T: Float + RTreeNum,
+{
+    parent: Arc<Feature<T>>,
+    index: PartIndex,
+    envelope: AABB<[T; 2]>,
+}
+
+impl<T> FeaturePart<T>
+where
+    T: Float + RTreeNum,
+{
+    /// The feature this is a part of.
+    pub fn parent(&self) -> &Arc<Feature<T>> {
+        &self.parent
+    }
+}
+
+impl<T> RTreeObject for FeaturePart<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<T> PointDistance for FeaturePart<T>
+where
+    T: Float + RTreeNum,
+{
+    fn distance_2(&self, point: &[T; 2]) -> T {
+        match (self.parent.as_ref(), self.index) {
+            (Feature::Point(p), PartIndex::Point(_)) => {
+                line_distance_2(std::slice::from_ref(p.position()), point)
+            }
+            (Feature::MultiPoint(p), PartIndex::Point(index)) => {
+                line_distance_2(&p.positions()[index..=index], point)
+            }
+            (Feature::LineString(l), PartIndex::Segments { start, end, .. }) => {
+                line_distance_2(&l.positions()[start..end], point)
+            }
+            (Feature::MultiLineString(l), PartIndex::Segments { line, start, end }) => {
+                line_distance_2(&l.line_at(line)[start..end], point)
+            }
+            (Feature::Polygon(p), PartIndex::Polygon(_)) => polygon_distance_2(p.rings(), point),
+            (Feature::MultiPolygon(p), PartIndex::Polygon(index)) => {
+                polygon_distance_2(p.rings(index), point)
+            }
+            (Feature::GeometryCollection(g), PartIndex::Member(index)) => {
+                let member = member_geometry(g.geometries(), index);
+                geometry_distance(&member, &geo::Point::from(*point).into()).powi(2)
+            }
+            _ => unreachable!("A part's index matches the kind of its parent"),
+        }
+    }
+}
+
+fn member_geometry<T>(geometries: &[geojson::Geometry], index: usize) -> geo::Geometry<T>
+where
+    T: Float,
+{
+    let mut member = create_geo_geometry_collection(&geometries[index..=index])
+        .expect("GeometryCollectionFeature coordinates were checked on conversion");
+    member.0.remove(0)
+}
+
+/// Splits a `Feature` into the parts that are indexed by `DecomposedTree`.
+///
+/// Each polygon and point of a multi geometry becomes its own part, lines are
+/// cut into runs of at most `segments_per_part` segments and each member of a
+/// geometry collection is a part.
+pub fn decompose<T>(feature: Feature<T>, segments_per_part: usize) -> Vec<FeaturePart<T>>
+where
+    T: Float + RTreeNum,
+{
+    let parts: Vec<(PartIndex, AABB<[T; 2]>)> = match &feature {
+        Feature::Point(p) => vec![(PartIndex::Point(0), AABB::from_point(*p.position()))],
+        Feature::LineString(l) => split_line(0, l.positions(), segments_per_part),
+        Feature::Polygon(p) => p
+            .rings()
+            .next()
+            .map(|exterior| (PartIndex::Polygon(0), AABB::from_points(exterior)))
+            .into_iter()
+            .collect(),
+        Feature::MultiPoint(p) => p
+            .positions()
+            .iter()
+            .enumerate()
+            .map(|(index, position)| (PartIndex::Point(index), AABB::from_point(*position)))
+            .collect(),
+        Feature::MultiLineString(l) => l
+            .line_positions()
+            .enumerate()
+            .flat_map(|(line, positions)| split_line(line, positions, segments_per_part))
+            .collect(),
+        Feature::MultiPolygon(p) => (0..p.len())
+            .filter_map(|index| {
+                p.rings(index)
+                    .next()
+                    .map(|exterior| (PartIndex::Polygon(index), AABB::from_points(exterior)))
+            })
+            .collect(),
+        Feature::GeometryCollection(g) => (0..g.geometries().len())
+            .filter_map(|index| {
+                geometry_envelope(&member_geometry(g.geometries(), index))
+                    .map(|envelope| (PartIndex::Member(index), envelope))
+            })
+            .collect(),
+    };
+
+    let parent = Arc::new(feature);
+    parts
+        .into_iter()
+        .map(|(index, envelope)| FeaturePart {
+            parent: Arc::clone(&parent),
+            index,
+            envelope,
+        })
+        .collect()
+}
+
+fn split_line<T>(
+    line: usize,
+    positions: &[[T; 2]],
+    segments_per_part: usize,
+) -> Vec<(PartIndex, AABB<[T; 2]>)>
+where
+    T: Float + RTreeNum,
+{
+    if positions.is_empty() {
+        return vec![];
+    }
+    let segments_per_part = segments_per_part.max(1);
+    let segments = positions.len().saturating_sub(1).max(1);
+    (0..segments)
+        .step_by(segments_per_part)
+        .map(|start| {
+            let end = (start + segments_per_part + 1).min(positions.len());
+            let index = PartIndex::Segments { line, start, end };
+            (index, AABB::from_points(&positions[start..end]))
+        })
+        .collect()
+}
+
+/// An `RTree` of `FeaturePart`s whose queries return each matching `Feature`
+/// once.
+pub struct DecomposedTree<T = f64>
+where
+    T: Float + RTreeNum,
+{
+    tree: RTree<FeaturePart<T>>,
+}
+
+impl<T> DecomposedTree<T>
+where
+    T: Float + RTreeNum,
+{
+    /// Decomposes each feature, see `decompose`, and bulk loads the parts.
+    pub fn bulk_load(features: Vec<Feature<T>>, segments_per_part: usize) -> Self {
+        let parts = features
+            .into_iter()
+            .flat_map(|feature| decompose(feature, segments_per_part))
+            .collect();
+        DecomposedTree {
+            tree: RTree::bulk_load(parts),
+        }
+    }
+
+    /// The underlying tree of parts.
+    pub fn tree(&self) -> &RTree<FeaturePart<T>> {
+        &self.tree
+    }
+
+    /// The features that have a part intersecting `envelope`.
+    pub fn locate_in_envelope_intersecting<'a>(
+        &'a self,
+        envelope: &AABB<[T; 2]>,
+    ) -> impl Iterator<Item = &'a Feature<T>> + 'a {
+        dedup_parents(self.tree.locate_in_envelope_intersecting(envelope))
+    }
+
+    /// The features that contain `point`.
+    pub fn locate_all_at_point<'a>(
+        &'a self,
+        point: &[T; 2],
+    ) -> impl Iterator<Item = &'a Feature<T>> + 'a {
+        dedup_parents(self.tree.locate_all_at_point(point))
+    }
+
+    /// The features in order of increasing distance from `point`.
+    pub fn nearest_neighbor_iter<'a>(
+        &'a self,
+        point: &[T; 2],
+    ) -> impl Iterator<Item = &'a Feature<T>> + 'a {
+        dedup_parents(self.tree.nearest_neighbor_iter(point))
+    }
+
+    pub fn nearest_neighbor(&self, point: &[T; 2]) -> Option<&Feature<T>> {
+        self.tree
+            .nearest_neighbor(point)
+            .map(|part| part.parent.as_ref())
+    }
+}
+
+fn dedup_parents<'a, T, I>(parts: I) -> impl Iterator<Item = &'a Feature<T>> + 'a
+where
+    T: Float + RTreeNum + 'a,
+    I: Iterator<Item = &'a FeaturePart<T>> + 'a,
+{
+    let mut seen = HashSet::new();
+    parts
+        .filter(move |part| seen.insert(Arc::as_ptr(&part.parent)))
+        .map(|part| part.parent.as_ref())
+}
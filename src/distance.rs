@@ -0,0 +1,173 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Euclidean distance between any two `geo::Geometry`s.
+
+use geo::{
+    contains::Contains, euclidean_distance::EuclideanDistance, intersects::Intersects, Coordinate,
+    Geometry, Line, LineString, Point, Polygon,
+};
+use num_traits::Float;
+use rstar::{RTreeNum, AABB};
+
+/// The points, segments and polygons that make up a geometry.
+struct Primitives<T>
+where
+    T: Float,
+{
+    points: Vec<Point<T>>,
+    lines: Vec<Line<T>>,
+    polygons: Vec<Polygon<T>>,
+    /// One position of every point, line string and ring, used to find out
+    /// if a geometry lies inside a polygon without crossing its boundary.
+    anchors: Vec<Coordinate<T>>,
+}
+
+impl<T> Primitives<T>
+where
+    T: Float,
+{
+    fn new(geometry: &Geometry<T>) -> Self {
+        let mut primitives = Primitives {
+            points: vec![],
+            lines: vec![],
+            polygons: vec![],
+            anchors: vec![],
+        };
+        primitives.add(geometry);
+        primitives
+    }
+
+    fn add(&mut self, geometry: &Geometry<T>) {
+        match geometry {
+            Geometry::Point(p) => self.add_point(*p),
+            Geometry::Line(l) => self.add_line_string(&LineString(vec![l.start, l.end])),
+            Geometry::LineString(l) => self.add_line_string(l),
+            Geometry::Polygon(p) => self.add_polygon(p),
+            Geometry::MultiPoint(p) => p.0.iter().for_each(|p| self.add_point(*p)),
+            Geometry::MultiLineString(l) => l.0.iter().for_each(|l| self.add_line_string(l)),
+            Geometry::MultiPolygon(p) => p.0.iter().for_each(|p| self.add_polygon(p)),
+            Geometry::GeometryCollection(g) => g.0.iter().for_each(|g| self.add(g)),
+            Geometry::Rect(r) => self.add_polygon(&(*r).into()),
+            Geometry::Triangle(t) => {
+                self.add_polygon(&Polygon::new(LineString(vec![t.0, t.1, t.2, t.0]), vec![]))
+            }
+        }
+    }
+
+    fn add_point(&mut self, point: Point<T>) {
+        self.points.push(point);
+        self.anchors.push(point.0);
+    }
+
+    fn add_line_string(&mut self, line_string: &LineString<T>) {
+        match line_string.0.len() {
+            0 => {}
+            1 => self.add_point(Point(line_string.0[0])),
+            _ => {
+                self.lines.extend(line_string.lines());
+                self.anchors.push(line_string.0[0]);
+            }
+        }
+    }
+
+    fn add_polygon(&mut self, polygon: &Polygon<T>) {
+        self.add_line_string(polygon.exterior());
+        polygon
+            .interiors()
+            .iter()
+            .for_each(|interior| self.add_line_string(interior));
+        self.polygons.push(polygon.clone());
+    }
+
+    fn contains_any(&self, other: &Primitives<T>) -> bool {
+        self.polygons
+            .iter()
+            .any(|polygon| other.anchors.iter().any(|c| polygon.contains(c)))
+    }
+}
+
+/// The bounding box of `geometry`, or `None` if it has no positions.
+pub(crate) fn geometry_envelope<T>(geometry: &Geometry<T>) -> Option<AABB<[T; 2]>>
+where
+    T: Float + RTreeNum,
+{
+    let primitives = Primitives::new(geometry);
+    let positions: Vec<[T; 2]> = primitives
+        .points
+        .iter()
+        .map(|p| [p.x(), p.y()])
+        .chain(
+            primitives
+                .lines
+                .iter()
+                .flat_map(|l| vec![[l.start.x, l.start.y], [l.end.x, l.end.y]]),
+        )
+        .collect();
+    if positions.is_empty() {
+        None
+    } else {
+        Some(AABB::from_points(positions.iter()))
+    }
+}
+
+/// The smallest distance between `a` and `b`, which is zero when they touch,
+/// cross or one lies inside a polygon of the other.
+pub fn geometry_distance<T>(a: &Geometry<T>, b: &Geometry<T>) -> T
+where
+    T: Float,
+{
+    let a = Primitives::new(a);
+    let b = Primitives::new(b);
+
+    if a.contains_any(&b) || b.contains_any(&a) {
+        return T::zero();
+    }
+    if a.lines
+        .iter()
+        .any(|line| b.lines.iter().any(|other| line.intersects(other)))
+    {
+        return T::zero();
+    }
+
+    let point_to_point = a.points.iter().flat_map(|p| {
+        b.points
+            .iter()
+            .map(move |other| p.euclidean_distance(other))
+    });
+    let point_to_line = a
+        .points
+        .iter()
+        .flat_map(|p| b.lines.iter().map(move |line| p.euclidean_distance(line)))
+        .chain(
+            b.points
+                .iter()
+                .flat_map(|p| a.lines.iter().map(move |line| p.euclidean_distance(line))),
+        );
+    // Segments that don't cross are closest at one of their end points.
+    let line_to_line = a.lines.iter().flat_map(|line| {
+        b.lines.iter().map(move |other| {
+            line.start_point()
+                .euclidean_distance(other)
+                .min(line.end_point().euclidean_distance(other))
+                .min(other.start_point().euclidean_distance(line))
+                .min(other.end_point().euclidean_distance(line))
+        })
+    });
+
+    point_to_point
+        .chain(point_to_line)
+        .chain(line_to_line)
+        .fold(T::infinity(), T::min)
+}
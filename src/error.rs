@@ -27,4 +27,131 @@ pub enum GeoJsonConversionError {
     IncorrectGeometryValue(String),
     /// The Geometry is malformed, such as a Point has 5 f64s
     MalformedGeometry(Option<Id>),
+    /// A position in the Geometry has a NaN or infinite coordinate, or a
+    /// coordinate that can't be represented in the target float type.
+    NonFiniteCoordinate(Option<Id>),
+    /// The text isn't valid Well-Known Text.
+    InvalidWkt(String),
+    /// The bytes aren't valid Well-Known Binary.
+    InvalidWkb(String),
+    /// A property is missing or can't be read into the type of the field
+    /// of a `#[derive(GeoJsonFeature)]` struct, or the field can't be
+    /// written as a property.
+    InvalidProperty {
+        id: Option<Id>,
+        key: String,
+        message: String,
+    },
+}
+
+/// An error that results from reading `PointFeature`s from CSV.
+#[cfg(feature = "csv")]
+#[derive(Debug)]
+pub enum CsvError {
+    /// The CSV couldn't be read or a row couldn't be parsed.
+    Csv(csv::Error),
+    /// The header has no column with this name.
+    MissingColumn(String),
+    /// The x or y value of the row starting on `line` isn't a number.
+    InvalidCoordinate {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    /// The point of the row starting on `line` couldn't be converted, such as
+    /// when a coordinate doesn't fit in the target float type.
+    Conversion {
+        line: u64,
+        error: GeoJsonConversionError,
+    },
+}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for CsvError {
+    fn from(error: csv::Error) -> CsvError {
+        CsvError::Csv(error)
+    }
+}
+
+/// An error that results from clipping features.
+#[derive(Debug)]
+pub enum ClipError {
+    /// The clipped geometry couldn't be converted back into a feature, such
+    /// as when a computed position doesn't fit in the float type.
+    Conversion(GeoJsonConversionError),
+}
+
+/// An error that results from aggregating features into cells.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AggregateError {
+    /// The extent has a NaN or infinite bound, or the cells covering it
+    /// would have coordinates that aren't finite.
+    NonFiniteExtent,
+    /// Covering the extent takes more than `aggregate::MAX_CELLS` cells.
+    TooManyCells,
+}
+
+/// An error that results from reading features from, or writing them to,
+/// GeoArrow record batches.
+#[cfg(feature = "arrow")]
+#[derive(Debug)]
+pub enum GeoArrowError {
+    /// Arrow couldn't build or read a batch or IPC file.
+    Arrow(arrow_schema::ArrowError),
+    Io(std::io::Error),
+    /// The batch has no column with this name.
+    MissingColumn(String),
+    /// The geometry column isn't WKB, WKT or a two dimensional GeoArrow
+    /// geometry.
+    UnsupportedGeometry(String),
+    /// An attribute column whose type can't be read as a JSON property.
+    UnsupportedColumn {
+        column: String,
+        data_type: String,
+    },
+    /// The feature in `row` of the batch couldn't be converted.
+    Conversion {
+        row: usize,
+        error: GeoJsonConversionError,
+    },
+}
+
+#[cfg(feature = "arrow")]
+impl From<arrow_schema::ArrowError> for GeoArrowError {
+    fn from(error: arrow_schema::ArrowError) -> GeoArrowError {
+        GeoArrowError::Arrow(error)
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl From<std::io::Error> for GeoArrowError {
+    fn from(error: std::io::Error) -> GeoArrowError {
+        GeoArrowError::Io(error)
+    }
+}
+
+/// An error that results from reading features from an `AsyncRead`.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub enum GeoJsonStreamError {
+    /// Reading failed, which ends the stream.
+    Io(std::io::Error),
+    /// The document isn't a FeatureCollection, or it ends part way through,
+    /// which ends the stream.
+    InvalidDocument(String),
+    /// Feature number `feature`, counting from 0, isn't a valid GeoJSON
+    /// feature.
+    InvalidFeature { feature: usize, message: String },
+    /// Feature number `feature`, counting from 0, couldn't be converted.
+    Conversion {
+        feature: usize,
+        error: GeoJsonConversionError,
+    },
+}
+
+#[cfg(feature = "tokio")]
+impl From<std::io::Error> for GeoJsonStreamError {
+    fn from(error: std::io::Error) -> GeoJsonStreamError {
+        GeoJsonStreamError::Io(error)
+    }
 }
@@ -12,21 +12,47 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::error::GeoJsonConversionError;
-use geojson::Bbox;
-use rstar::AABB;
+use crate::{error::GeoJsonConversionError, json::JsonObject};
+use geojson::{feature::Id, Bbox, Geometry, Value};
+use num_traits::Float;
+use rstar::{RTreeNum, AABB};
 
-pub(crate) trait GenericFeature<U, G> {
+/// The steps of converting a `geojson::Feature` into a feature type `U` whose
+/// geometry is of GeoJSON type `G`, such as `PolygonType`.
+///
+/// Every feature type in this crate is converted this way, and a custom
+/// feature type can be too, for example one that only keeps some of the
+/// properties. `try_from` takes the geometry out of the feature, checks it,
+/// uses the bbox of the feature or else computes one, and then creates the
+/// feature type. Along with `IndexableFeature`, this is what
+/// `impl_rtree_feature!` needs to give a custom type `TryFrom`,
+/// `RTreeObject` and `PointDistance`.
+pub trait GenericFeature<U, G> {
+    /// Takes the geometry out of the feature, failing with
+    /// `MissingGeometry` or `IncorrectGeometryValue` when there isn't one
+    /// of the right type.
     fn take_geometry_type(feature: &mut geojson::Feature) -> Result<G, GeoJsonConversionError>;
 
+    /// The hook for validating the geometry, before anything is computed
+    /// from it. `check_position` checks a single position.
     fn check_geometry(
         geometry: &G,
         feature: &geojson::Feature,
     ) -> Result<(), GeoJsonConversionError>;
 
+    /// The hook for computing `[min x, min y, max x, max y]` of a checked
+    /// geometry, only called when the feature has no bbox of its own. The
+    /// `create_geo_*` functions in `conversion` give `geo` types whose
+    /// `bounding_rect` can be used.
     fn compute_bbox(feature: &mut geojson::Feature, geometry: &G) -> Bbox;
 
-    fn create_self(feature: geojson::Feature, bbox: Bbox, geometry: G) -> U;
+    /// Creates the feature type from what is left of the feature. `create_bbox`
+    /// converts the bbox to the stored scalar type.
+    fn create_self(
+        feature: geojson::Feature,
+        bbox: Bbox,
+        geometry: G,
+    ) -> Result<U, GeoJsonConversionError>;
 
     fn try_from(mut feature: geojson::Feature) -> Result<U, GeoJsonConversionError> {
         let geometry = Self::take_geometry_type(&mut feature)?;
@@ -38,26 +64,136 @@ pub(crate) trait GenericFeature<U, G> {
             .take()
             .unwrap_or_else(|| Self::compute_bbox(&mut feature, &geometry));
 
-        Ok(Self::create_self(feature, bbox, geometry))
+        Self::create_self(feature, bbox, geometry)
     }
 }
 
-pub(crate) trait GetBbox<'a> {
-    fn bbox(&'a self) -> &'a Bbox;
-
-    fn envelope(&'a self) -> AABB<[f64; 2]> {
-        AABB::from_points(
-            [
-                [
-                    *self.bbox().get(0).expect("A bounding box has 4 values"),
-                    *self.bbox().get(1).expect("A bounding box has 4 values"),
-                ],
-                [
-                    *self.bbox().get(2).expect("A bounding box has 4 values"),
-                    *self.bbox().get(3).expect("A bounding box has 4 values"),
-                ],
-            ]
-            .iter(),
-        )
+/// Checks that a position has exactly two coordinates and that both are finite
+/// when represented as `T`.
+pub fn check_position<T>(
+    position: &[f64],
+    feature: &geojson::Feature,
+) -> Result<(), GeoJsonConversionError>
+where
+    T: Float,
+{
+    if position.len() != 2 {
+        let id = feature.id.clone();
+        return Err(GeoJsonConversionError::MalformedGeometry(id));
+    }
+    if !position.iter().all(|c| is_finite_as::<T>(*c)) {
+        let id = feature.id.clone();
+        return Err(GeoJsonConversionError::NonFiniteCoordinate(id));
+    }
+    Ok(())
+}
+
+/// Converts a `GeoJson` bounding box into the `[min x, min y, max x, max y]`
+/// that is stored on each feature.
+pub fn create_bbox<T>(
+    bbox: &[f64],
+    feature: &geojson::Feature,
+) -> Result<[T; 4], GeoJsonConversionError>
+where
+    T: Float,
+{
+    if bbox.len() != 4 {
+        let id = feature.id.clone();
+        return Err(GeoJsonConversionError::MalformedGeometry(id));
+    }
+    let mut converted = [T::zero(); 4];
+    for (c, value) in converted.iter_mut().zip(bbox) {
+        *c = T::from(*value)
+            .filter(|_| is_finite_as::<T>(*value))
+            .ok_or_else(|| GeoJsonConversionError::NonFiniteCoordinate(feature.id.clone()))?;
+    }
+    Ok(converted)
+}
+
+fn is_finite_as<T>(value: f64) -> bool
+where
+    T: Float,
+{
+    value.is_finite() && T::from(value).is_some_and(|v| v.is_finite())
+}
+
+pub(crate) trait GetBbox<T> {
+    fn bbox(&self) -> &[T; 4];
+
+    fn envelope(&self) -> AABB<[T; 2]>
+    where
+        T: RTreeNum,
+    {
+        let bbox = self.bbox();
+        AABB::from_corners([bbox[0], bbox[1]], [bbox[2], bbox[3]])
+    }
+}
+
+/// Implements `TryFrom<geojson::Feature>`, `RTreeObject` and `PointDistance`
+/// for a custom feature type, given its GeoJSON geometry type and optionally
+/// its scalar type, which is `f64` otherwise.
+///
+/// The type has to implement `GenericFeature` for the conversion, and
+/// `IndexableFeature` for its bbox and geometry. The distance to a point is
+/// measured with `distance::geometry_distance`.
+///
+/// ```ignore
+/// impl_rtree_feature!(Parcel, PolygonType);
+/// impl_rtree_feature!(SmallParcel, PolygonType, f32);
+/// ```
+#[macro_export]
+macro_rules! impl_rtree_feature {
+    ($feature:ty, $geometry:ty) => {
+        $crate::impl_rtree_feature!($feature, $geometry, f64);
+    };
+    ($feature:ty, $geometry:ty, $scalar:ty) => {
+        impl ::std::convert::TryFrom<$crate::__private::geojson::Feature> for $feature {
+            type Error = $crate::GeoJsonConversionError;
+
+            fn try_from(
+                feature: $crate::__private::geojson::Feature,
+            ) -> ::std::result::Result<$feature, $crate::GeoJsonConversionError> {
+                <$feature as $crate::generic::GenericFeature<$feature, $geometry>>::try_from(
+                    feature,
+                )
+            }
+        }
+
+        impl $crate::__private::rstar::RTreeObject for $feature {
+            type Envelope = $crate::__private::rstar::AABB<[$scalar; 2]>;
+
+            fn envelope(&self) -> Self::Envelope {
+                let bbox = <$feature as $crate::IndexableFeature<$scalar>>::bbox(self);
+                $crate::__private::rstar::AABB::from_corners([bbox[0], bbox[1]], [bbox[2], bbox[3]])
+            }
+        }
+
+        impl $crate::__private::rstar::PointDistance for $feature {
+            fn distance_2(&self, point: &[$scalar; 2]) -> $scalar {
+                let distance = $crate::distance::geometry_distance(
+                    &<$feature as $crate::IndexableFeature<$scalar>>::geo_geometry(self),
+                    &$crate::__private::geo::Geometry::Point($crate::__private::geo::Point::new(
+                        point[0], point[1],
+                    )),
+                );
+                distance * distance
+            }
+        }
+    };
+}
+
+/// A `geojson::Feature` for a geometry that came from somewhere other than
+/// GeoJSON, so it can be checked and converted by `TryFrom`.
+pub(crate) fn create_feature(
+    value: Value,
+    id: Option<Id>,
+    properties: Option<JsonObject>,
+) -> geojson::Feature {
+    geojson::Feature {
+        bbox: None,
+        geometry: Some(Geometry::new(value)),
+        id,
+        properties,
+        foreign_members: None,
     }
 }
@@ -0,0 +1,471 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading `Feature`s from GeoArrow record batches and writing them back,
+//! in memory or as Arrow IPC files. Needs the `arrow` feature.
+//!
+//! The geometry column can be WKB, WKT, or one of the GeoArrow native point,
+//! line string, polygon and multi geometry encodings with interleaved or
+//! separated x and y coordinates. The other columns become properties.
+//! Features are written with WKB geometries, as GeoArrow has no native
+//! encoding for a column of mixed geometry types.
+
+use crate::{
+    error::{GeoArrowError, GeoJsonConversionError},
+    json::JsonObject,
+    well_known::{wkb_to_value, wkt_to_value},
+    Feature, WellKnown,
+};
+use arrow_array::{
+    cast::AsArray,
+    types::{
+        Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type,
+        UInt32Type, UInt64Type, UInt8Type,
+    },
+    Array, ArrayRef, BinaryArray, BooleanArray, Float64Array, Int64Array, NullArray, RecordBatch,
+    StringArray, UInt64Array,
+};
+use arrow_ipc::{reader::FileReader, writer::FileWriter};
+use arrow_schema::{DataType, Field, Schema};
+use geojson::{PointType, PolygonType, Value};
+use num_traits::Float;
+use rstar::{RTree, RTreeNum};
+use serde_json::{Number, Value as JsonValue};
+use std::{
+    collections::{BTreeSet, HashMap},
+    convert::TryFrom,
+    fs::File,
+    path::Path,
+    sync::Arc,
+};
+
+/// The name of the geometry column written by `to_record_batch`.
+pub const GEOMETRY_COLUMN: &str = "geometry";
+
+/// The field metadata key Arrow uses for the name of an extension type, such
+/// as `geoarrow.polygon`.
+pub const EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+
+/// Reads a feature from each row of `batch`.
+///
+/// A column without a GeoArrow extension name is read as WKB if it is binary
+/// and as WKT if it is a string. A null geometry fails to convert with
+/// `MissingGeometry`. Null attributes are given as null properties.
+pub fn read_record_batch<T>(
+    batch: &RecordBatch,
+    geometry_column: &str,
+) -> Result<Vec<Feature<T>>, GeoArrowError>
+where
+    T: Float,
+{
+    let schema = batch.schema();
+    let (geometry_index, field) = schema
+        .column_with_name(geometry_column)
+        .ok_or_else(|| GeoArrowError::MissingColumn(geometry_column.into()))?;
+    let encoding = Encoding::new(field)?;
+    let geometries = batch.column(geometry_index);
+
+    let attributes: Vec<(&String, &ArrayRef)> = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .enumerate()
+        .filter(|(index, _)| *index != geometry_index)
+        .map(|(_, (field, column))| (field.name(), column))
+        .collect();
+    for (name, column) in &attributes {
+        if !is_attribute_type(column.data_type()) {
+            return Err(GeoArrowError::UnsupportedColumn {
+                column: name.to_string(),
+                data_type: column.data_type().to_string(),
+            });
+        }
+    }
+
+    (0..batch.num_rows())
+        .map(|row| {
+            let properties: JsonObject = attributes
+                .iter()
+                .map(|(name, column)| (name.to_string(), attribute_value(column.as_ref(), row)))
+                .collect();
+            let value = encoding
+                .value(geometries.as_ref(), row)
+                .map_err(|error| GeoArrowError::Conversion { row, error })?;
+            let feature = geojson::Feature {
+                bbox: None,
+                geometry: value.map(geojson::Geometry::new),
+                id: None,
+                properties: Some(properties),
+                foreign_members: None,
+            };
+            Feature::try_from(feature).map_err(|error| GeoArrowError::Conversion { row, error })
+        })
+        .collect()
+}
+
+/// Reads the features of every batch and bulk loads them into an `RTree`.
+pub fn bulk_load<T>(
+    batches: &[RecordBatch],
+    geometry_column: &str,
+) -> Result<RTree<Feature<T>>, GeoArrowError>
+where
+    T: Float + RTreeNum,
+{
+    let mut features = vec![];
+    for batch in batches {
+        features.extend(read_record_batch(batch, geometry_column)?);
+    }
+    Ok(RTree::bulk_load(features))
+}
+
+/// Writes features, such as the results of a query, to a record batch with
+/// a `geoarrow.wkb` geometry column and a column per property key.
+///
+/// Each property column gets the narrowest type that holds all of its
+/// values: boolean, 64 bit integer, 64 bit float or string. A column with
+/// values of more than one type, or with arrays or objects, is written as
+/// strings of JSON. Ids aren't written, and nor is a property named
+/// `geometry`.
+pub fn to_record_batch<'a, T, I>(features: I) -> Result<RecordBatch, GeoArrowError>
+where
+    T: Float + 'a,
+    I: IntoIterator<Item = &'a Feature<T>>,
+{
+    let features: Vec<&Feature<T>> = features.into_iter().collect();
+    let extension: HashMap<String, String> =
+        vec![(EXTENSION_NAME_KEY.to_string(), "geoarrow.wkb".to_string())]
+            .into_iter()
+            .collect();
+    let mut fields =
+        vec![Field::new(GEOMETRY_COLUMN, DataType::Binary, false).with_metadata(extension)];
+    let mut columns: Vec<ArrayRef> = vec![Arc::new(BinaryArray::from_iter_values(
+        features.iter().map(|feature| feature.to_wkb()),
+    ))];
+
+    let keys: BTreeSet<&String> = features
+        .iter()
+        .filter_map(|feature| feature.properties())
+        .flat_map(|properties| properties.keys())
+        .filter(|key| key.as_str() != GEOMETRY_COLUMN)
+        .collect();
+    for key in keys {
+        let values: Vec<Option<&JsonValue>> = features
+            .iter()
+            .map(|feature| {
+                feature
+                    .properties()
+                    .and_then(|properties| properties.get(key))
+                    .filter(|value| !value.is_null())
+            })
+            .collect();
+        let column = attribute_column(&values);
+        fields.push(Field::new(key.as_str(), column.data_type().clone(), true));
+        columns.push(column);
+    }
+
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+/// Reads the features of every batch of an Arrow IPC file.
+pub fn read_ipc_file<T, P>(path: P, geometry_column: &str) -> Result<Vec<Feature<T>>, GeoArrowError>
+where
+    T: Float,
+    P: AsRef<Path>,
+{
+    let reader = FileReader::try_new(File::open(path)?, None)?;
+    let mut features = vec![];
+    for batch in reader {
+        features.extend(read_record_batch(&batch?, geometry_column)?);
+    }
+    Ok(features)
+}
+
+/// Writes features to an Arrow IPC file as one batch, see `to_record_batch`.
+pub fn write_ipc_file<'a, T, I, P>(path: P, features: I) -> Result<(), GeoArrowError>
+where
+    T: Float + 'a,
+    I: IntoIterator<Item = &'a Feature<T>>,
+    P: AsRef<Path>,
+{
+    let batch = to_record_batch(features)?;
+    let mut writer = FileWriter::try_new(File::create(path)?, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Wkb,
+    Wkt,
+    Point,
+    LineString,
+    Polygon,
+    MultiPoint,
+    MultiLineString,
+    MultiPolygon,
+}
+
+impl Encoding {
+    fn new(field: &Field) -> Result<Encoding, GeoArrowError> {
+        let data_type = field.data_type();
+        let encoding = match field.metadata().get(EXTENSION_NAME_KEY).map(String::as_str) {
+            Some("geoarrow.point") => Encoding::Point,
+            Some("geoarrow.linestring") => Encoding::LineString,
+            Some("geoarrow.polygon") => Encoding::Polygon,
+            Some("geoarrow.multipoint") => Encoding::MultiPoint,
+            Some("geoarrow.multilinestring") => Encoding::MultiLineString,
+            Some("geoarrow.multipolygon") => Encoding::MultiPolygon,
+            Some("geoarrow.wkb") | None if is_binary(data_type) => Encoding::Wkb,
+            Some("geoarrow.wkt") | None if is_string(data_type) => Encoding::Wkt,
+            _ => return Err(GeoArrowError::UnsupportedGeometry(field.name().clone())),
+        };
+        let supported = match encoding {
+            Encoding::Wkb => is_binary(data_type),
+            Encoding::Wkt => is_string(data_type),
+            Encoding::Point => is_coordinates(data_type),
+            Encoding::LineString | Encoding::MultiPoint => is_nested(data_type, 1),
+            Encoding::Polygon | Encoding::MultiLineString => is_nested(data_type, 2),
+            Encoding::MultiPolygon => is_nested(data_type, 3),
+        };
+        if supported {
+            Ok(encoding)
+        } else {
+            Err(GeoArrowError::UnsupportedGeometry(field.name().clone()))
+        }
+    }
+
+    /// The geometry in `row`, or `None` if it is null.
+    fn value(
+        self,
+        column: &dyn Array,
+        row: usize,
+    ) -> Result<Option<Value>, GeoJsonConversionError> {
+        if column.is_null(row) {
+            return Ok(None);
+        }
+        let value = match self {
+            Encoding::Wkb => match column.as_binary_opt::<i32>() {
+                Some(binary) => wkb_to_value(binary.value(row))?,
+                None => wkb_to_value(column.as_binary::<i64>().value(row))?,
+            },
+            Encoding::Wkt => match column.as_string_opt::<i32>() {
+                Some(string) => wkt_to_value(string.value(row))?,
+                None => wkt_to_value(column.as_string::<i64>().value(row))?,
+            },
+            Encoding::Point => Value::Point(position(column, row)),
+            Encoding::LineString => Value::LineString(positions(members(column, row).as_ref())),
+            Encoding::MultiPoint => Value::MultiPoint(positions(members(column, row).as_ref())),
+            Encoding::Polygon => Value::Polygon(rings(members(column, row).as_ref())),
+            Encoding::MultiLineString => {
+                Value::MultiLineString(rings(members(column, row).as_ref()))
+            }
+            Encoding::MultiPolygon => {
+                let polygons = members(column, row);
+                Value::MultiPolygon(
+                    (0..polygons.len())
+                        .map(|polygon| rings(members(polygons.as_ref(), polygon).as_ref()))
+                        .collect(),
+                )
+            }
+        };
+        Ok(Some(value))
+    }
+}
+
+fn is_binary(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Binary | DataType::LargeBinary)
+}
+
+fn is_string(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Utf8 | DataType::LargeUtf8)
+}
+
+/// Interleaved `[x, y]` or separated `{x, y}` 64 bit float coordinates. The
+/// separated fields are found by name, so they can be in either order.
+fn is_coordinates(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::FixedSizeList(field, 2) => field.data_type() == &DataType::Float64,
+        DataType::Struct(fields) => {
+            fields.len() == 2
+                && COORDINATE_FIELDS.iter().all(|name| {
+                    fields
+                        .find(name)
+                        .is_some_and(|(_, field)| field.data_type() == &DataType::Float64)
+                })
+        }
+        _ => false,
+    }
+}
+
+/// The names of the fields of separated coordinates.
+const COORDINATE_FIELDS: [&str; 2] = ["x", "y"];
+
+/// Coordinates inside `depth` levels of lists.
+fn is_nested(data_type: &DataType, depth: usize) -> bool {
+    if depth == 0 {
+        return is_coordinates(data_type);
+    }
+    match data_type {
+        DataType::List(field) | DataType::LargeList(field) => {
+            is_nested(field.data_type(), depth - 1)
+        }
+        _ => false,
+    }
+}
+
+/// The members of the list in `row`.
+fn members(column: &dyn Array, row: usize) -> ArrayRef {
+    match column.as_list_opt::<i32>() {
+        Some(list) => list.value(row),
+        None => column.as_list::<i64>().value(row),
+    }
+}
+
+fn position(coordinates: &dyn Array, index: usize) -> PointType {
+    match coordinates.as_fixed_size_list_opt() {
+        Some(list) => {
+            let values = list.values().as_primitive::<Float64Type>();
+            let start = list.value_offset(index) as usize;
+            vec![values.value(start), values.value(start + 1)]
+        }
+        None => {
+            let fields = coordinates.as_struct();
+            COORDINATE_FIELDS
+                .iter()
+                .map(|name| {
+                    fields
+                        .column_by_name(name)
+                        .expect("The coordinate fields were checked")
+                        .as_primitive::<Float64Type>()
+                        .value(index)
+                })
+                .collect()
+        }
+    }
+}
+
+fn positions(coordinates: &dyn Array) -> Vec<PointType> {
+    (0..coordinates.len())
+        .map(|index| position(coordinates, index))
+        .collect()
+}
+
+/// The rings of a polygon, or the lines of a multi line string.
+fn rings(lists: &dyn Array) -> PolygonType {
+    (0..lists.len())
+        .map(|index| positions(members(lists, index).as_ref()))
+        .collect()
+}
+
+fn is_attribute_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Null
+            | DataType::Boolean
+            | DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Float32
+            | DataType::Float64
+            | DataType::Utf8
+            | DataType::LargeUtf8
+    )
+}
+
+/// The value of an attribute column, which is null for a float that isn't
+/// finite as JSON has no such numbers.
+fn attribute_value(column: &dyn Array, row: usize) -> JsonValue {
+    if column.is_null(row) {
+        return JsonValue::Null;
+    }
+    let float = |value: f64| Number::from_f64(value).map_or(JsonValue::Null, JsonValue::Number);
+    match column.data_type() {
+        DataType::Boolean => column.as_boolean().value(row).into(),
+        DataType::Int8 => column.as_primitive::<Int8Type>().value(row).into(),
+        DataType::Int16 => column.as_primitive::<Int16Type>().value(row).into(),
+        DataType::Int32 => column.as_primitive::<Int32Type>().value(row).into(),
+        DataType::Int64 => column.as_primitive::<Int64Type>().value(row).into(),
+        DataType::UInt8 => column.as_primitive::<UInt8Type>().value(row).into(),
+        DataType::UInt16 => column.as_primitive::<UInt16Type>().value(row).into(),
+        DataType::UInt32 => column.as_primitive::<UInt32Type>().value(row).into(),
+        DataType::UInt64 => column.as_primitive::<UInt64Type>().value(row).into(),
+        DataType::Float32 => float(column.as_primitive::<Float32Type>().value(row).into()),
+        DataType::Float64 => float(column.as_primitive::<Float64Type>().value(row)),
+        DataType::Utf8 => column.as_string::<i32>().value(row).into(),
+        DataType::LargeUtf8 => column.as_string::<i64>().value(row).into(),
+        _ => JsonValue::Null,
+    }
+}
+
+/// A column holding the values of one property, `None` where a feature
+/// doesn't have it or it is null. Integers above `i64::MAX` are kept as
+/// `UInt64` when every value is a non-negative integer, and are otherwise
+/// written as JSON text rather than losing precision as floats.
+fn attribute_column(values: &[Option<&JsonValue>]) -> ArrayRef {
+    let present = || values.iter().flatten();
+    if present().next().is_none() {
+        Arc::new(NullArray::new(values.len()))
+    } else if present().all(|value| value.is_boolean()) {
+        Arc::new(BooleanArray::from(
+            values
+                .iter()
+                .map(|value| value.and_then(JsonValue::as_bool))
+                .collect::<Vec<_>>(),
+        ))
+    } else if present().all(|value| value.is_i64()) {
+        Arc::new(Int64Array::from(
+            values
+                .iter()
+                .map(|value| value.and_then(JsonValue::as_i64))
+                .collect::<Vec<_>>(),
+        ))
+    } else if present().all(|value| value.is_u64()) {
+        Arc::new(UInt64Array::from(
+            values
+                .iter()
+                .map(|value| value.and_then(JsonValue::as_u64))
+                .collect::<Vec<_>>(),
+        ))
+    } else if present().all(|value| value.is_f64() || value.is_i64()) {
+        Arc::new(Float64Array::from(
+            values
+                .iter()
+                .map(|value| value.and_then(JsonValue::as_f64))
+                .collect::<Vec<_>>(),
+        ))
+    } else if present().all(|value| value.is_string()) {
+        Arc::new(StringArray::from(
+            values
+                .iter()
+                .map(|value| value.and_then(JsonValue::as_str))
+                .collect::<Vec<_>>(),
+        ))
+    } else {
+        Arc::new(StringArray::from(
+            values
+                .iter()
+                .map(|value| value.map(JsonValue::to_string))
+                .collect::<Vec<_>>(),
+        ))
+    }
+}
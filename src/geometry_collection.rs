@@ -13,56 +13,78 @@
 // limitations under the License.
 
 use crate::{
-    conversion::create_geo_geometry_collection, error::GeoJsonConversionError,
-    generic::GenericFeature, json::JsonObject, LineStringFeature, MultiLineStringFeature,
-    MultiPointFeature, MultiPolygonFeature, PointFeature, PolygonFeature,
+    conversion::{convert_position, create_geo_geometry_collection},
+    distance::geometry_distance,
+    error::GeoJsonConversionError,
+    generic::{create_bbox, GenericFeature, GetBbox},
+    json::JsonObject,
+    LineStringFeature, MultiLineStringFeature, MultiPointFeature, MultiPolygonFeature,
+    PointFeature, PolygonFeature,
 };
 use geo::{algorithm::bounding_rect::BoundingRect, Coordinate, Rect};
 use geojson::{feature::Id, Bbox, Geometry, Value};
+use num_traits::Float;
+use rstar::{Envelope, Point, PointDistance, RTreeNum, RTreeObject, AABB};
 use std::convert::TryFrom;
 
+/// The member geometries are kept as `GeoJson` `f64` coordinates, only the
+/// bounding box is stored as `T`.
 #[derive(Clone, Debug, PartialEq)]
-pub struct GeometryCollectionFeature {
-    bbox: Bbox,
+pub struct GeometryCollectionFeature<T = f64> {
+    bbox: [T; 4],
     geometries: Vec<Geometry>,
     pub id: Option<Id>,
     pub properties: Option<JsonObject>,
     pub foreign_members: Option<JsonObject>,
 }
 
-impl GeometryCollectionFeature {
+impl<T> GeometryCollectionFeature<T>
+where
+    T: Float,
+{
     pub fn geometries(&self) -> &[Geometry] {
         &self.geometries
     }
 
-    pub fn geo_geometry(&self) -> geo::GeometryCollection<f64> {
+    pub fn geo_geometry(&self) -> geo::GeometryCollection<T> {
         create_geo_geometry_collection(self.geometries())
+            .expect("GeometryCollectionFeature coordinates were checked on conversion")
     }
 }
 
-impl Into<geojson::Feature> for GeometryCollectionFeature {
-    fn into(self) -> geojson::Feature {
-        let geometry = geojson::Geometry::new(geojson::Value::GeometryCollection(self.geometries));
+impl<T> From<GeometryCollectionFeature<T>> for geojson::Feature
+where
+    T: Float,
+{
+    fn from(feature: GeometryCollectionFeature<T>) -> geojson::Feature {
+        let geometry =
+            geojson::Geometry::new(geojson::Value::GeometryCollection(feature.geometries));
 
         geojson::Feature {
-            id: self.id,
-            properties: self.properties,
-            foreign_members: self.foreign_members,
+            id: feature.id,
+            properties: feature.properties,
+            foreign_members: feature.foreign_members,
             geometry: Some(geometry),
-            bbox: Some(self.bbox),
+            bbox: Some(convert_position(&feature.bbox).expect("Bbox can be widened to f64")),
         }
     }
 }
 
-impl TryFrom<geojson::Feature> for GeometryCollectionFeature {
+impl<T> TryFrom<geojson::Feature> for GeometryCollectionFeature<T>
+where
+    T: Float,
+{
     type Error = GeoJsonConversionError;
 
-    fn try_from(feature: geojson::Feature) -> Result<GeometryCollectionFeature, Self::Error> {
-        <Self as GenericFeature<GeometryCollectionFeature, Vec<Geometry>>>::try_from(feature)
+    fn try_from(feature: geojson::Feature) -> Result<GeometryCollectionFeature<T>, Self::Error> {
+        <Self as GenericFeature<GeometryCollectionFeature<T>, Vec<Geometry>>>::try_from(feature)
     }
 }
 
-impl GenericFeature<GeometryCollectionFeature, Vec<Geometry>> for GeometryCollectionFeature {
+impl<T> GenericFeature<GeometryCollectionFeature<T>, Vec<Geometry>> for GeometryCollectionFeature<T>
+where
+    T: Float,
+{
     fn take_geometry_type(
         feature: &mut geojson::Feature,
     ) -> Result<Vec<Geometry>, GeoJsonConversionError> {
@@ -87,28 +109,31 @@ impl GenericFeature<GeometryCollectionFeature, Vec<Geometry>> for GeometryCollec
         geometry: &Vec<Geometry>,
         feature: &geojson::Feature,
     ) -> Result<(), GeoJsonConversionError> {
+        // An empty collection, at any depth, has no bounding box.
+        if geometry.is_empty() {
+            let id = feature.id.clone();
+            return Err(GeoJsonConversionError::MalformedGeometry(id));
+        }
         for geom in geometry {
-            let res = match &geom.value {
-                Value::Point(p) => PointFeature::check_geometry(&p, feature),
-                Value::LineString(l) => LineStringFeature::check_geometry(&l, feature),
-                Value::Polygon(p) => PolygonFeature::check_geometry(&p, feature),
-                Value::MultiPoint(p) => MultiPointFeature::check_geometry(&p, feature),
-                Value::MultiLineString(l) => MultiLineStringFeature::check_geometry(&l, feature),
-                Value::MultiPolygon(p) => MultiPolygonFeature::check_geometry(&p, feature),
-                Value::GeometryCollection(g) => {
-                    GeometryCollectionFeature::check_geometry(&g, feature)
+            match &geom.value {
+                Value::Point(p) => PointFeature::<T>::check_geometry(p, feature),
+                Value::LineString(l) => LineStringFeature::<T>::check_geometry(l, feature),
+                Value::Polygon(p) => PolygonFeature::<T>::check_geometry(p, feature),
+                Value::MultiPoint(p) => MultiPointFeature::<T>::check_geometry(p, feature),
+                Value::MultiLineString(l) => {
+                    MultiLineStringFeature::<T>::check_geometry(l, feature)
                 }
-            };
-            if res.is_err() {
-                return res;
-            }
+                Value::MultiPolygon(p) => MultiPolygonFeature::<T>::check_geometry(p, feature),
+                Value::GeometryCollection(g) => Self::check_geometry(g, feature),
+            }?;
         }
         Ok(())
     }
 
     fn compute_bbox(feature: &mut geojson::Feature, geometry: &Vec<Geometry>) -> Bbox {
         feature.bbox.take().unwrap_or_else(|| {
-            let geo_geometry_collection = create_geo_geometry_collection(geometry);
+            let geo_geometry_collection = create_geo_geometry_collection::<f64>(geometry)
+                .expect("GeometryCollection coordinates were checked");
             let polygons: Vec<geo::Polygon<f64>> = convert_bounding_rect(geo_geometry_collection)
                 .into_iter()
                 .map(|v| v.into())
@@ -129,14 +154,46 @@ impl GenericFeature<GeometryCollectionFeature, Vec<Geometry>> for GeometryCollec
         feature: geojson::Feature,
         bbox: Bbox,
         geometry: Vec<Geometry>,
-    ) -> GeometryCollectionFeature {
-        GeometryCollectionFeature {
-            bbox,
-            id: feature.id,
+    ) -> Result<GeometryCollectionFeature<T>, GeoJsonConversionError> {
+        Ok(GeometryCollectionFeature {
+            bbox: create_bbox(&bbox, &feature)?,
             geometries: geometry,
+            id: feature.id,
             properties: feature.properties,
             foreign_members: feature.foreign_members,
-        }
+        })
+    }
+}
+
+impl<T> GetBbox<T> for GeometryCollectionFeature<T> {
+    fn bbox(&self) -> &[T; 4] {
+        &self.bbox
+    }
+}
+
+impl<T> RTreeObject for GeometryCollectionFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        <Self as GetBbox<T>>::envelope(self)
+    }
+}
+
+impl<T> PointDistance for GeometryCollectionFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    /// The distance to the nearest member geometry.
+    fn distance_2(
+        &self,
+        point: &<Self::Envelope as Envelope>::Point,
+    ) -> <<Self::Envelope as Envelope>::Point as Point>::Scalar {
+        let point = geo::Geometry::Point((*point).into());
+        let collection = geo::Geometry::GeometryCollection(self.geo_geometry());
+        geometry_distance(&collection, &point).powi(2)
     }
 }
 
@@ -0,0 +1,416 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `IncrementalTree` keeps an `RTree` of `Feature`s up to date as features
+//! are inserted, updated and removed, and tells subscribers what changed.
+//!
+//! ```
+//! use geojson_rstar::{incremental::IncrementalTree, vector_tile::TileAddress};
+//! use std::{collections::HashSet, sync::{Arc, Mutex}};
+//!
+//! let stale = Arc::new(Mutex::new(HashSet::new()));
+//! let mut tree = IncrementalTree::<f64>::new();
+//! let tiles = Arc::clone(&stale);
+//! tree.subscribe(move |change| {
+//!     for envelope in change.envelopes() {
+//!         tiles.lock().unwrap().extend(TileAddress::covering(&envelope, 14));
+//!     }
+//! });
+//! ```
+
+use crate::Feature;
+use geojson::feature::Id;
+use num_traits::Float;
+use rstar::{Envelope, PointDistance, RTree, RTreeNum, RTreeObject, SelectionFunction, AABB};
+use std::{collections::HashMap, sync::Arc};
+
+/// Identifies a feature in an `IncrementalTree` for as long as it's there,
+/// whether or not the feature has an id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FeatureKey(u64);
+
+/// Identifies a subscriber, to unsubscribe it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Subscription(u64);
+
+/// A change to an `IncrementalTree`, with the envelopes it affects.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeEvent<T = f64>
+where
+    T: Float + RTreeNum,
+{
+    Added {
+        key: FeatureKey,
+        envelope: AABB<[T; 2]>,
+    },
+    Removed {
+        key: FeatureKey,
+        envelope: AABB<[T; 2]>,
+    },
+    Modified {
+        key: FeatureKey,
+        old_envelope: AABB<[T; 2]>,
+        new_envelope: AABB<[T; 2]>,
+    },
+}
+
+impl<T> ChangeEvent<T>
+where
+    T: Float + RTreeNum,
+{
+    pub fn key(&self) -> FeatureKey {
+        match self {
+            ChangeEvent::Added { key, .. }
+            | ChangeEvent::Removed { key, .. }
+            | ChangeEvent::Modified { key, .. } => *key,
+        }
+    }
+
+    /// Where something changed: the envelope of an added or removed feature,
+    /// or both the old and new envelopes of a modified one.
+    pub fn envelopes(&self) -> impl Iterator<Item = AABB<[T; 2]>> {
+        let (first, second) = match self {
+            ChangeEvent::Added { envelope, .. } | ChangeEvent::Removed { envelope, .. } => {
+                (*envelope, None)
+            }
+            ChangeEvent::Modified {
+                old_envelope,
+                new_envelope,
+                ..
+            } => (*old_envelope, Some(*new_envelope)),
+        };
+        std::iter::once(first).chain(second)
+    }
+}
+
+/// A `Feature` in an `IncrementalTree`'s `RTree`, with its key.
+#[derive(Clone, Debug)]
+pub struct IndexedFeature<T = f64>
+where
+    T: Float + RTreeNum,
+{
+    key: FeatureKey,
+    feature: Arc<Feature<T>>,
+}
+
+impl<T> IndexedFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    pub fn key(&self) -> FeatureKey {
+        self.key
+    }
+
+    pub fn feature(&self) -> &Feature<T> {
+        &self.feature
+    }
+}
+
+impl<T> RTreeObject for IndexedFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.feature.envelope()
+    }
+}
+
+impl<T> PointDistance for IndexedFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn distance_2(&self, point: &[T; 2]) -> T {
+        self.feature.distance_2(point)
+    }
+}
+
+/// Finds the feature with `key`, which lies in `envelope`.
+struct SelectKey<T>
+where
+    T: Float + RTreeNum,
+{
+    key: FeatureKey,
+    envelope: AABB<[T; 2]>,
+}
+
+impl<T> SelectionFunction<IndexedFeature<T>> for SelectKey<T>
+where
+    T: Float + RTreeNum,
+{
+    fn should_unpack_parent(&self, envelope: &AABB<[T; 2]>) -> bool {
+        envelope.contains_envelope(&self.envelope)
+    }
+
+    fn should_unpack_leaf(&self, leaf: &IndexedFeature<T>) -> bool {
+        leaf.key == self.key
+    }
+}
+
+/// A feature id that can be hashed. Numbers are compared by how they're
+/// written.
+#[derive(PartialEq, Eq, Hash)]
+enum IdKey {
+    String(String),
+    Number(String),
+}
+
+impl From<&Id> for IdKey {
+    fn from(id: &Id) -> Self {
+        match id {
+            Id::String(id) => IdKey::String(id.clone()),
+            Id::Number(id) => IdKey::Number(id.to_string()),
+        }
+    }
+}
+
+type Subscriber<T> = Box<dyn FnMut(&ChangeEvent<T>) + Send>;
+
+/// An `RTree` of `Feature`s that can be changed one feature at a time.
+///
+/// Features are looked up by the `FeatureKey` returned when they're
+/// inserted, or by their id. Ids are unique in the tree: inserting a feature
+/// with the id of one already there replaces it.
+///
+/// Features inserted one at a time make a less balanced tree than
+/// `RTree::bulk_load`, so with `rebalance_every` the tree is bulk loaded
+/// again after that many changes.
+pub struct IncrementalTree<T = f64>
+where
+    T: Float + RTreeNum,
+{
+    tree: RTree<IndexedFeature<T>>,
+    features: HashMap<FeatureKey, Arc<Feature<T>>>,
+    ids: HashMap<IdKey, FeatureKey>,
+    subscribers: Vec<(Subscription, Subscriber<T>)>,
+    next_key: u64,
+    next_subscription: u64,
+    rebalance_every: Option<usize>,
+    changes: usize,
+}
+
+impl<T> Default for IncrementalTree<T>
+where
+    T: Float + RTreeNum,
+{
+    fn default() -> Self {
+        IncrementalTree {
+            tree: RTree::new(),
+            features: HashMap::new(),
+            ids: HashMap::new(),
+            subscribers: vec![],
+            next_key: 0,
+            next_subscription: 0,
+            rebalance_every: None,
+            changes: 0,
+        }
+    }
+}
+
+impl<T> IncrementalTree<T>
+where
+    T: Float + RTreeNum,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bulk loads `features`, without change events. When features share an
+    /// id, the last one is kept.
+    pub fn bulk_load(features: Vec<Feature<T>>) -> Self {
+        let mut tree = Self::default();
+        for feature in features {
+            let key = match feature.id().and_then(|id| tree.ids.get(&id.into())) {
+                Some(key) => *key,
+                None => tree.next_key(),
+            };
+            if let Some(id) = feature.id() {
+                tree.ids.insert(id.into(), key);
+            }
+            tree.features.insert(key, Arc::new(feature));
+        }
+        tree.rebalance();
+        tree
+    }
+
+    /// Bulk loads the tree again after every `changes` inserts, updates and
+    /// removals.
+    pub fn rebalance_every(mut self, changes: usize) -> Self {
+        self.rebalance_every = Some(changes.max(1));
+        self
+    }
+
+    /// The underlying tree, for queries.
+    pub fn tree(&self) -> &RTree<IndexedFeature<T>> {
+        &self.tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    pub fn get(&self, key: FeatureKey) -> Option<&Feature<T>> {
+        self.features.get(&key).map(AsRef::as_ref)
+    }
+
+    /// The key of the feature with `id`.
+    pub fn key_of(&self, id: &Id) -> Option<FeatureKey> {
+        self.ids.get(&id.into()).copied()
+    }
+
+    /// Calls `subscriber` with every change from now on, after the tree has
+    /// changed.
+    pub fn subscribe<F>(&mut self, subscriber: F) -> Subscription
+    where
+        F: FnMut(&ChangeEvent<T>) + Send + 'static,
+    {
+        let subscription = Subscription(self.next_subscription);
+        self.next_subscription += 1;
+        self.subscribers.push((subscription, Box::new(subscriber)));
+        subscription
+    }
+
+    /// Returns false if `subscription` had already been unsubscribed.
+    pub fn unsubscribe(&mut self, subscription: Subscription) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|(s, _)| *s != subscription);
+        self.subscribers.len() != before
+    }
+
+    /// Inserts `feature`, or replaces the feature with the same id.
+    pub fn insert(&mut self, feature: Feature<T>) -> FeatureKey {
+        if let Some(key) = feature.id().and_then(|id| self.key_of(id)) {
+            self.update(key, feature);
+            return key;
+        }
+        let key = self.next_key();
+        let envelope = feature.envelope();
+        self.attach(key, feature);
+        self.changed(ChangeEvent::Added { key, envelope });
+        key
+    }
+
+    /// Replaces the feature with `key`, returning the old feature. If there's
+    /// no feature with `key`, nothing changes and `None` is returned.
+    ///
+    /// If the new feature has the id of another feature, that feature is
+    /// removed.
+    pub fn update(&mut self, key: FeatureKey, feature: Feature<T>) -> Option<Feature<T>> {
+        let old = self.detach(key)?;
+        let old_envelope = old.envelope();
+        let new_envelope = feature.envelope();
+        self.attach(key, feature);
+        self.changed(ChangeEvent::Modified {
+            key,
+            old_envelope,
+            new_envelope,
+        });
+        Some(unwrap_feature(old))
+    }
+
+    pub fn remove(&mut self, key: FeatureKey) -> Option<Feature<T>> {
+        let old = self.detach(key)?;
+        self.changed(ChangeEvent::Removed {
+            key,
+            envelope: old.envelope(),
+        });
+        Some(unwrap_feature(old))
+    }
+
+    /// Bulk loads the tree again, which makes queries as fast as a tree
+    /// made with `RTree::bulk_load`.
+    pub fn rebalance(&mut self) {
+        let features = self
+            .features
+            .iter()
+            .map(|(key, feature)| IndexedFeature {
+                key: *key,
+                feature: Arc::clone(feature),
+            })
+            .collect();
+        self.tree = RTree::bulk_load(features);
+        self.changes = 0;
+    }
+
+    fn next_key(&mut self) -> FeatureKey {
+        let key = FeatureKey(self.next_key);
+        self.next_key += 1;
+        key
+    }
+
+    /// Takes the feature with `key` out of the tree and the maps.
+    fn detach(&mut self, key: FeatureKey) -> Option<Arc<Feature<T>>> {
+        let feature = self.features.remove(&key)?;
+        self.tree.remove_with_selection_function(SelectKey {
+            key,
+            envelope: feature.envelope(),
+        });
+        if let Some(id) = feature.id() {
+            let id = IdKey::from(id);
+            if self.ids.get(&id) == Some(&key) {
+                self.ids.remove(&id);
+            }
+        }
+        Some(feature)
+    }
+
+    /// Puts `feature` in the tree and the maps, first removing any other
+    /// feature with its id.
+    fn attach(&mut self, key: FeatureKey, feature: Feature<T>) {
+        let displaced = feature
+            .id()
+            .and_then(|id| self.ids.insert(id.into(), key))
+            .filter(|other| *other != key);
+        if let Some(other) = displaced {
+            if let Some(old) = self.detach(other) {
+                self.changed(ChangeEvent::Removed {
+                    key: other,
+                    envelope: old.envelope(),
+                });
+            }
+        }
+        let feature = Arc::new(feature);
+        self.tree.insert(IndexedFeature {
+            key,
+            feature: Arc::clone(&feature),
+        });
+        self.features.insert(key, feature);
+    }
+
+    fn changed(&mut self, event: ChangeEvent<T>) {
+        self.changes += 1;
+        if self
+            .rebalance_every
+            .is_some_and(|every| self.changes >= every)
+        {
+            self.rebalance();
+        }
+        for (_, subscriber) in self.subscribers.iter_mut() {
+            subscriber(&event);
+        }
+    }
+}
+
+/// The feature of an `Arc` that was only held by the tree's maps.
+fn unwrap_feature<T>(feature: Arc<Feature<T>>) -> Feature<T>
+where
+    T: Float + RTreeNum,
+{
+    Arc::try_unwrap(feature).unwrap_or_else(|feature| (*feature).clone())
+}
@@ -0,0 +1,305 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `IndexableFeature` is implemented by every feature type, for code that
+//! works with any of them.
+
+use crate::{
+    generic::GetBbox, json::JsonObject, Feature, GeometryCollectionFeature, LineStringFeature,
+    MultiLineStringFeature, MultiPointFeature, MultiPolygonFeature, PointFeature, PolygonFeature,
+};
+use geojson::{feature::Id, Geometry, Value};
+use num_traits::Float;
+use rstar::{RTreeNum, RTreeObject, AABB};
+
+/// A feature that can be put in an `RTree`, with the parts of a GeoJSON
+/// feature that every feature type keeps.
+///
+/// ```
+/// use geojson_rstar::IndexableFeature;
+/// use rstar::RTree;
+///
+/// fn names<F: IndexableFeature>(tree: &RTree<F>) -> Vec<String> {
+///     tree.iter()
+///         .filter_map(|f| f.properties()?.get("name")?.as_str())
+///         .map(String::from)
+///         .collect()
+/// }
+/// ```
+pub trait IndexableFeature<T = f64>: RTreeObject<Envelope = AABB<[T; 2]>>
+where
+    T: Float + RTreeNum,
+{
+    fn id(&self) -> Option<&Id>;
+
+    fn properties(&self) -> Option<&JsonObject>;
+
+    /// `[min x, min y, max x, max y]`, either given in the GeoJSON or computed
+    /// from the geometry.
+    fn bbox(&self) -> &[T; 4];
+
+    fn geometry(&self) -> Geometry;
+
+    fn geo_geometry(&self) -> geo::Geometry<T>;
+
+    fn into_geojson(self) -> geojson::Feature;
+}
+
+impl<T> IndexableFeature<T> for PointFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+
+    fn properties(&self) -> Option<&JsonObject> {
+        self.properties.as_ref()
+    }
+
+    fn bbox(&self) -> &[T; 4] {
+        GetBbox::bbox(self)
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::new(Value::Point(self.point()))
+    }
+
+    fn geo_geometry(&self) -> geo::Geometry<T> {
+        geo::Geometry::Point(self.geo_point())
+    }
+
+    fn into_geojson(self) -> geojson::Feature {
+        self.into()
+    }
+}
+
+impl<T> IndexableFeature<T> for LineStringFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+
+    fn properties(&self) -> Option<&JsonObject> {
+        self.properties.as_ref()
+    }
+
+    fn bbox(&self) -> &[T; 4] {
+        GetBbox::bbox(self)
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::new(Value::LineString(self.line()))
+    }
+
+    fn geo_geometry(&self) -> geo::Geometry<T> {
+        geo::Geometry::LineString(self.geo_line())
+    }
+
+    fn into_geojson(self) -> geojson::Feature {
+        self.into()
+    }
+}
+
+impl<T> IndexableFeature<T> for PolygonFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+
+    fn properties(&self) -> Option<&JsonObject> {
+        self.properties.as_ref()
+    }
+
+    fn bbox(&self) -> &[T; 4] {
+        GetBbox::bbox(self)
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::new(Value::Polygon(self.polygon()))
+    }
+
+    fn geo_geometry(&self) -> geo::Geometry<T> {
+        geo::Geometry::Polygon(self.geo_polygon())
+    }
+
+    fn into_geojson(self) -> geojson::Feature {
+        self.into()
+    }
+}
+
+impl<T> IndexableFeature<T> for MultiPointFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+
+    fn properties(&self) -> Option<&JsonObject> {
+        self.properties.as_ref()
+    }
+
+    fn bbox(&self) -> &[T; 4] {
+        GetBbox::bbox(self)
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::new(Value::MultiPoint(self.points()))
+    }
+
+    fn geo_geometry(&self) -> geo::Geometry<T> {
+        geo::Geometry::MultiPoint(self.geo_points())
+    }
+
+    fn into_geojson(self) -> geojson::Feature {
+        self.into()
+    }
+}
+
+impl<T> IndexableFeature<T> for MultiLineStringFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+
+    fn properties(&self) -> Option<&JsonObject> {
+        self.properties.as_ref()
+    }
+
+    fn bbox(&self) -> &[T; 4] {
+        GetBbox::bbox(self)
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::new(Value::MultiLineString(self.lines()))
+    }
+
+    fn geo_geometry(&self) -> geo::Geometry<T> {
+        geo::Geometry::MultiLineString(self.geo_lines())
+    }
+
+    fn into_geojson(self) -> geojson::Feature {
+        self.into()
+    }
+}
+
+impl<T> IndexableFeature<T> for MultiPolygonFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+
+    fn properties(&self) -> Option<&JsonObject> {
+        self.properties.as_ref()
+    }
+
+    fn bbox(&self) -> &[T; 4] {
+        GetBbox::bbox(self)
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::new(Value::MultiPolygon(self.polygons()))
+    }
+
+    fn geo_geometry(&self) -> geo::Geometry<T> {
+        geo::Geometry::MultiPolygon(self.geo_polygons())
+    }
+
+    fn into_geojson(self) -> geojson::Feature {
+        self.into()
+    }
+}
+
+impl<T> IndexableFeature<T> for GeometryCollectionFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+
+    fn properties(&self) -> Option<&JsonObject> {
+        self.properties.as_ref()
+    }
+
+    fn bbox(&self) -> &[T; 4] {
+        GetBbox::bbox(self)
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::new(Value::GeometryCollection(self.geometries().to_vec()))
+    }
+
+    fn geo_geometry(&self) -> geo::Geometry<T> {
+        geo::Geometry::GeometryCollection(GeometryCollectionFeature::geo_geometry(self))
+    }
+
+    fn into_geojson(self) -> geojson::Feature {
+        self.into()
+    }
+}
+
+impl<T> IndexableFeature<T> for Feature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn id(&self) -> Option<&Id> {
+        Feature::id(self)
+    }
+
+    fn properties(&self) -> Option<&JsonObject> {
+        Feature::properties(self)
+    }
+
+    fn bbox(&self) -> &[T; 4] {
+        match self {
+            Feature::Point(f) => GetBbox::bbox(f),
+            Feature::Polygon(f) => GetBbox::bbox(f),
+            Feature::LineString(f) => GetBbox::bbox(f),
+            Feature::MultiPoint(f) => GetBbox::bbox(f),
+            Feature::MultiLineString(f) => GetBbox::bbox(f),
+            Feature::MultiPolygon(f) => GetBbox::bbox(f),
+            Feature::GeometryCollection(f) => GetBbox::bbox(f),
+        }
+    }
+
+    fn geometry(&self) -> Geometry {
+        match self {
+            Feature::Point(f) => f.geometry(),
+            Feature::Polygon(f) => f.geometry(),
+            Feature::LineString(f) => f.geometry(),
+            Feature::MultiPoint(f) => f.geometry(),
+            Feature::MultiLineString(f) => f.geometry(),
+            Feature::MultiPolygon(f) => f.geometry(),
+            Feature::GeometryCollection(f) => f.geometry(),
+        }
+    }
+
+    fn geo_geometry(&self) -> geo::Geometry<T> {
+        self.into()
+    }
+
+    fn into_geojson(self) -> geojson::Feature {
+        self.into()
+    }
+}
@@ -12,46 +12,158 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod aggregate;
+pub mod clip;
+pub mod cluster;
+pub mod columnar;
 pub mod conversion;
+mod coordinates;
+#[cfg(feature = "csv")]
+pub mod csv_points;
+pub mod decomposed;
+pub mod distance;
 pub mod error;
 pub mod generic;
+#[cfg(feature = "arrow")]
+pub mod geoarrow;
 pub mod geometry_collection;
+pub mod incremental;
+pub mod indexable;
 pub mod linestring_feature;
+pub mod measure;
 pub mod multilinestring_feature;
 pub mod multipoint_feature;
 pub mod multipolygon_feature;
+mod overlay;
 pub mod point_feature;
 pub mod polygon_feature;
+pub mod projection;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod query;
+pub mod simplify;
+#[cfg(feature = "tokio")]
+pub mod stream;
+pub mod vector_tile;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod well_known;
 
-pub use error::GeoJsonConversionError;
+pub use columnar::{ColumnValue, ColumnarTree, PropertyRow, PropertyTable};
+#[cfg(feature = "csv")]
+pub use csv_points::CsvPoints;
+pub use decomposed::{DecomposedTree, FeaturePart};
+#[cfg(feature = "csv")]
+pub use error::CsvError;
+#[cfg(feature = "arrow")]
+pub use error::GeoArrowError;
+#[cfg(feature = "tokio")]
+pub use error::GeoJsonStreamError;
+pub use error::{AggregateError, ClipError, GeoJsonConversionError};
 pub use geometry_collection::GeometryCollectionFeature;
+pub use incremental::{ChangeEvent, FeatureKey, IncrementalTree};
+pub use indexable::IndexableFeature;
 pub use linestring_feature::LineStringFeature;
+pub use measure::Measure;
 pub use multilinestring_feature::MultiLineStringFeature;
 pub use multipoint_feature::MultiPointFeature;
 pub use multipolygon_feature::MultiPolygonFeature;
 pub use point_feature::PointFeature;
 pub use polygon_feature::PolygonFeature;
+pub use projection::{ProjectFeature, Projection, PropertyFilter};
+pub use well_known::WellKnown;
 
+#[cfg(feature = "derive")]
+pub use geojson_rstar_derive::GeoJsonFeature;
+
+use num_traits::Float;
+use rstar::RTreeNum;
 use std::convert::TryFrom;
 
+/// The crates used by `impl_rtree_feature!` and `#[derive(GeoJsonFeature)]`,
+/// so they work whatever they are called by the crate using them.
+#[doc(hidden)]
+pub mod __private {
+    pub use geo;
+    pub use geojson;
+    pub use rstar;
+    pub use serde_json;
+}
+
 mod json {
     use serde_json::{Map, Value as JsonValue};
     pub type JsonObject = Map<String, JsonValue>;
 }
 
+/// Any of the feature types, so features with mixed geometries can be loaded
+/// into one `RTree`. `T` is the scalar type used to store coordinates.
 #[derive(Debug, Clone, PartialEq)]
-pub enum Feature {
-    Point(PointFeature),
-    Polygon(PolygonFeature),
-    LineString(LineStringFeature),
-    MultiPoint(MultiPointFeature),
-    MultiLineString(MultiLineStringFeature),
-    MultiPolygon(MultiPolygonFeature),
-    GeometryCollection(GeometryCollectionFeature),
+pub enum Feature<T = f64> {
+    Point(PointFeature<T>),
+    Polygon(PolygonFeature<T>),
+    LineString(LineStringFeature<T>),
+    MultiPoint(MultiPointFeature<T>),
+    MultiLineString(MultiLineStringFeature<T>),
+    MultiPolygon(MultiPolygonFeature<T>),
+    GeometryCollection(GeometryCollectionFeature<T>),
+}
+
+/// The kind of geometry a `Feature` has, used to restrict queries to some
+/// kinds of features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GeometryKind {
+    Point,
+    Polygon,
+    LineString,
+    MultiPoint,
+    MultiLineString,
+    MultiPolygon,
+    GeometryCollection,
+}
+
+impl<T> Feature<T> {
+    pub fn kind(&self) -> GeometryKind {
+        match self {
+            Feature::Point(_) => GeometryKind::Point,
+            Feature::Polygon(_) => GeometryKind::Polygon,
+            Feature::LineString(_) => GeometryKind::LineString,
+            Feature::MultiPoint(_) => GeometryKind::MultiPoint,
+            Feature::MultiLineString(_) => GeometryKind::MultiLineString,
+            Feature::MultiPolygon(_) => GeometryKind::MultiPolygon,
+            Feature::GeometryCollection(_) => GeometryKind::GeometryCollection,
+        }
+    }
+
+    pub fn id(&self) -> Option<&geojson::feature::Id> {
+        match self {
+            Feature::Point(f) => f.id.as_ref(),
+            Feature::Polygon(f) => f.id.as_ref(),
+            Feature::LineString(f) => f.id.as_ref(),
+            Feature::MultiPoint(f) => f.id.as_ref(),
+            Feature::MultiLineString(f) => f.id.as_ref(),
+            Feature::MultiPolygon(f) => f.id.as_ref(),
+            Feature::GeometryCollection(f) => f.id.as_ref(),
+        }
+    }
+
+    pub fn properties(&self) -> Option<&json::JsonObject> {
+        match self {
+            Feature::Point(f) => f.properties.as_ref(),
+            Feature::Polygon(f) => f.properties.as_ref(),
+            Feature::LineString(f) => f.properties.as_ref(),
+            Feature::MultiPoint(f) => f.properties.as_ref(),
+            Feature::MultiLineString(f) => f.properties.as_ref(),
+            Feature::MultiPolygon(f) => f.properties.as_ref(),
+            Feature::GeometryCollection(f) => f.properties.as_ref(),
+        }
+    }
 }
 
-impl rstar::RTreeObject for Feature {
-    type Envelope = rstar::AABB<[f64; 2]>;
+impl<T> rstar::RTreeObject for Feature<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = rstar::AABB<[T; 2]>;
 
     fn envelope(&self) -> Self::Envelope {
         match self {
@@ -61,14 +173,15 @@ impl rstar::RTreeObject for Feature {
             Feature::MultiPoint(mpoint) => mpoint.envelope(),
             Feature::MultiLineString(mline) => mline.envelope(),
             Feature::MultiPolygon(mpolygon) => mpolygon.envelope(),
-            Feature::GeometryCollection(_) => {
-                panic!("RTreeObject is not implemented for GeometryCollectionFeature")
-            }
+            Feature::GeometryCollection(collection) => collection.envelope(),
         }
     }
 }
 
-impl rstar::PointDistance for Feature {
+impl<T> rstar::PointDistance for Feature<T>
+where
+    T: Float + RTreeNum,
+{
     fn distance_2(
         &self,
         point: &<Self::Envelope as rstar::Envelope>::Point,
@@ -80,17 +193,18 @@ impl rstar::PointDistance for Feature {
             Feature::MultiPoint(mpoint) => mpoint.distance_2(point),
             Feature::MultiLineString(mline) => mline.distance_2(point),
             Feature::MultiPolygon(mpolygon) => mpolygon.distance_2(point),
-            Feature::GeometryCollection(_) => {
-                panic!("PointDistance is not implemented for GeometryCollectionFeature")
-            }
+            Feature::GeometryCollection(collection) => collection.distance_2(point),
         }
     }
 }
 
-impl TryFrom<geojson::Feature> for Feature {
+impl<T> TryFrom<geojson::Feature> for Feature<T>
+where
+    T: Float,
+{
     type Error = GeoJsonConversionError;
 
-    fn try_from(feature: geojson::Feature) -> Result<Feature, Self::Error> {
+    fn try_from(feature: geojson::Feature) -> Result<Feature<T>, Self::Error> {
         match feature.geometry.as_ref().map(|g| &g.value) {
             Some(geojson::Value::Point(_)) => PointFeature::try_from(feature).map(Feature::Point),
             Some(geojson::Value::LineString(_)) => {
@@ -116,9 +230,41 @@ impl TryFrom<geojson::Feature> for Feature {
     }
 }
 
-impl Into<geojson::Feature> for Feature {
-    fn into(self) -> geojson::Feature {
-        match self {
+impl<T> Feature<T>
+where
+    T: Float,
+{
+    /// Converts everything in a `GeoJson`: each feature of a collection, a
+    /// single feature, or a bare geometry as a feature without properties.
+    pub fn from_geojson(
+        geojson: geojson::GeoJson,
+    ) -> Result<Vec<Feature<T>>, GeoJsonConversionError> {
+        match geojson {
+            geojson::GeoJson::FeatureCollection(collection) => collection
+                .features
+                .into_iter()
+                .map(Feature::try_from)
+                .collect(),
+            geojson::GeoJson::Feature(feature) => Ok(vec![Feature::try_from(feature)?]),
+            geojson::GeoJson::Geometry(geometry) => {
+                Ok(vec![Feature::try_from(geojson::Feature {
+                    bbox: None,
+                    geometry: Some(geometry),
+                    id: None,
+                    properties: None,
+                    foreign_members: None,
+                })?])
+            }
+        }
+    }
+}
+
+impl<T> From<Feature<T>> for geojson::Feature
+where
+    T: Float,
+{
+    fn from(feature: Feature<T>) -> geojson::Feature {
+        match feature {
             Feature::Point(p) => p.into(),
             Feature::LineString(l) => l.into(),
             Feature::Polygon(p) => p.into(),
@@ -129,3 +275,20 @@ impl Into<geojson::Feature> for Feature {
         }
     }
 }
+
+impl<T> From<&Feature<T>> for geo::Geometry<T>
+where
+    T: Float,
+{
+    fn from(feature: &Feature<T>) -> geo::Geometry<T> {
+        match feature {
+            Feature::Point(p) => geo::Geometry::Point(p.geo_point()),
+            Feature::LineString(l) => geo::Geometry::LineString(l.geo_line()),
+            Feature::Polygon(p) => geo::Geometry::Polygon(p.geo_polygon()),
+            Feature::MultiPoint(p) => geo::Geometry::MultiPoint(p.geo_points()),
+            Feature::MultiLineString(l) => geo::Geometry::MultiLineString(l.geo_lines()),
+            Feature::MultiPolygon(p) => geo::Geometry::MultiPolygon(p.geo_polygons()),
+            Feature::GeometryCollection(g) => geo::Geometry::GeometryCollection(g.geo_geometry()),
+        }
+    }
+}
@@ -15,59 +15,86 @@
 //! `LineStringFeature` can be used with rstar `RTree`
 
 use crate::{
-    conversion::create_geo_line_string,
+    conversion::{convert_position, create_geo_line_string},
+    coordinates::{
+        create_positions, geo_line_string_to_positions, positions_to_geo_line_string,
+        positions_to_line_string_type,
+    },
     error::GeoJsonConversionError,
-    generic::{GenericFeature, GetBbox},
+    generic::{check_position, create_bbox, GenericFeature, GetBbox},
     json::JsonObject,
+    simplify::{simplify_line_string, Simplification, SimplifyFeature},
 };
 use geo::algorithm::{bounding_rect::BoundingRect, euclidean_length::EuclideanLength};
 use geojson::{feature::Id, Bbox, LineStringType};
 use num_traits::identities::Zero;
-use rstar::{Envelope, Point, PointDistance, RTreeObject, AABB};
+use num_traits::Float;
+use rstar::{Envelope, Point, PointDistance, RTreeNum, RTreeObject, AABB};
 use std::convert::TryFrom;
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct LineStringFeature {
-    bbox: Bbox,
-    line: LineStringType,
+pub struct LineStringFeature<T = f64> {
+    bbox: [T; 4],
+    line: Vec<[T; 2]>,
     pub id: Option<Id>,
     pub properties: Option<JsonObject>,
     pub foreign_members: Option<JsonObject>,
 }
 
-impl LineStringFeature {
-    pub fn line(&self) -> &LineStringType {
+impl<T> LineStringFeature<T>
+where
+    T: Float,
+{
+    /// The line as GeoJSON positions. They're built from the stored
+    /// coordinates, so this returns an owned value rather than a reference;
+    /// `positions` borrows them instead.
+    pub fn line(&self) -> LineStringType {
+        positions_to_line_string_type(&self.line)
+    }
+
+    pub fn positions(&self) -> &[[T; 2]] {
         &self.line
     }
 
-    pub fn geo_line(&self) -> geo::LineString<f64> {
-        create_geo_line_string(&self.line)
+    pub fn geo_line(&self) -> geo::LineString<T> {
+        positions_to_geo_line_string(&self.line)
     }
 }
 
-impl Into<geojson::Feature> for LineStringFeature {
-    fn into(self) -> geojson::Feature {
-        let geometry = geojson::Geometry::new(geojson::Value::LineString(self.line));
+impl<T> From<LineStringFeature<T>> for geojson::Feature
+where
+    T: Float,
+{
+    fn from(feature: LineStringFeature<T>) -> geojson::Feature {
+        let geometry = geojson::Geometry::new(geojson::Value::LineString(
+            positions_to_line_string_type(&feature.line),
+        ));
 
         geojson::Feature {
-            id: self.id,
-            properties: self.properties,
-            foreign_members: self.foreign_members,
+            id: feature.id,
+            properties: feature.properties,
+            foreign_members: feature.foreign_members,
             geometry: Some(geometry),
-            bbox: Some(self.bbox),
+            bbox: Some(convert_position(&feature.bbox).expect("Bbox can be widened to f64")),
         }
     }
 }
 
-impl TryFrom<geojson::Feature> for LineStringFeature {
+impl<T> TryFrom<geojson::Feature> for LineStringFeature<T>
+where
+    T: Float,
+{
     type Error = GeoJsonConversionError;
 
-    fn try_from(feature: geojson::Feature) -> Result<LineStringFeature, Self::Error> {
-        <Self as GenericFeature<LineStringFeature, LineStringType>>::try_from(feature)
+    fn try_from(feature: geojson::Feature) -> Result<LineStringFeature<T>, Self::Error> {
+        <Self as GenericFeature<LineStringFeature<T>, LineStringType>>::try_from(feature)
     }
 }
 
-impl GenericFeature<LineStringFeature, LineStringType> for LineStringFeature {
+impl<T> GenericFeature<LineStringFeature<T>, LineStringType> for LineStringFeature<T>
+where
+    T: Float,
+{
     fn take_geometry_type(
         feature: &mut geojson::Feature,
     ) -> Result<LineStringType, GeoJsonConversionError> {
@@ -92,12 +119,12 @@ impl GenericFeature<LineStringFeature, LineStringType> for LineStringFeature {
         geometry: &LineStringType,
         feature: &geojson::Feature,
     ) -> Result<(), GeoJsonConversionError> {
-        let euclidean_length: f64 = create_geo_line_string(&geometry).euclidean_length();
-        if (euclidean_length - f64::zero()).abs() < ::std::f64::EPSILON {
-            let id = feature.id.clone();
-            return Err(GeoJsonConversionError::MalformedGeometry(id));
-        }
-        if geometry.iter().any(|p| p.len() != 2) {
+        geometry
+            .iter()
+            .try_for_each(|point| check_position::<T>(point, feature))?;
+
+        let euclidean_length: f64 = create_geo_line_string(geometry)?.euclidean_length();
+        if (euclidean_length - f64::zero()).abs() < f64::EPSILON {
             let id = feature.id.clone();
             return Err(GeoJsonConversionError::MalformedGeometry(id));
         }
@@ -106,7 +133,8 @@ impl GenericFeature<LineStringFeature, LineStringType> for LineStringFeature {
 
     fn compute_bbox(feature: &mut geojson::Feature, geometry: &LineStringType) -> Bbox {
         feature.bbox.take().unwrap_or_else(|| {
-            let geo_linestring = create_geo_line_string(geometry);
+            let geo_linestring =
+                create_geo_line_string(geometry).expect("LineString coordinates were checked");
 
             let maybe_rect = geo_linestring
                 .bounding_rect()
@@ -124,32 +152,54 @@ impl GenericFeature<LineStringFeature, LineStringType> for LineStringFeature {
         feature: geojson::Feature,
         bbox: Bbox,
         geometry: LineStringType,
-    ) -> LineStringFeature {
-        LineStringFeature {
-            bbox,
+    ) -> Result<LineStringFeature<T>, GeoJsonConversionError> {
+        Ok(LineStringFeature {
+            bbox: create_bbox(&bbox, &feature)?,
+            line: create_positions(&geometry)?,
             id: feature.id,
-            line: geometry,
             properties: feature.properties,
             foreign_members: feature.foreign_members,
+        })
+    }
+}
+
+impl<T> SimplifyFeature<T> for LineStringFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn simplified(&self, simplification: &Simplification<T>) -> Self {
+        let line = simplify_line_string(&self.geo_line(), simplification);
+        LineStringFeature {
+            bbox: self.bbox,
+            line: geo_line_string_to_positions(&line),
+            id: self.id.clone(),
+            properties: self.properties.clone(),
+            foreign_members: self.foreign_members.clone(),
         }
     }
 }
 
-impl<'a> GetBbox<'a> for LineStringFeature {
-    fn bbox(&'a self) -> &'a Bbox {
+impl<T> GetBbox<T> for LineStringFeature<T> {
+    fn bbox(&self) -> &[T; 4] {
         &self.bbox
     }
 }
 
-impl RTreeObject for LineStringFeature {
-    type Envelope = AABB<[f64; 2]>;
+impl<T> RTreeObject for LineStringFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
 
     fn envelope(&self) -> Self::Envelope {
-        <Self as GetBbox>::envelope(self)
+        <Self as GetBbox<T>>::envelope(self)
     }
 }
 
-impl PointDistance for LineStringFeature {
+impl<T> PointDistance for LineStringFeature<T>
+where
+    T: Float + RTreeNum,
+{
     fn distance_2(
         &self,
         point: &<Self::Envelope as Envelope>::Point,
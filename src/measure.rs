@@ -0,0 +1,329 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Area, length, centroid, convex hull and representative point of every
+//! feature type, without converting to `geo` types by hand.
+
+use crate::IndexableFeature;
+use geo::{
+    area::Area, centroid::Centroid, chamberlain_duquette_area::ChamberlainDuquetteArea,
+    convexhull::ConvexHull, euclidean_length::EuclideanLength, haversine_length::HaversineLength,
+    Coordinate, Geometry, LineString, MultiPoint, Point, Polygon,
+};
+use num_traits::{Float, FromPrimitive};
+use rstar::RTreeNum;
+use std::iter::Sum;
+
+/// Measures of a feature's geometry.
+///
+/// A geometry collection is measured as a whole: areas and lengths are added
+/// up over its members, and its centroid is that of the members with the
+/// most dimensions, so points don't pull the centroid of polygons. The
+/// `geodesic_` measures take coordinates to be longitude and latitude in
+/// degrees and give meters on a spherical earth.
+pub trait Measure<T>: IndexableFeature<T>
+where
+    T: Float + RTreeNum + FromPrimitive + Sum,
+{
+    /// The planar area of the polygons, holes taken out.
+    fn area(&self) -> T {
+        Components::new(&self.geo_geometry()).area()
+    }
+
+    /// The planar length of the lines. The outlines of polygons aren't
+    /// counted.
+    fn length(&self) -> T {
+        Components::new(&self.geo_geometry()).length()
+    }
+
+    /// The area of the polygons in square meters.
+    fn geodesic_area(&self) -> T {
+        Components::new(&self.geo_geometry())
+            .polygons
+            .iter()
+            .map(geodesic_polygon_area)
+            .sum()
+    }
+
+    /// The haversine length of the lines in meters.
+    fn geodesic_length(&self) -> T {
+        Components::new(&self.geo_geometry())
+            .line_strings
+            .iter()
+            .map(HaversineLength::haversine_length)
+            .sum()
+    }
+
+    /// Always `Some` for a converted feature, as empty geometry collections
+    /// are rejected.
+    fn centroid(&self) -> Option<Point<T>> {
+        Components::new(&self.geo_geometry()).centroid()
+    }
+
+    /// The smallest convex polygon holding the geometry, counter clockwise.
+    /// Always `Some` for a converted feature.
+    fn convex_hull(&self) -> Option<Polygon<T>> {
+        let coordinates = Components::new(&self.geo_geometry()).coordinates();
+        if coordinates.is_empty() {
+            None
+        } else {
+            Some(MultiPoint::from(coordinates).convex_hull())
+        }
+    }
+
+    /// A point that is on the geometry, unlike the centroid of a concave
+    /// polygon or a curved line. Always `Some` for a converted feature.
+    fn representative_point(&self) -> Option<Point<T>> {
+        Components::new(&self.geo_geometry()).representative_point()
+    }
+}
+
+impl<T, F> Measure<T> for F
+where
+    T: Float + RTreeNum + FromPrimitive + Sum,
+    F: IndexableFeature<T>,
+{
+}
+
+/// The points, lines and polygons that make up a geometry.
+struct Components<T>
+where
+    T: Float,
+{
+    points: Vec<Point<T>>,
+    line_strings: Vec<LineString<T>>,
+    polygons: Vec<Polygon<T>>,
+}
+
+impl<T> Components<T>
+where
+    T: Float + FromPrimitive + Sum,
+{
+    fn new(geometry: &Geometry<T>) -> Self {
+        let mut components = Components {
+            points: vec![],
+            line_strings: vec![],
+            polygons: vec![],
+        };
+        components.add(geometry);
+        components
+    }
+
+    fn add(&mut self, geometry: &Geometry<T>) {
+        match geometry {
+            Geometry::Point(p) => self.points.push(*p),
+            Geometry::Line(l) => self.line_strings.push(LineString(vec![l.start, l.end])),
+            Geometry::LineString(l) => self.line_strings.push(l.clone()),
+            Geometry::Polygon(p) => self.polygons.push(p.clone()),
+            Geometry::MultiPoint(p) => self.points.extend(p.0.iter().copied()),
+            Geometry::MultiLineString(l) => self.line_strings.extend(l.0.iter().cloned()),
+            Geometry::MultiPolygon(p) => self.polygons.extend(p.0.iter().cloned()),
+            Geometry::GeometryCollection(g) => g.0.iter().for_each(|g| self.add(g)),
+            Geometry::Rect(r) => self.polygons.push((*r).into()),
+            Geometry::Triangle(t) => self
+                .polygons
+                .push(Polygon::new(LineString(vec![t.0, t.1, t.2, t.0]), vec![])),
+        }
+    }
+
+    fn area(&self) -> T {
+        self.polygons.iter().map(Area::unsigned_area).sum()
+    }
+
+    fn length(&self) -> T {
+        self.line_strings
+            .iter()
+            .map(EuclideanLength::euclidean_length)
+            .sum()
+    }
+
+    /// The lines, along with the rings of polygons that have no area.
+    fn lines(&self) -> impl Iterator<Item = &LineString<T>> {
+        self.line_strings.iter().chain(
+            self.polygons
+                .iter()
+                .filter(|p| p.unsigned_area() == T::zero())
+                .flat_map(|p| std::iter::once(p.exterior()).chain(p.interiors())),
+        )
+    }
+
+    fn coordinates(&self) -> Vec<Coordinate<T>> {
+        self.points
+            .iter()
+            .map(|p| p.0)
+            .chain(self.line_strings.iter().flat_map(|l| l.0.iter().copied()))
+            .chain(
+                self.polygons
+                    .iter()
+                    .flat_map(|p| p.exterior().0.iter().copied()),
+            )
+            .collect()
+    }
+
+    /// The centroid of the polygons weighted by area, or if they have none,
+    /// of the lines weighted by length, or else of all the positions.
+    fn centroid(&self) -> Option<Point<T>> {
+        let polygons = self
+            .polygons
+            .iter()
+            .filter_map(|p| Some((p.centroid()?, p.unsigned_area())));
+        if let Some(centroid) = weighted_centroid(polygons) {
+            return Some(centroid);
+        }
+        let lines = self
+            .lines()
+            .filter_map(|l| Some((l.centroid()?, l.euclidean_length())));
+        if let Some(centroid) = weighted_centroid(lines) {
+            return Some(centroid);
+        }
+        MultiPoint::from(self.coordinates()).centroid()
+    }
+
+    /// A point inside the largest polygon, or if they have no area, half way
+    /// along the longest line, or else the first position.
+    fn representative_point(&self) -> Option<Point<T>> {
+        let largest = self
+            .polygons
+            .iter()
+            .map(|p| (p, p.unsigned_area()))
+            .filter(|(_, area)| *area > T::zero())
+            .fold(
+                None,
+                |largest: Option<(&Polygon<T>, T)>, (p, area)| match largest {
+                    Some((_, most)) if most >= area => largest,
+                    _ => Some((p, area)),
+                },
+            );
+        if let Some(point) = largest.and_then(|(p, _)| interior_point(p)) {
+            return Some(point);
+        }
+        let longest = self
+            .lines()
+            .map(|l| (l, l.euclidean_length()))
+            .filter(|(_, length)| *length > T::zero())
+            .fold(
+                None,
+                |longest: Option<(&LineString<T>, T)>, (l, length)| match longest {
+                    Some((_, most)) if most >= length => longest,
+                    _ => Some((l, length)),
+                },
+            );
+        if let Some((line, length)) = longest {
+            return Some(point_along(line, length / (T::one() + T::one())));
+        }
+        self.coordinates().first().map(|c| Point(*c))
+    }
+}
+
+/// `None` when the weights add up to zero.
+fn weighted_centroid<T, I>(centroids: I) -> Option<Point<T>>
+where
+    T: Float,
+    I: Iterator<Item = (Point<T>, T)>,
+{
+    let (x, y, total) = centroids.fold(
+        (T::zero(), T::zero(), T::zero()),
+        |(x, y, total), (centroid, weight)| {
+            (
+                x + centroid.x() * weight,
+                y + centroid.y() * weight,
+                total + weight,
+            )
+        },
+    );
+    if total > T::zero() {
+        Some(Point::new(x / total, y / total))
+    } else {
+        None
+    }
+}
+
+/// The middle of the widest span of the polygon along a horizontal line
+/// through the middle of its bbox. The line is moved off any vertex so each
+/// edge it meets is crossed cleanly.
+fn interior_point<T>(polygon: &Polygon<T>) -> Option<Point<T>>
+where
+    T: Float,
+{
+    let rings: Vec<&LineString<T>> = std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .collect();
+    let mut ys: Vec<T> = rings
+        .iter()
+        .flat_map(|ring| ring.0.iter().map(|c| c.y))
+        .collect();
+    ys.sort_by(|a, b| a.partial_cmp(b).expect("A coordinate is a number"));
+    ys.dedup();
+    let two = T::one() + T::one();
+    let middle = (*ys.first()? + *ys.last()?) / two;
+    let y = match ys.iter().position(|y| *y >= middle) {
+        Some(i) if ys[i] == middle => (middle + *ys.get(i + 1)?) / two,
+        _ => middle,
+    };
+
+    let mut xs: Vec<T> = rings
+        .iter()
+        .flat_map(|ring| ring.lines())
+        .filter(|line| (line.start.y > y) != (line.end.y > y))
+        .map(|line| {
+            line.start.x
+                + (y - line.start.y) * (line.end.x - line.start.x) / (line.end.y - line.start.y)
+        })
+        .collect();
+    xs.sort_by(|a, b| a.partial_cmp(b).expect("A coordinate is a number"));
+    xs.chunks_exact(2)
+        .max_by(|a, b| {
+            (a[1] - a[0])
+                .partial_cmp(&(b[1] - b[0]))
+                .expect("A width is a number")
+        })
+        .map(|span| Point::new((span[0] + span[1]) / two, y))
+}
+
+/// The point `distance` along `line` from its start.
+fn point_along<T>(line: &LineString<T>, distance: T) -> Point<T>
+where
+    T: Float,
+{
+    let mut remaining = distance;
+    for segment in line.lines() {
+        let length = segment.euclidean_length();
+        if remaining <= length && length > T::zero() {
+            let t = remaining / length;
+            return Point::new(
+                segment.start.x + t * segment.dx(),
+                segment.start.y + t * segment.dy(),
+            );
+        }
+        remaining = remaining - length;
+    }
+    Point(*line.0.last().expect("A measured line has positions"))
+}
+
+/// Each ring is measured on its own so holes are taken out whichever way
+/// round they are.
+fn geodesic_polygon_area<T>(polygon: &Polygon<T>) -> T
+where
+    T: Float + FromPrimitive + Sum,
+{
+    let ring_area = |ring: &LineString<T>| {
+        Polygon::new(ring.clone(), vec![]).chamberlain_duquette_unsigned_area()
+    };
+    polygon
+        .interiors()
+        .iter()
+        .fold(ring_area(polygon.exterior()), |area, hole| {
+            area - ring_area(hole)
+        })
+}
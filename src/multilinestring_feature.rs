@@ -15,60 +15,93 @@
 //! `MultiLineStringFeature` can be used with rstar `RTree`
 
 use crate::{
-    conversion::create_geo_multi_line_string,
+    conversion::{convert_position, create_geo_multi_line_string},
+    coordinates::{positions_to_geo_line_string, Parts},
     error::GeoJsonConversionError,
-    generic::{GenericFeature, GetBbox},
+    generic::{check_position, create_bbox, GenericFeature, GetBbox},
     json::JsonObject,
+    simplify::{simplify_line_string, Simplification, SimplifyFeature},
 };
 use geo::{bounding_rect::BoundingRect, euclidean_distance::EuclideanDistance};
 use geojson::{feature::Id, Bbox, LineStringType};
-use rstar::{Envelope, Point, PointDistance, RTreeObject, AABB};
+use num_traits::Float;
+use rstar::{Envelope, Point, PointDistance, RTreeNum, RTreeObject, AABB};
 use std::convert::TryFrom;
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct MultiLineStringFeature {
-    bbox: Bbox,
-    lines: Vec<LineStringType>,
+pub struct MultiLineStringFeature<T = f64> {
+    bbox: [T; 4],
+    lines: Parts<T>,
     pub id: Option<Id>,
     pub properties: Option<JsonObject>,
     pub foreign_members: Option<JsonObject>,
 }
 
-impl MultiLineStringFeature {
-    pub fn lines(&self) -> &[LineStringType] {
-        &self.lines
+impl<T> MultiLineStringFeature<T>
+where
+    T: Float,
+{
+    /// The lines as GeoJSON positions. They're built from the stored
+    /// coordinates, so this returns an owned value rather than a reference;
+    /// `line_positions` borrows them instead.
+    pub fn lines(&self) -> Vec<LineStringType> {
+        self.lines.to_nested()
     }
 
-    pub fn geo_lines(&self) -> geo::MultiLineString<f64> {
-        create_geo_multi_line_string(&self.lines)
+    /// The positions of each line.
+    pub fn line_positions(&self) -> impl Iterator<Item = &[[T; 2]]> {
+        self.lines.iter()
+    }
+
+    pub(crate) fn line_at(&self, index: usize) -> &[[T; 2]] {
+        self.lines.get(index)
+    }
+
+    pub fn geo_lines(&self) -> geo::MultiLineString<T> {
+        geo::MultiLineString(
+            self.lines
+                .iter()
+                .map(positions_to_geo_line_string)
+                .collect(),
+        )
     }
 }
 
-impl Into<geojson::Feature> for MultiLineStringFeature {
-    fn into(self) -> geojson::Feature {
-        let geometry = geojson::Geometry::new(geojson::Value::MultiLineString(self.lines));
+impl<T> From<MultiLineStringFeature<T>> for geojson::Feature
+where
+    T: Float,
+{
+    fn from(feature: MultiLineStringFeature<T>) -> geojson::Feature {
+        let geometry =
+            geojson::Geometry::new(geojson::Value::MultiLineString(feature.lines.to_nested()));
 
         geojson::Feature {
-            id: self.id,
-            properties: self.properties,
-            foreign_members: self.foreign_members,
+            id: feature.id,
+            properties: feature.properties,
+            foreign_members: feature.foreign_members,
             geometry: Some(geometry),
-            bbox: Some(self.bbox),
+            bbox: Some(convert_position(&feature.bbox).expect("Bbox can be widened to f64")),
         }
     }
 }
 
-impl TryFrom<geojson::Feature> for MultiLineStringFeature {
+impl<T> TryFrom<geojson::Feature> for MultiLineStringFeature<T>
+where
+    T: Float,
+{
     type Error = GeoJsonConversionError;
 
     fn try_from(
         feature: geojson::Feature,
-    ) -> Result<MultiLineStringFeature, GeoJsonConversionError> {
-        <Self as GenericFeature<MultiLineStringFeature, Vec<LineStringType>>>::try_from(feature)
+    ) -> Result<MultiLineStringFeature<T>, GeoJsonConversionError> {
+        <Self as GenericFeature<MultiLineStringFeature<T>, Vec<LineStringType>>>::try_from(feature)
     }
 }
 
-impl GenericFeature<MultiLineStringFeature, Vec<LineStringType>> for MultiLineStringFeature {
+impl<T> GenericFeature<MultiLineStringFeature<T>, Vec<LineStringType>> for MultiLineStringFeature<T>
+where
+    T: Float,
+{
     fn take_geometry_type(
         feature: &mut geojson::Feature,
     ) -> Result<Vec<LineStringType>, GeoJsonConversionError> {
@@ -97,12 +130,16 @@ impl GenericFeature<MultiLineStringFeature, Vec<LineStringType>> for MultiLineSt
             let id = feature.id.clone();
             return Err(GeoJsonConversionError::MalformedGeometry(id));
         }
-        Ok(())
+        geometry
+            .iter()
+            .flatten()
+            .try_for_each(|point| check_position::<T>(point, feature))
     }
 
     fn compute_bbox(feature: &mut geojson::Feature, geometry: &Vec<LineStringType>) -> Bbox {
         feature.bbox.take().unwrap_or_else(|| {
             let maybe_rect = create_geo_multi_line_string(geometry)
+                .expect("MultiLineString coordinates were checked")
                 .bounding_rect()
                 .expect("Was able to create bounding rectangle");
             vec![
@@ -118,37 +155,64 @@ impl GenericFeature<MultiLineStringFeature, Vec<LineStringType>> for MultiLineSt
         feature: geojson::Feature,
         bbox: Bbox,
         geometry: Vec<LineStringType>,
-    ) -> MultiLineStringFeature {
-        MultiLineStringFeature {
+    ) -> Result<MultiLineStringFeature<T>, GeoJsonConversionError> {
+        Ok(MultiLineStringFeature {
+            bbox: create_bbox(&bbox, &feature)?,
+            lines: Parts::try_from_nested(&geometry)?,
             id: feature.id,
-            bbox,
-            lines: geometry,
             properties: feature.properties,
             foreign_members: feature.foreign_members,
+        })
+    }
+}
+
+impl<T> SimplifyFeature<T> for MultiLineStringFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn simplified(&self, simplification: &Simplification<T>) -> Self {
+        let lines: Vec<geo::LineString<T>> = self
+            .geo_lines()
+            .0
+            .iter()
+            .map(|line| simplify_line_string(line, simplification))
+            .collect();
+        MultiLineStringFeature {
+            bbox: self.bbox,
+            lines: Parts::from_geo_line_strings(&lines),
+            id: self.id.clone(),
+            properties: self.properties.clone(),
+            foreign_members: self.foreign_members.clone(),
         }
     }
 }
 
-impl<'a> GetBbox<'a> for MultiLineStringFeature {
-    fn bbox(&'a self) -> &'a Bbox {
+impl<T> GetBbox<T> for MultiLineStringFeature<T> {
+    fn bbox(&self) -> &[T; 4] {
         &self.bbox
     }
 }
 
-impl RTreeObject for MultiLineStringFeature {
-    type Envelope = AABB<[f64; 2]>;
+impl<T> RTreeObject for MultiLineStringFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
 
     fn envelope(&self) -> Self::Envelope {
-        <Self as GetBbox>::envelope(self)
+        <Self as GetBbox<T>>::envelope(self)
     }
 }
 
-impl PointDistance for MultiLineStringFeature {
+impl<T> PointDistance for MultiLineStringFeature<T>
+where
+    T: Float + RTreeNum,
+{
     fn distance_2(
         &self,
         point: &<Self::Envelope as Envelope>::Point,
     ) -> <<Self::Envelope as Envelope>::Point as Point>::Scalar {
-        let p: geo::Point<f64> = (*point).into();
+        let p: geo::Point<T> = (*point).into();
         self.geo_lines().euclidean_distance(&p).powi(2)
     }
 }
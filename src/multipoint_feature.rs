@@ -15,58 +15,84 @@
 //! `MultiPointFeature` can be used with `rstar::RTree` and carry along the information from the `GeoJson`
 
 use crate::{
-    conversion::create_geo_multi_point,
+    conversion::{convert_position, create_geo_multi_point},
+    coordinates::{create_positions, positions_to_line_string_type},
     error::GeoJsonConversionError,
-    generic::{GenericFeature, GetBbox},
+    generic::{check_position, create_bbox, GenericFeature, GetBbox},
     json::JsonObject,
 };
 use geo::{bounding_rect::BoundingRect, euclidean_distance::EuclideanDistance};
 use geojson::{feature::Id, Bbox, PointType};
-use rstar::{Envelope, Point, PointDistance, RTreeObject, AABB};
+use num_traits::Float;
+use rstar::{Envelope, Point, PointDistance, RTreeNum, RTreeObject, AABB};
 use std::convert::TryFrom;
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct MultiPointFeature {
-    bbox: Bbox,
-    points: Vec<PointType>,
+pub struct MultiPointFeature<T = f64> {
+    bbox: [T; 4],
+    points: Vec<[T; 2]>,
     pub id: Option<Id>,
     pub properties: Option<JsonObject>,
     pub foreign_members: Option<JsonObject>,
 }
 
-impl MultiPointFeature {
-    pub fn points(&self) -> &[PointType] {
+impl<T> MultiPointFeature<T>
+where
+    T: Float,
+{
+    /// The points as GeoJSON positions. They're built from the stored
+    /// coordinates, so this returns an owned value rather than a reference;
+    /// `positions` borrows them instead.
+    pub fn points(&self) -> Vec<PointType> {
+        positions_to_line_string_type(&self.points)
+    }
+
+    pub fn positions(&self) -> &[[T; 2]] {
         &self.points
     }
 
-    pub fn geo_points(&self) -> geo::MultiPoint<f64> {
-        create_geo_multi_point(&self.points)
+    pub fn geo_points(&self) -> geo::MultiPoint<T> {
+        self.points
+            .iter()
+            .map(|p| geo::Point::new(p[0], p[1]))
+            .collect()
     }
 }
 
-impl Into<geojson::Feature> for MultiPointFeature {
-    fn into(self) -> geojson::Feature {
-        let geometry = geojson::Geometry::new(geojson::Value::MultiPoint(self.points));
+impl<T> From<MultiPointFeature<T>> for geojson::Feature
+where
+    T: Float,
+{
+    fn from(feature: MultiPointFeature<T>) -> geojson::Feature {
+        let geometry = geojson::Geometry::new(geojson::Value::MultiPoint(
+            positions_to_line_string_type(&feature.points),
+        ));
 
         geojson::Feature {
-            id: self.id,
-            properties: self.properties,
-            foreign_members: self.foreign_members,
+            id: feature.id,
+            properties: feature.properties,
+            foreign_members: feature.foreign_members,
             geometry: Some(geometry),
-            bbox: Some(self.bbox),
+            bbox: Some(convert_position(&feature.bbox).expect("Bbox can be widened to f64")),
         }
     }
 }
 
-impl TryFrom<geojson::Feature> for MultiPointFeature {
+impl<T> TryFrom<geojson::Feature> for MultiPointFeature<T>
+where
+    T: Float,
+{
     type Error = GeoJsonConversionError;
 
-    fn try_from(feature: geojson::Feature) -> Result<MultiPointFeature, GeoJsonConversionError> {
-        <Self as GenericFeature<MultiPointFeature, Vec<PointType>>>::try_from(feature)
+    fn try_from(feature: geojson::Feature) -> Result<MultiPointFeature<T>, GeoJsonConversionError> {
+        <Self as GenericFeature<MultiPointFeature<T>, Vec<PointType>>>::try_from(feature)
     }
 }
 
-impl GenericFeature<MultiPointFeature, Vec<PointType>> for MultiPointFeature {
+impl<T> GenericFeature<MultiPointFeature<T>, Vec<PointType>> for MultiPointFeature<T>
+where
+    T: Float,
+{
     fn take_geometry_type(
         feature: &mut geojson::Feature,
     ) -> Result<Vec<PointType>, GeoJsonConversionError> {
@@ -91,16 +117,19 @@ impl GenericFeature<MultiPointFeature, Vec<PointType>> for MultiPointFeature {
         geometry: &Vec<PointType>,
         feature: &geojson::Feature,
     ) -> Result<(), GeoJsonConversionError> {
-        if geometry.is_empty() || geometry.iter().any(|g| g.len() != 2) {
+        if geometry.is_empty() {
             let id = feature.id.clone();
             return Err(GeoJsonConversionError::MalformedGeometry(id));
         }
-        Ok(())
+        geometry
+            .iter()
+            .try_for_each(|point| check_position::<T>(point, feature))
     }
 
     fn compute_bbox(feature: &mut geojson::Feature, geometry: &Vec<PointType>) -> Bbox {
         feature.bbox.take().unwrap_or_else(|| {
             let bounding = create_geo_multi_point(geometry)
+                .expect("MultiPoint coordinates were checked")
                 .bounding_rect()
                 .expect("Geo multi point had no bounding rectangle");
             vec![
@@ -116,37 +145,43 @@ impl GenericFeature<MultiPointFeature, Vec<PointType>> for MultiPointFeature {
         feature: geojson::Feature,
         bbox: Bbox,
         geometry: Vec<PointType>,
-    ) -> MultiPointFeature {
-        MultiPointFeature {
-            bbox,
+    ) -> Result<MultiPointFeature<T>, GeoJsonConversionError> {
+        Ok(MultiPointFeature {
+            bbox: create_bbox(&bbox, &feature)?,
+            points: create_positions(&geometry)?,
             id: feature.id,
-            points: geometry,
             properties: feature.properties,
             foreign_members: feature.foreign_members,
-        }
+        })
     }
 }
 
-impl<'a> GetBbox<'a> for MultiPointFeature {
-    fn bbox(&'a self) -> &'a Bbox {
+impl<T> GetBbox<T> for MultiPointFeature<T> {
+    fn bbox(&self) -> &[T; 4] {
         &self.bbox
     }
 }
 
-impl RTreeObject for MultiPointFeature {
-    type Envelope = AABB<[f64; 2]>;
+impl<T> RTreeObject for MultiPointFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
 
     fn envelope(&self) -> Self::Envelope {
-        <Self as GetBbox>::envelope(self)
+        <Self as GetBbox<T>>::envelope(self)
     }
 }
 
-impl PointDistance for MultiPointFeature {
+impl<T> PointDistance for MultiPointFeature<T>
+where
+    T: Float + RTreeNum,
+{
     fn distance_2(
         &self,
         point: &<Self::Envelope as Envelope>::Point,
     ) -> <<Self::Envelope as Envelope>::Point as Point>::Scalar {
-        let p: geo::Point<f64> = (*point).into();
+        let p: geo::Point<T> = (*point).into();
         self.geo_points().euclidean_distance(&p).powi(2)
     }
 }
@@ -15,58 +15,117 @@
 //! `MultiPolygonFeature` can be used with `rstar::RTree` and carry along the information from the `GeoJson`
 
 use crate::{
-    conversion::create_geo_multi_polygon,
+    conversion::{convert_position, create_geo_multi_polygon},
+    coordinates::{polygon_distance_2, positions_to_line_string_type, rings_to_geo_polygon, Parts},
     error::GeoJsonConversionError,
-    generic::{GenericFeature, GetBbox},
+    generic::{check_position, create_bbox, GenericFeature, GetBbox},
     json::JsonObject,
+    simplify::{simplify_multi_polygon, Simplification, SimplifyFeature},
 };
-use geo::{bounding_rect::BoundingRect, euclidean_distance::EuclideanDistance};
+use geo::bounding_rect::BoundingRect;
 use geojson::{feature::Id, Bbox, PolygonType};
-use rstar::{Envelope, Point, PointDistance, RTreeObject, AABB};
+use num_traits::Float;
+use rstar::{Envelope, Point, PointDistance, RTreeNum, RTreeObject, AABB};
 use std::convert::TryFrom;
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct MultiPolygonFeature {
-    bbox: Bbox,
-    polygons: Vec<PolygonType>,
+pub struct MultiPolygonFeature<T = f64> {
+    bbox: [T; 4],
+    rings: Parts<T>,
+    polygon_ends: Vec<usize>,
     pub id: Option<Id>,
     pub properties: Option<JsonObject>,
     pub foreign_members: Option<JsonObject>,
 }
 
-impl MultiPolygonFeature {
-    pub fn polygons(&self) -> &[PolygonType] {
-        &self.polygons
+impl<T> MultiPolygonFeature<T>
+where
+    T: Float,
+{
+    /// The polygons as GeoJSON positions. They're built from the stored
+    /// coordinates, so this returns an owned value rather than a reference;
+    /// `rings` borrows them instead.
+    pub fn polygons(&self) -> Vec<PolygonType> {
+        self.polygon_rings()
+            .map(|(start, end)| {
+                self.rings
+                    .range(start, end)
+                    .map(positions_to_line_string_type)
+                    .collect()
+            })
+            .collect()
     }
 
-    pub fn geo_polygons(&self) -> geo::MultiPolygon<f64> {
-        create_geo_multi_polygon(&self.polygons)
+    pub fn geo_polygons(&self) -> geo::MultiPolygon<T> {
+        geo::MultiPolygon(
+            self.polygon_rings()
+                .map(|(start, end)| rings_to_geo_polygon(self.rings.range(start, end)))
+                .collect(),
+        )
+    }
+
+    /// The positions of each ring of the polygon at `index`, the exterior
+    /// first.
+    pub fn rings(&self, index: usize) -> impl Iterator<Item = &[[T; 2]]> {
+        let start = if index == 0 {
+            0
+        } else {
+            self.polygon_ends[index - 1]
+        };
+        self.rings.range(start, self.polygon_ends[index])
+    }
+
+    /// The number of polygons.
+    pub fn len(&self) -> usize {
+        self.polygon_ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.polygon_ends.is_empty()
+    }
+
+    /// The `start..end` range of rings for each polygon.
+    fn polygon_rings(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.polygon_ends.iter().scan(0, |start, &end| {
+            let range = (*start, end);
+            *start = end;
+            Some(range)
+        })
     }
 }
 
-impl Into<geojson::Feature> for MultiPolygonFeature {
-    fn into(self) -> geojson::Feature {
-        let geometry = geojson::Geometry::new(geojson::Value::MultiPolygon(self.polygons));
+impl<T> From<MultiPolygonFeature<T>> for geojson::Feature
+where
+    T: Float,
+{
+    fn from(feature: MultiPolygonFeature<T>) -> geojson::Feature {
+        let geometry = geojson::Geometry::new(geojson::Value::MultiPolygon(feature.polygons()));
 
         geojson::Feature {
-            id: self.id,
-            properties: self.properties,
-            foreign_members: self.foreign_members,
+            id: feature.id,
+            properties: feature.properties,
+            foreign_members: feature.foreign_members,
             geometry: Some(geometry),
-            bbox: Some(self.bbox),
+            bbox: Some(convert_position(&feature.bbox).expect("Bbox can be widened to f64")),
         }
     }
 }
 
-impl TryFrom<geojson::Feature> for MultiPolygonFeature {
+impl<T> TryFrom<geojson::Feature> for MultiPolygonFeature<T>
+where
+    T: Float,
+{
     type Error = GeoJsonConversionError;
 
     fn try_from(feature: geojson::Feature) -> Result<Self, Self::Error> {
-        <Self as GenericFeature<MultiPolygonFeature, Vec<PolygonType>>>::try_from(feature)
+        <Self as GenericFeature<MultiPolygonFeature<T>, Vec<PolygonType>>>::try_from(feature)
     }
 }
 
-impl GenericFeature<MultiPolygonFeature, Vec<PolygonType>> for MultiPolygonFeature {
+impl<T> GenericFeature<MultiPolygonFeature<T>, Vec<PolygonType>> for MultiPolygonFeature<T>
+where
+    T: Float,
+{
     fn take_geometry_type(
         feature: &mut geojson::Feature,
     ) -> Result<Vec<PolygonType>, GeoJsonConversionError> {
@@ -97,12 +156,17 @@ impl GenericFeature<MultiPolygonFeature, Vec<PolygonType>> for MultiPolygonFeatu
             let id = feature.id.clone();
             return Err(GeoJsonConversionError::MalformedGeometry(id));
         }
-        Ok(())
+        geometries
+            .iter()
+            .flatten()
+            .flatten()
+            .try_for_each(|point| check_position::<T>(point, feature))
     }
 
     fn compute_bbox(feature: &mut geojson::Feature, geometries: &Vec<PolygonType>) -> Bbox {
         feature.bbox.take().unwrap_or_else(|| {
             let bounding = create_geo_multi_polygon(geometries)
+                .expect("MultiPolygon coordinates were checked")
                 .bounding_rect()
                 .expect("Geo multipolygon had to bounding rectangle");
             vec![
@@ -118,37 +182,75 @@ impl GenericFeature<MultiPolygonFeature, Vec<PolygonType>> for MultiPolygonFeatu
         feature: geojson::Feature,
         bbox: Bbox,
         geometry: Vec<PolygonType>,
-    ) -> MultiPolygonFeature {
-        MultiPolygonFeature {
-            bbox,
+    ) -> Result<MultiPolygonFeature<T>, GeoJsonConversionError> {
+        Ok(MultiPolygonFeature {
+            bbox: create_bbox(&bbox, &feature)?,
+            rings: Parts::try_from_nested(geometry.iter().flatten())?,
+            polygon_ends: geometry
+                .iter()
+                .scan(0, |end, polygon| {
+                    *end += polygon.len();
+                    Some(*end)
+                })
+                .collect(),
             id: feature.id,
-            polygons: geometry,
             properties: feature.properties,
             foreign_members: feature.foreign_members,
+        })
+    }
+}
+
+impl<T> SimplifyFeature<T> for MultiPolygonFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn simplified(&self, simplification: &Simplification<T>) -> Self {
+        let polygons = simplify_multi_polygon(&self.geo_polygons(), simplification).0;
+        let mut polygon_ends = vec![];
+        let mut rings = vec![];
+        for polygon in &polygons {
+            rings.push(polygon.exterior());
+            rings.extend(polygon.interiors());
+            polygon_ends.push(rings.len());
+        }
+        MultiPolygonFeature {
+            bbox: self.bbox,
+            rings: Parts::from_geo_line_strings(rings),
+            polygon_ends,
+            id: self.id.clone(),
+            properties: self.properties.clone(),
+            foreign_members: self.foreign_members.clone(),
         }
     }
 }
 
-impl<'a> GetBbox<'a> for MultiPolygonFeature {
-    fn bbox(&'a self) -> &'a Bbox {
+impl<T> GetBbox<T> for MultiPolygonFeature<T> {
+    fn bbox(&self) -> &[T; 4] {
         &self.bbox
     }
 }
 
-impl RTreeObject for MultiPolygonFeature {
-    type Envelope = AABB<[f64; 2]>;
+impl<T> RTreeObject for MultiPolygonFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
 
     fn envelope(&self) -> Self::Envelope {
-        <Self as GetBbox>::envelope(self)
+        <Self as GetBbox<T>>::envelope(self)
     }
 }
 
-impl PointDistance for MultiPolygonFeature {
+impl<T> PointDistance for MultiPolygonFeature<T>
+where
+    T: Float + RTreeNum,
+{
     fn distance_2(
         &self,
         point: &<Self::Envelope as Envelope>::Point,
     ) -> <<Self::Envelope as Envelope>::Point as Point>::Scalar {
-        let p: geo::Point<f64> = (*point).into();
-        self.geo_polygons().euclidean_distance(&p).powi(2)
+        self.polygon_rings()
+            .map(|(start, end)| polygon_distance_2(self.rings.range(start, end), point))
+            .fold(T::infinity(), T::min)
     }
 }
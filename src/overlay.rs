@@ -0,0 +1,429 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Intersecting polygons of any shape, holes included, for clipping polygon
+//! features to a polygon.
+//!
+//! The rings of both polygons are turned so their interior is on the left,
+//! then cut wherever they cross or touch. The pieces of each polygon inside
+//! the other are the boundary of the intersection, along with the pieces the
+//! two share where both interiors are on the same side. Those pieces are
+//! joined back into rings, the counter clockwise ones becoming exteriors and
+//! the clockwise ones their holes.
+
+use crate::coordinates::{ring_contains, side, signed_area};
+use num_traits::Float;
+use rstar::{Envelope, RTree, RTreeNum, RTreeObject, AABB};
+use std::collections::{HashMap, HashSet};
+
+/// The rings of a polygon, each closed, the first being the exterior.
+pub(crate) type Rings<T> = Vec<Vec<[T; 2]>>;
+
+/// A polygon that other polygons can be intersected with.
+pub(crate) struct Overlay<T>
+where
+    T: Float + RTreeNum,
+{
+    edges: RTree<IndexedEdge<T>>,
+}
+
+impl<T> Overlay<T>
+where
+    T: Float + RTreeNum,
+{
+    pub(crate) fn new(rings: &[Vec<[T; 2]>]) -> Self {
+        Overlay {
+            edges: RTree::bulk_load(
+                edges(&oriented(rings))
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, edge)| IndexedEdge { index, edge })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The polygons making up the part of `rings` inside this polygon.
+    pub(crate) fn intersection(&self, rings: &[Vec<[T; 2]>]) -> Vec<Rings<T>> {
+        let subject = oriented(rings);
+        let subject_edges = edges(&subject);
+        if subject_edges.is_empty() {
+            return vec![];
+        }
+        let envelope = subject_edges.iter().fold(
+            AABB::from_point(subject_edges[0].start),
+            |envelope, edge| envelope.merged(&edge.envelope()),
+        );
+        // Only the edges near the subject can be inside it.
+        let clip_edges: Vec<&IndexedEdge<T>> = self
+            .edges
+            .locate_in_envelope_intersecting(&envelope)
+            .collect();
+        let clip_index: HashMap<usize, usize> = clip_edges
+            .iter()
+            .enumerate()
+            .map(|(position, edge)| (edge.index, position))
+            .collect();
+
+        let mut subject_cuts = vec![vec![]; subject_edges.len()];
+        let mut clip_cuts = vec![vec![]; clip_edges.len()];
+        for (index, edge) in subject_edges.iter().enumerate() {
+            for other in self.edges.locate_in_envelope_intersecting(&edge.envelope()) {
+                cut(
+                    edge,
+                    &other.edge,
+                    &mut subject_cuts[index],
+                    &mut clip_cuts[clip_index[&other.index]],
+                );
+            }
+        }
+        let subject_pieces = pieces(subject_edges.iter().copied().zip(subject_cuts));
+        let clip_pieces = pieces(clip_edges.iter().map(|edge| edge.edge).zip(clip_cuts));
+
+        let subject_keys: HashSet<_> = subject_pieces.iter().map(Edge::key).collect();
+        let clip_keys: HashSet<_> = clip_pieces.iter().map(Edge::key).collect();
+        let mut kept = vec![];
+        for piece in subject_pieces {
+            if clip_keys.contains(&piece.key()) {
+                // Shared, with both interiors on its left.
+                kept.push(piece);
+            } else if !clip_keys.contains(&piece.reversed().key()) && self.contains(&piece.middle())
+            {
+                kept.push(piece);
+            }
+        }
+        for piece in clip_pieces {
+            let shared = subject_keys.contains(&piece.key())
+                || subject_keys.contains(&piece.reversed().key());
+            if !shared && contains(&subject, &piece.middle()) {
+                kept.push(piece);
+            }
+        }
+        assemble(join(kept))
+    }
+
+    /// Whether `position` is inside, counting the edges a ray to the right
+    /// of it crosses.
+    fn contains(&self, position: &[T; 2]) -> bool {
+        let ray = AABB::from_corners(*position, [T::infinity(), position[1]]);
+        self.edges
+            .locate_in_envelope_intersecting(&ray)
+            .filter(|edge| {
+                let (a, b) = (edge.edge.start, edge.edge.end);
+                (a[1] > position[1]) != (b[1] > position[1])
+                    && position[0] < a[0] + (position[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0])
+            })
+            .count()
+            % 2
+            == 1
+    }
+}
+
+/// One edge of a ring, with the interior of its polygon on the left.
+#[derive(Clone, Copy, Debug)]
+struct Edge<T> {
+    start: [T; 2],
+    end: [T; 2],
+}
+
+type Key = ((u64, u64), (u64, u64));
+
+impl<T> Edge<T>
+where
+    T: Float,
+{
+    fn key(&self) -> Key {
+        (position_key(self.start), position_key(self.end))
+    }
+
+    fn reversed(&self) -> Edge<T> {
+        Edge {
+            start: self.end,
+            end: self.start,
+        }
+    }
+
+    fn middle(&self) -> [T; 2] {
+        let two = T::one() + T::one();
+        [
+            (self.start[0] + self.end[0]) / two,
+            (self.start[1] + self.end[1]) / two,
+        ]
+    }
+}
+
+impl<T> RTreeObject for Edge<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_corners(self.start, self.end)
+    }
+}
+
+/// An edge of the clip polygon, numbered so each query can keep its cuts.
+struct IndexedEdge<T> {
+    index: usize,
+    edge: Edge<T>,
+}
+
+impl<T> RTreeObject for IndexedEdge<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.edge.envelope()
+    }
+}
+
+/// Positions are matched exactly, with zero and negative zero the same.
+fn position_key<T>(position: [T; 2]) -> (u64, u64)
+where
+    T: Float,
+{
+    let bits = |c: T| (c.to_f64().expect("A float widens to f64") + 0.0).to_bits();
+    (bits(position[0]), bits(position[1]))
+}
+
+/// The rings with repeated positions removed and those without area left
+/// out, the exterior turned counter clockwise and the holes clockwise. A
+/// polygon whose exterior has no area is left with no rings.
+fn oriented<T>(rings: &[Vec<[T; 2]>]) -> Rings<T>
+where
+    T: Float,
+{
+    let mut oriented = vec![];
+    for (index, ring) in rings.iter().enumerate() {
+        let mut ring = ring.clone();
+        ring.dedup();
+        if ring.first() != ring.last() {
+            ring.extend(ring.first().copied());
+        }
+        let area = signed_area(&ring);
+        if ring.len() < 4 || area == T::zero() {
+            if index == 0 {
+                return vec![];
+            }
+            continue;
+        }
+        if (index == 0) != (area > T::zero()) {
+            ring.reverse();
+        }
+        oriented.push(ring);
+    }
+    oriented
+}
+
+fn edges<T>(rings: &[Vec<[T; 2]>]) -> Vec<Edge<T>>
+where
+    T: Float,
+{
+    rings
+        .iter()
+        .flat_map(|ring| ring.windows(2))
+        .map(|pair| Edge {
+            start: pair[0],
+            end: pair[1],
+        })
+        .collect()
+}
+
+/// Adds where the edges `a` and `b` cross or touch to the positions each is
+/// to be cut at. A crossing is computed once, so both get the same position.
+fn cut<T>(a: &Edge<T>, b: &Edge<T>, a_cuts: &mut Vec<[T; 2]>, b_cuts: &mut Vec<[T; 2]>)
+where
+    T: Float,
+{
+    let opposite =
+        |x: T, y: T| (x > T::zero() && y < T::zero()) || (x < T::zero() && y > T::zero());
+    let a_start = side(b.start, b.end, a.start);
+    let a_end = side(b.start, b.end, a.end);
+    if opposite(a_start, a_end)
+        && opposite(side(a.start, a.end, b.start), side(a.start, a.end, b.end))
+    {
+        let t = a_start / (a_start - a_end);
+        let crossing = [
+            a.start[0] + t * (a.end[0] - a.start[0]),
+            a.start[1] + t * (a.end[1] - a.start[1]),
+        ];
+        a_cuts.push(crossing);
+        b_cuts.push(crossing);
+        return;
+    }
+    for position in [b.start, b.end] {
+        if on_edge(a, position) {
+            a_cuts.push(position);
+        }
+    }
+    for position in [a.start, a.end] {
+        if on_edge(b, position) {
+            b_cuts.push(position);
+        }
+    }
+}
+
+/// Whether `position` is on `edge`, other than at its ends.
+fn on_edge<T>(edge: &Edge<T>, position: [T; 2]) -> bool
+where
+    T: Float,
+{
+    let between = |axis: usize| {
+        position[axis] >= edge.start[axis].min(edge.end[axis])
+            && position[axis] <= edge.start[axis].max(edge.end[axis])
+    };
+    position != edge.start
+        && position != edge.end
+        && side(edge.start, edge.end, position) == T::zero()
+        && between(0)
+        && between(1)
+}
+
+/// Cuts each edge into pieces at its cuts.
+fn pieces<T, I>(edges: I) -> Vec<Edge<T>>
+where
+    T: Float,
+    I: Iterator<Item = (Edge<T>, Vec<[T; 2]>)>,
+{
+    let mut pieces = vec![];
+    for (edge, mut cuts) in edges {
+        let along = |p: &[T; 2]| {
+            (p[0] - edge.start[0]) * (edge.end[0] - edge.start[0])
+                + (p[1] - edge.start[1]) * (edge.end[1] - edge.start[1])
+        };
+        cuts.retain(|cut| *cut != edge.start && *cut != edge.end);
+        cuts.sort_by(|a, b| along(a).partial_cmp(&along(b)).expect("A cut is a number"));
+        cuts.dedup();
+        let positions: Vec<[T; 2]> = std::iter::once(edge.start)
+            .chain(cuts)
+            .chain(std::iter::once(edge.end))
+            .collect();
+        pieces.extend(positions.windows(2).map(|pair| Edge {
+            start: pair[0],
+            end: pair[1],
+        }));
+    }
+    pieces
+}
+
+/// Whether `position` is inside the polygon made of `rings`.
+fn contains<T>(rings: &[Vec<[T; 2]>], position: &[T; 2]) -> bool
+where
+    T: Float,
+{
+    rings
+        .iter()
+        .filter(|ring| ring_contains(ring, position))
+        .count()
+        % 2
+        == 1
+}
+
+/// Joins the pieces into closed rings. Where several pieces leave the same
+/// position, the one turning furthest left is taken, which keeps polygons
+/// that only touch at a corner apart.
+fn join<T>(pieces: Vec<Edge<T>>) -> Rings<T>
+where
+    T: Float,
+{
+    let mut leaving: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (index, piece) in pieces.iter().enumerate() {
+        leaving
+            .entry(position_key(piece.start))
+            .or_default()
+            .push(index);
+    }
+    let angle = |from: [T; 2], to: [T; 2]| (to[1] - from[1]).atan2(to[0] - from[0]);
+    let full_turn = T::from(std::f64::consts::PI * 2.0).expect("A constant fits in T");
+
+    let mut used = vec![false; pieces.len()];
+    let mut rings = vec![];
+    for first in 0..pieces.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let mut ring = vec![pieces[first].start];
+        let mut current = pieces[first];
+        loop {
+            ring.push(current.end);
+            if current.end == ring[0] {
+                rings.push(ring);
+                break;
+            }
+            let back = angle(current.end, current.start);
+            let next = leaving
+                .get(&position_key(current.end))
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|index| !used[*index])
+                .max_by(|a, b| {
+                    let turn = |index: &usize| {
+                        let turn = angle(pieces[*index].start, pieces[*index].end) - back;
+                        if turn < T::zero() {
+                            turn + full_turn
+                        } else {
+                            turn
+                        }
+                    };
+                    turn(a).partial_cmp(&turn(b)).expect("An angle is a number")
+                });
+            match next {
+                Some(index) => {
+                    used[index] = true;
+                    current = pieces[index];
+                }
+                // The pieces didn't meet up, which rounding can cause.
+                None => break,
+            }
+        }
+    }
+    rings
+}
+
+/// Makes polygons of the counter clockwise rings, each with the clockwise
+/// rings inside it as holes.
+fn assemble<T>(rings: Rings<T>) -> Vec<Rings<T>>
+where
+    T: Float,
+{
+    let (exteriors, holes): (Rings<T>, Rings<T>) = rings
+        .into_iter()
+        .filter(|ring| ring.len() >= 4 && signed_area(ring) != T::zero())
+        .partition(|ring| signed_area(ring) > T::zero());
+    let mut polygons: Vec<Rings<T>> = exteriors.into_iter().map(|ring| vec![ring]).collect();
+    for hole in holes {
+        let inside = Edge {
+            start: hole[0],
+            end: hole[1],
+        }
+        .middle();
+        let smallest = polygons
+            .iter_mut()
+            .filter(|polygon| ring_contains(&polygon[0], &inside))
+            .min_by(|a, b| {
+                signed_area(&a[0])
+                    .partial_cmp(&signed_area(&b[0]))
+                    .expect("An area is a number")
+            });
+        if let Some(polygon) = smallest {
+            polygon.push(hole);
+        }
+    }
+    polygons
+}
@@ -15,58 +15,84 @@
 //! `PointFeature` can be used with `rstar::RTree` and carry along the information from the `GeoJson`
 
 use crate::{
-    conversion::create_geo_point,
+    conversion::convert_position,
+    coordinates::{create_position, position_to_point_type},
     error::GeoJsonConversionError,
-    generic::{GenericFeature, GetBbox},
+    generic::{check_position, create_bbox, GenericFeature, GetBbox},
     json::JsonObject,
 };
 use geojson::{feature::Id, Bbox, PointType};
-use rstar::{Envelope, Point, PointDistance, RTreeObject, AABB};
+use num_traits::Float;
+use rstar::{Envelope, Point, PointDistance, RTreeNum, RTreeObject, AABB};
 use std::convert::TryFrom;
 
 /// `PointFeature` has `TryFrom<geojson::Feature>` and can be used with `RTree`
+///
+/// Coordinates are stored as `T`, so `PointFeature<f32>` can be used to halve
+/// the memory of an index where `f32` precision is enough.
 #[derive(Clone, Debug, PartialEq)]
-pub struct PointFeature {
-    bbox: Bbox,
-    point: PointType,
+pub struct PointFeature<T = f64> {
+    bbox: [T; 4],
+    point: [T; 2],
     pub id: Option<Id>,
     pub properties: Option<JsonObject>,
     pub foreign_members: Option<JsonObject>,
 }
 
-impl PointFeature {
-    pub fn point(&self) -> &PointType {
+impl<T> PointFeature<T>
+where
+    T: Float,
+{
+    /// The point as a GeoJSON position. It's built from the stored
+    /// coordinates, so this returns an owned value rather than a reference;
+    /// `position` borrows it instead.
+    pub fn point(&self) -> PointType {
+        position_to_point_type(&self.point)
+    }
+
+    pub fn position(&self) -> &[T; 2] {
         &self.point
     }
 
-    pub fn geo_point(&self) -> geo::Point<f64> {
-        create_geo_point(&self.point)
+    pub fn geo_point(&self) -> geo::Point<T> {
+        geo::Point::new(self.point[0], self.point[1])
     }
 }
 
-impl Into<geojson::Feature> for PointFeature {
-    fn into(self) -> geojson::Feature {
-        let geometry = geojson::Geometry::new(geojson::Value::Point(self.point));
+impl<T> From<PointFeature<T>> for geojson::Feature
+where
+    T: Float,
+{
+    fn from(feature: PointFeature<T>) -> geojson::Feature {
+        let geometry = geojson::Geometry::new(geojson::Value::Point(position_to_point_type(
+            &feature.point,
+        )));
 
         geojson::Feature {
-            id: self.id,
-            properties: self.properties,
-            foreign_members: self.foreign_members,
+            id: feature.id,
+            properties: feature.properties,
+            foreign_members: feature.foreign_members,
             geometry: Some(geometry),
-            bbox: Some(self.bbox),
+            bbox: Some(convert_position(&feature.bbox).expect("Bbox can be widened to f64")),
         }
     }
 }
 
-impl TryFrom<geojson::Feature> for PointFeature {
+impl<T> TryFrom<geojson::Feature> for PointFeature<T>
+where
+    T: Float,
+{
     type Error = GeoJsonConversionError;
 
-    fn try_from(feature: geojson::Feature) -> Result<PointFeature, GeoJsonConversionError> {
-        <Self as GenericFeature<PointFeature, PointType>>::try_from(feature)
+    fn try_from(feature: geojson::Feature) -> Result<PointFeature<T>, GeoJsonConversionError> {
+        <Self as GenericFeature<PointFeature<T>, PointType>>::try_from(feature)
     }
 }
 
-impl GenericFeature<PointFeature, PointType> for PointFeature {
+impl<T> GenericFeature<PointFeature<T>, PointType> for PointFeature<T>
+where
+    T: Float,
+{
     fn take_geometry_type(
         feature: &mut geojson::Feature,
     ) -> Result<PointType, GeoJsonConversionError> {
@@ -91,11 +117,7 @@ impl GenericFeature<PointFeature, PointType> for PointFeature {
         geometry: &PointType,
         feature: &geojson::Feature,
     ) -> Result<(), GeoJsonConversionError> {
-        if geometry.len() != 2 {
-            let id = feature.id.clone();
-            return Err(GeoJsonConversionError::MalformedGeometry(id));
-        }
-        Ok(())
+        check_position::<T>(geometry, feature)
     }
 
     fn compute_bbox(feature: &mut geojson::Feature, geometry: &PointType) -> Bbox {
@@ -105,32 +127,42 @@ impl GenericFeature<PointFeature, PointType> for PointFeature {
             .unwrap_or_else(|| vec![geometry[0], geometry[1], geometry[0], geometry[1]])
     }
 
-    fn create_self(feature: geojson::Feature, bbox: Bbox, geometry: PointType) -> PointFeature {
-        PointFeature {
-            bbox,
+    fn create_self(
+        feature: geojson::Feature,
+        bbox: Bbox,
+        geometry: PointType,
+    ) -> Result<PointFeature<T>, GeoJsonConversionError> {
+        Ok(PointFeature {
+            bbox: create_bbox(&bbox, &feature)?,
+            point: create_position(&geometry)?,
             id: feature.id,
-            point: geometry,
             properties: feature.properties,
             foreign_members: feature.foreign_members,
-        }
+        })
     }
 }
 
-impl<'a> GetBbox<'a> for PointFeature {
-    fn bbox(&'a self) -> &'a Bbox {
+impl<T> GetBbox<T> for PointFeature<T> {
+    fn bbox(&self) -> &[T; 4] {
         &self.bbox
     }
 }
 
-impl RTreeObject for PointFeature {
-    type Envelope = AABB<[f64; 2]>;
+impl<T> RTreeObject for PointFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
 
     fn envelope(&self) -> Self::Envelope {
-        <Self as GetBbox>::envelope(self)
+        <Self as GetBbox<T>>::envelope(self)
     }
 }
 
-impl PointDistance for PointFeature {
+impl<T> PointDistance for PointFeature<T>
+where
+    T: Float + RTreeNum,
+{
     fn distance_2(
         &self,
         point: &<Self::Envelope as Envelope>::Point,
@@ -15,58 +15,81 @@
 //! `PolygonFeature` can be used with `rstar::RTree` and carry along the information from the `GeoJson`
 
 use crate::{
-    conversion::create_geo_polygon,
+    conversion::{convert_position, create_geo_polygon},
+    coordinates::{polygon_distance_2, rings_to_geo_polygon, Parts},
     error::GeoJsonConversionError,
-    generic::{GenericFeature, GetBbox},
+    generic::{check_position, create_bbox, GenericFeature, GetBbox},
     json::JsonObject,
+    simplify::{simplify_polygon, Simplification, SimplifyFeature},
 };
-use geo::{bounding_rect::BoundingRect, euclidean_distance::EuclideanDistance, Polygon};
+use geo::{bounding_rect::BoundingRect, Polygon};
 use geojson::{feature::Id, Bbox, PolygonType};
-use rstar::{Envelope, Point, PointDistance, RTreeObject, AABB};
+use num_traits::Float;
+use rstar::{Envelope, Point, PointDistance, RTreeNum, RTreeObject, AABB};
 use std::convert::TryFrom;
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct PolygonFeature {
-    bbox: Bbox,
-    polygon: PolygonType,
+pub struct PolygonFeature<T = f64> {
+    bbox: [T; 4],
+    rings: Parts<T>,
     pub id: Option<Id>,
     pub properties: Option<JsonObject>,
     pub foreign_members: Option<JsonObject>,
 }
 
-impl PolygonFeature {
-    pub fn polygon(&self) -> &PolygonType {
-        &self.polygon
+impl<T> PolygonFeature<T>
+where
+    T: Float,
+{
+    /// The rings as GeoJSON positions. They're built from the stored
+    /// coordinates, so this returns an owned value rather than a reference;
+    /// `rings` borrows them instead.
+    pub fn polygon(&self) -> PolygonType {
+        self.rings.to_nested()
     }
 
-    pub fn geo_polygon(&self) -> Polygon<f64> {
-        create_geo_polygon(&self.polygon)
+    /// The positions of each ring, the exterior first.
+    pub fn rings(&self) -> impl Iterator<Item = &[[T; 2]]> {
+        self.rings.iter()
+    }
+
+    pub fn geo_polygon(&self) -> Polygon<T> {
+        rings_to_geo_polygon(self.rings.iter())
     }
 }
 
-impl Into<geojson::Feature> for PolygonFeature {
-    fn into(self) -> geojson::Feature {
-        let geometry = geojson::Geometry::new(geojson::Value::Polygon(self.polygon));
+impl<T> From<PolygonFeature<T>> for geojson::Feature
+where
+    T: Float,
+{
+    fn from(feature: PolygonFeature<T>) -> geojson::Feature {
+        let geometry = geojson::Geometry::new(geojson::Value::Polygon(feature.rings.to_nested()));
 
         geojson::Feature {
-            id: self.id,
-            properties: self.properties,
-            foreign_members: self.foreign_members,
+            id: feature.id,
+            properties: feature.properties,
+            foreign_members: feature.foreign_members,
             geometry: Some(geometry),
-            bbox: Some(self.bbox),
+            bbox: Some(convert_position(&feature.bbox).expect("Bbox can be widened to f64")),
         }
     }
 }
 
-impl TryFrom<geojson::Feature> for PolygonFeature {
+impl<T> TryFrom<geojson::Feature> for PolygonFeature<T>
+where
+    T: Float,
+{
     type Error = GeoJsonConversionError;
 
     fn try_from(feature: geojson::Feature) -> Result<Self, Self::Error> {
-        <Self as GenericFeature<PolygonFeature, PolygonType>>::try_from(feature)
+        <Self as GenericFeature<PolygonFeature<T>, PolygonType>>::try_from(feature)
     }
 }
 
-impl GenericFeature<PolygonFeature, PolygonType> for PolygonFeature {
+impl<T> GenericFeature<PolygonFeature<T>, PolygonType> for PolygonFeature<T>
+where
+    T: Float,
+{
     fn take_geometry_type(
         feature: &mut geojson::Feature,
     ) -> Result<PolygonType, GeoJsonConversionError> {
@@ -91,13 +114,10 @@ impl GenericFeature<PolygonFeature, PolygonType> for PolygonFeature {
         geometry: &PolygonType,
         feature: &geojson::Feature,
     ) -> Result<(), GeoJsonConversionError> {
-        if geometry
+        geometry
             .iter()
-            .any(|line| line.iter().any(|p| p.len() != 2))
-        {
-            let id = feature.id.clone();
-            return Err(GeoJsonConversionError::MalformedGeometry(id));
-        }
+            .flatten()
+            .try_for_each(|point| check_position::<T>(point, feature))?;
 
         if geometry.is_empty() || geometry.iter().any(|v| v.is_empty()) {
             let id = feature.id.clone();
@@ -109,6 +129,7 @@ impl GenericFeature<PolygonFeature, PolygonType> for PolygonFeature {
     fn compute_bbox(feature: &mut geojson::Feature, geometry: &PolygonType) -> Bbox {
         feature.bbox.take().unwrap_or_else(|| {
             let maybe_rect = create_geo_polygon(geometry)
+                .expect("Polygon coordinates were checked")
                 .bounding_rect()
                 .expect("Expect a bounding rectangle");
             vec![
@@ -120,37 +141,64 @@ impl GenericFeature<PolygonFeature, PolygonType> for PolygonFeature {
         })
     }
 
-    fn create_self(feature: geojson::Feature, bbox: Bbox, geometry: PolygonType) -> PolygonFeature {
-        PolygonFeature {
-            bbox,
+    fn create_self(
+        feature: geojson::Feature,
+        bbox: Bbox,
+        geometry: PolygonType,
+    ) -> Result<PolygonFeature<T>, GeoJsonConversionError> {
+        Ok(PolygonFeature {
+            bbox: create_bbox(&bbox, &feature)?,
+            rings: Parts::try_from_nested(&geometry)?,
             id: feature.id,
-            polygon: geometry,
             properties: feature.properties,
             foreign_members: feature.foreign_members,
+        })
+    }
+}
+
+impl<T> SimplifyFeature<T> for PolygonFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn simplified(&self, simplification: &Simplification<T>) -> Self {
+        let polygon = simplify_polygon(&self.geo_polygon(), simplification);
+        PolygonFeature {
+            bbox: self.bbox,
+            rings: Parts::from_geo_line_strings(
+                std::iter::once(polygon.exterior()).chain(polygon.interiors()),
+            ),
+            id: self.id.clone(),
+            properties: self.properties.clone(),
+            foreign_members: self.foreign_members.clone(),
         }
     }
 }
 
-impl<'a> GetBbox<'a> for PolygonFeature {
-    fn bbox(&'a self) -> &'a Bbox {
+impl<T> GetBbox<T> for PolygonFeature<T> {
+    fn bbox(&self) -> &[T; 4] {
         &self.bbox
     }
 }
 
-impl RTreeObject for PolygonFeature {
-    type Envelope = AABB<[f64; 2]>;
+impl<T> RTreeObject for PolygonFeature<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
 
     fn envelope(&self) -> Self::Envelope {
-        <Self as GetBbox>::envelope(self)
+        <Self as GetBbox<T>>::envelope(self)
     }
 }
 
-impl PointDistance for PolygonFeature {
+impl<T> PointDistance for PolygonFeature<T>
+where
+    T: Float + RTreeNum,
+{
     fn distance_2(
         &self,
         point: &<Self::Envelope as Envelope>::Point,
     ) -> <<Self::Envelope as Envelope>::Point as Point>::Scalar {
-        let p: geo::Point<f64> = (*point).into();
-        self.geo_polygon().euclidean_distance(&p).powi(2)
+        polygon_distance_2(self.rings.iter(), point)
     }
 }
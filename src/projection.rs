@@ -0,0 +1,168 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Keeping only some of the properties of features, and dropping their
+//! foreign members, so features with many unused attributes take less
+//! memory once loaded.
+
+use crate::{
+    error::GeoJsonConversionError, json::JsonObject, Feature, GeometryCollectionFeature,
+    LineStringFeature, MultiLineStringFeature, MultiPointFeature, MultiPolygonFeature,
+    PointFeature, PolygonFeature,
+};
+use std::{collections::HashSet, convert::TryFrom};
+
+/// Which property keys to keep.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyFilter {
+    All,
+    /// Only these keys. Features keep an empty object if they have none of
+    /// them.
+    Only(HashSet<String>),
+    /// Every key but these.
+    Except(HashSet<String>),
+    /// No properties at all, leaving them `None`.
+    None,
+}
+
+impl PropertyFilter {
+    pub fn only<I, S>(keys: I) -> PropertyFilter
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        PropertyFilter::Only(keys.into_iter().map(Into::into).collect())
+    }
+
+    pub fn except<I, S>(keys: I) -> PropertyFilter
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        PropertyFilter::Except(keys.into_iter().map(Into::into).collect())
+    }
+
+    fn keeps(&self, key: &str) -> bool {
+        match self {
+            PropertyFilter::All => true,
+            PropertyFilter::Only(keys) => keys.contains(key),
+            PropertyFilter::Except(keys) => !keys.contains(key),
+            PropertyFilter::None => false,
+        }
+    }
+}
+
+/// What to keep of each feature besides its geometry, bbox and id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Projection {
+    pub properties: PropertyFilter,
+    pub foreign_members: bool,
+}
+
+impl Default for Projection {
+    /// Keeps everything.
+    fn default() -> Projection {
+        Projection {
+            properties: PropertyFilter::All,
+            foreign_members: true,
+        }
+    }
+}
+
+impl Projection {
+    pub fn project_properties(&self, properties: &mut Option<JsonObject>) {
+        match &self.properties {
+            PropertyFilter::All => {}
+            PropertyFilter::None => *properties = None,
+            filter => {
+                if let Some(object) = properties.take() {
+                    *properties = Some(
+                        object
+                            .into_iter()
+                            .filter(|(key, _)| filter.keeps(key))
+                            .collect(),
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn project_foreign_members(&self, foreign_members: &mut Option<JsonObject>) {
+        if !self.foreign_members {
+            *foreign_members = None;
+        }
+    }
+
+    /// Projects a GeoJSON feature before it is converted, so the dropped
+    /// values are never copied into a feature type.
+    pub fn apply(&self, feature: &mut geojson::Feature) {
+        self.project_properties(&mut feature.properties);
+        self.project_foreign_members(&mut feature.foreign_members);
+    }
+}
+
+/// Features whose properties and foreign members can be projected, either
+/// as they are loaded or once they are in a tree, through `RTree::iter_mut`.
+pub trait ProjectFeature: Sized {
+    fn project(&mut self, projection: &Projection);
+
+    /// Projects a GeoJSON feature and converts it.
+    fn try_from_projected(
+        mut feature: geojson::Feature,
+        projection: &Projection,
+    ) -> Result<Self, GeoJsonConversionError>
+    where
+        Self: TryFrom<geojson::Feature, Error = GeoJsonConversionError>,
+    {
+        projection.apply(&mut feature);
+        Self::try_from(feature)
+    }
+}
+
+macro_rules! impl_project_feature {
+    ($($feature:ident),*) => {
+        $(
+            impl<T> ProjectFeature for $feature<T> {
+                fn project(&mut self, projection: &Projection) {
+                    projection.project_properties(&mut self.properties);
+                    projection.project_foreign_members(&mut self.foreign_members);
+                }
+            }
+        )*
+    };
+}
+
+impl_project_feature!(
+    PointFeature,
+    LineStringFeature,
+    PolygonFeature,
+    MultiPointFeature,
+    MultiLineStringFeature,
+    MultiPolygonFeature,
+    GeometryCollectionFeature
+);
+
+impl<T> ProjectFeature for Feature<T> {
+    fn project(&mut self, projection: &Projection) {
+        match self {
+            Feature::Point(f) => f.project(projection),
+            Feature::Polygon(f) => f.project(projection),
+            Feature::LineString(f) => f.project(projection),
+            Feature::MultiPoint(f) => f.project(projection),
+            Feature::MultiLineString(f) => f.project(projection),
+            Feature::MultiPolygon(f) => f.project(projection),
+            Feature::GeometryCollection(f) => f.project(projection),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `geojson_rstar` Python module, with the `python` feature.
+//!
+//! Built as a `cdylib`, with `maturin` or with
+//! `cargo rustc --lib --release --features python --crate-type cdylib`, it
+//! exposes `Index`, which loads GeoJSON into an `RTree<Feature>` and answers
+//! queries with features as GeoJSON dicts, and `ConversionError`, raised when
+//! a feature can't be converted. The error's `id` attribute is the id of the
+//! feature, or `None`.
+//!
+//! ```python
+//! from geojson_rstar import Index
+//!
+//! index = Index.from_file("parcels.geojson")
+//! [parcel] = index.contains(-122.4, 37.8)
+//! print(parcel["properties"]["apn"])
+//! ```
+
+use crate::{
+    error::GeoJsonConversionError,
+    query::{locate_containing_point, nearest_k_to_geometry},
+    Feature,
+};
+use geojson::{feature::Id, GeoJson};
+use pyo3::{create_exception, exceptions::PyValueError, prelude::*};
+use rstar::{RTree, AABB};
+use serde_json::Value as JsonValue;
+use std::{fs, path::PathBuf};
+
+create_exception!(
+    geojson_rstar,
+    ConversionError,
+    PyValueError,
+    "A GeoJSON feature couldn't be converted. `id` is the id of the feature, or None."
+);
+
+/// An `RTree` of the features of a GeoJSON document.
+#[pyclass(name = "Index", module = "geojson_rstar", frozen)]
+pub struct PyIndex {
+    tree: RTree<Feature<f64>>,
+}
+
+#[pymethods]
+impl PyIndex {
+    /// Loads a FeatureCollection, a Feature or a bare geometry.
+    #[staticmethod]
+    fn from_geojson(py: Python<'_>, text: &str) -> PyResult<PyIndex> {
+        let geojson: GeoJson = text
+            .parse()
+            .map_err(|error| PyValueError::new_err(format!("Invalid GeoJSON: {}", error)))?;
+        let features = Feature::from_geojson(geojson).map_err(|e| conversion_error(py, e))?;
+        Ok(PyIndex {
+            tree: RTree::bulk_load(features),
+        })
+    }
+
+    #[staticmethod]
+    fn from_file(py: Python<'_>, path: PathBuf) -> PyResult<PyIndex> {
+        let text = fs::read_to_string(path)?;
+        PyIndex::from_geojson(py, &text)
+    }
+
+    fn __len__(&self) -> usize {
+        self.tree.size()
+    }
+
+    /// The `k` features nearest to the point, closest first.
+    #[pyo3(signature = (x, y, k = 1))]
+    fn nearest(&self, py: Python<'_>, x: f64, y: f64, k: usize) -> PyResult<Vec<Py<PyAny>>> {
+        let point = geo::Geometry::Point(geo::Point::new(x, y));
+        to_dicts(
+            py,
+            nearest_k_to_geometry(&self.tree, point, k, None)
+                .into_iter()
+                .map(|(feature, _)| feature),
+        )
+    }
+
+    /// The features whose bbox intersects the box.
+    fn bbox(
+        &self,
+        py: Python<'_>,
+        min_x: f64,
+        min_y: f64,
+        max_x: f64,
+        max_y: f64,
+    ) -> PyResult<Vec<Py<PyAny>>> {
+        let envelope = AABB::from_corners([min_x, min_y], [max_x, max_y]);
+        to_dicts(py, self.tree.locate_in_envelope_intersecting(&envelope))
+    }
+
+    /// The features that contain the point, their boundary included.
+    fn contains(&self, py: Python<'_>, x: f64, y: f64) -> PyResult<Vec<Py<PyAny>>> {
+        to_dicts(py, locate_containing_point(&self.tree, &[x, y]))
+    }
+
+    /// The features whose bbox lies entirely within the box. A feature's
+    /// geometry is within the box exactly when its bbox is, unless the GeoJSON
+    /// gave a bbox larger than the geometry, and then that feature can be left
+    /// out.
+    fn within(
+        &self,
+        py: Python<'_>,
+        min_x: f64,
+        min_y: f64,
+        max_x: f64,
+        max_y: f64,
+    ) -> PyResult<Vec<Py<PyAny>>> {
+        let envelope = AABB::from_corners([min_x, min_y], [max_x, max_y]);
+        to_dicts(py, self.tree.locate_in_envelope(&envelope))
+    }
+}
+
+#[pymodule]
+pub fn geojson_rstar(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyIndex>()?;
+    module.add("ConversionError", module.py().get_type::<ConversionError>())?;
+    Ok(())
+}
+
+fn to_dicts<'a, I>(py: Python<'_>, features: I) -> PyResult<Vec<Py<PyAny>>>
+where
+    I: Iterator<Item = &'a Feature<f64>>,
+{
+    let loads = py.import("json")?.getattr("loads")?;
+    features
+        .map(|feature| {
+            let geojson = GeoJson::Feature(feature.clone().into());
+            Ok(loads.call1((geojson.to_string(),))?.unbind())
+        })
+        .collect()
+}
+
+fn conversion_error(py: Python<'_>, error: GeoJsonConversionError) -> PyErr {
+    let id: Option<&Id> = match &error {
+        GeoJsonConversionError::MissingGeometry(id)
+        | GeoJsonConversionError::MalformedGeometry(id)
+        | GeoJsonConversionError::NonFiniteCoordinate(id)
+        | GeoJsonConversionError::InvalidProperty { id, .. } => id.as_ref(),
+        GeoJsonConversionError::IncorrectGeometryValue(_)
+        | GeoJsonConversionError::InvalidWkt(_)
+        | GeoJsonConversionError::InvalidWkb(_) => None,
+    };
+    let id = match id {
+        Some(Id::String(id)) => JsonValue::String(id.clone()),
+        Some(Id::Number(id)) => JsonValue::Number(id.clone()),
+        None => JsonValue::Null,
+    };
+    let err = ConversionError::new_err(format!("{:?}", error));
+    let id = py
+        .import("json")
+        .and_then(|json| json.call_method1("loads", (id.to_string(),)));
+    if let Ok(id) = id {
+        // Setting an attribute of a fresh exception can't fail.
+        let _ = err.value(py).setattr("id", id);
+    }
+    err
+}
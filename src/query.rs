@@ -0,0 +1,347 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Queries over an `RTree` of `Feature`s that go beyond what `RTree` offers.
+
+use crate::{
+    distance::{geometry_distance, geometry_envelope},
+    Feature, GeometryKind,
+};
+use geojson::feature::Id;
+use num_traits::Float;
+use rstar::{Envelope, RTree, RTreeNode, RTreeNum, RTreeObject, SelectionFunction, AABB};
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+/// Returns up to `k` features nearest to `point` along with their distance,
+/// closest first.
+///
+/// Unlike `PointDistance::distance_2` the distance is not squared. Features
+/// at the same distance are ordered by id, see `compare_ids`, so the result
+/// doesn't depend on how the tree was built. Features farther than
+/// `max_distance` are left out.
+pub fn nearest_k<'a, T>(
+    tree: &'a RTree<Feature<T>>,
+    point: &[T; 2],
+    k: usize,
+    max_distance: Option<T>,
+) -> Vec<(&'a Feature<T>, T)>
+where
+    T: Float + RTreeNum,
+{
+    let max_distance_2 = max_distance.map(|d| d * d);
+    let mut nearest: Vec<(&Feature<T>, T)> = vec![];
+    if k == 0 {
+        return nearest;
+    }
+
+    for (feature, distance_2) in tree.nearest_neighbor_iter_with_distance_2(point) {
+        if max_distance_2.is_some_and(|max| distance_2 > max) {
+            break;
+        }
+        // Keep taking features tied with the kth so ids decide which are kept.
+        if nearest.len() >= k && distance_2 > nearest[k - 1].1 {
+            break;
+        }
+        nearest.push((feature, distance_2));
+    }
+
+    sort_nearest(&mut nearest, k);
+    nearest
+        .into_iter()
+        .map(|(feature, distance_2)| (feature, distance_2.sqrt()))
+        .collect()
+}
+
+/// Returns the feature nearest to `geometry`, which can be a `geo::Geometry`
+/// or a `&Feature`, along with its distance.
+pub fn nearest_to_geometry<T, G>(tree: &RTree<Feature<T>>, geometry: G) -> Option<(&Feature<T>, T)>
+where
+    T: Float + RTreeNum,
+    G: Into<geo::Geometry<T>>,
+{
+    nearest_k_to_geometry(tree, geometry, 1, None).pop()
+}
+
+/// Like `nearest_k`, but measures the distance from `geometry`, which can be a
+/// `geo::Geometry` or a `&Feature`, to each feature's geometry.
+///
+/// The tree is searched in order of the distance between the bounding box of
+/// `geometry` and each node's envelope, and the geometry to geometry distance
+/// is only computed for features whose envelope is close enough.
+pub fn nearest_k_to_geometry<T, G>(
+    tree: &RTree<Feature<T>>,
+    geometry: G,
+    k: usize,
+    max_distance: Option<T>,
+) -> Vec<(&Feature<T>, T)>
+where
+    T: Float + RTreeNum,
+    G: Into<geo::Geometry<T>>,
+{
+    let geometry = geometry.into();
+    match geometry_envelope(&geometry) {
+        Some(query_envelope) => best_first(
+            tree,
+            &query_envelope,
+            k,
+            max_distance,
+            |_| true,
+            |feature| geometry_distance(&geometry, &feature.into()),
+        ),
+        None => vec![],
+    }
+}
+
+/// Like `nearest_k`, but only returns features whose kind is in `kinds`.
+///
+/// The kinds are checked at the leaves: every node is searched as usual, and
+/// features of other kinds are dropped when they're reached, without
+/// computing their distance to `point`.
+pub fn nearest_k_of_kinds<'a, T>(
+    tree: &'a RTree<Feature<T>>,
+    point: &[T; 2],
+    kinds: &[GeometryKind],
+    k: usize,
+    max_distance: Option<T>,
+) -> Vec<(&'a Feature<T>, T)>
+where
+    T: Float + RTreeNum,
+{
+    let geometry = geo::Geometry::Point(geo::Point::new(point[0], point[1]));
+    best_first(
+        tree,
+        &AABB::from_point(*point),
+        k,
+        max_distance,
+        |feature| kinds.contains(&feature.kind()),
+        |feature| geometry_distance(&feature.into(), &geometry),
+    )
+}
+
+/// The features that contain `point`, their boundary included. Unlike
+/// `RTree::locate_all_at_point` this works for geometry collections too.
+pub fn locate_containing_point<'a, T>(
+    tree: &'a RTree<Feature<T>>,
+    point: &[T; 2],
+) -> impl Iterator<Item = &'a Feature<T>>
+where
+    T: Float + RTreeNum,
+{
+    let geometry = geo::Geometry::Point(geo::Point::new(point[0], point[1]));
+    tree.locate_in_envelope_intersecting(&AABB::from_point(*point))
+        .filter(move |feature| geometry_distance(&(*feature).into(), &geometry) == T::zero())
+}
+
+/// The features whose envelope intersects `envelope` and whose kind is in
+/// `kinds`.
+pub fn locate_in_envelope_intersecting_of_kinds<'a, T>(
+    tree: &'a RTree<Feature<T>>,
+    envelope: &AABB<[T; 2]>,
+    kinds: &'a [GeometryKind],
+) -> impl Iterator<Item = &'a Feature<T>>
+where
+    T: Float + RTreeNum,
+{
+    tree.locate_with_selection_function(SelectIntersectingOfKinds {
+        envelope: *envelope,
+        kinds,
+    })
+}
+
+struct SelectIntersectingOfKinds<'a, T>
+where
+    T: Float + RTreeNum,
+{
+    envelope: AABB<[T; 2]>,
+    kinds: &'a [GeometryKind],
+}
+
+impl<'a, T> SelectionFunction<Feature<T>> for SelectIntersectingOfKinds<'a, T>
+where
+    T: Float + RTreeNum,
+{
+    fn should_unpack_parent(&self, envelope: &AABB<[T; 2]>) -> bool {
+        self.envelope.intersects(envelope)
+    }
+
+    fn should_unpack_leaf(&self, leaf: &Feature<T>) -> bool {
+        self.kinds.contains(&leaf.kind()) && self.envelope.intersects(&leaf.envelope())
+    }
+}
+
+/// Searches the tree in order of the distance between `query_envelope` and
+/// each node's envelope, which is never more than the `distance` to the
+/// features in that node, and keeps the `k` nearest features that `accept`.
+fn best_first<'a, T, A, D>(
+    tree: &'a RTree<Feature<T>>,
+    query_envelope: &AABB<[T; 2]>,
+    k: usize,
+    max_distance: Option<T>,
+    accept: A,
+    distance: D,
+) -> Vec<(&'a Feature<T>, T)>
+where
+    T: Float + RTreeNum,
+    A: Fn(&Feature<T>) -> bool,
+    D: Fn(&Feature<T>) -> T,
+{
+    let mut nearest: Vec<(&Feature<T>, T)> = vec![];
+    if k == 0 {
+        return nearest;
+    }
+
+    let mut candidates = BinaryHeap::new();
+    let push_children = |candidates: &mut BinaryHeap<Candidate<'a, T>>,
+                         children: &'a [RTreeNode<Feature<T>>]| {
+        candidates.extend(
+            children
+                .iter()
+                .filter(|node| match node {
+                    RTreeNode::Leaf(feature) => accept(feature),
+                    RTreeNode::Parent(_) => true,
+                })
+                .map(|node| Candidate::new(node, query_envelope)),
+        )
+    };
+    push_children(&mut candidates, tree.root().children());
+
+    while let Some(candidate) = candidates.pop() {
+        if max_distance.is_some_and(|max| candidate.distance > max) {
+            break;
+        }
+        if nearest.len() >= k && candidate.distance > nearest[k - 1].1 {
+            break;
+        }
+        match candidate.node {
+            RTreeNode::Leaf(feature) => {
+                let distance = distance(feature);
+                if max_distance.is_none_or(|max| distance <= max) {
+                    let index = nearest
+                        .iter()
+                        .position(|(_, d)| *d > distance)
+                        .unwrap_or(nearest.len());
+                    nearest.insert(index, (feature, distance));
+                }
+            }
+            RTreeNode::Parent(parent) => push_children(&mut candidates, parent.children()),
+        }
+    }
+
+    sort_nearest(&mut nearest, k);
+    nearest
+}
+
+/// A node of the tree ordered by the smallest possible distance to the query,
+/// closest first.
+struct Candidate<'a, T>
+where
+    T: Float + RTreeNum,
+{
+    distance: T,
+    node: &'a RTreeNode<Feature<T>>,
+}
+
+impl<'a, T> Candidate<'a, T>
+where
+    T: Float + RTreeNum,
+{
+    fn new(node: &'a RTreeNode<Feature<T>>, query_envelope: &AABB<[T; 2]>) -> Self {
+        let envelope = match node {
+            RTreeNode::Leaf(feature) => feature.envelope(),
+            RTreeNode::Parent(parent) => parent.envelope(),
+        };
+        Candidate {
+            distance: envelope_distance(&envelope, query_envelope),
+            node,
+        }
+    }
+}
+
+impl<'a, T> PartialEq for Candidate<'a, T>
+where
+    T: Float + RTreeNum,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.distance == other.distance
+    }
+}
+
+impl<'a, T> Eq for Candidate<'a, T> where T: Float + RTreeNum {}
+
+impl<'a, T> PartialOrd for Candidate<'a, T>
+where
+    T: Float + RTreeNum,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, T> Ord for Candidate<'a, T>
+where
+    T: Float + RTreeNum,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .distance
+            .partial_cmp(&self.distance)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+fn envelope_distance<T>(a: &AABB<[T; 2]>, b: &AABB<[T; 2]>) -> T
+where
+    T: Float + RTreeNum,
+{
+    let gap = |axis: usize| {
+        (a.lower()[axis] - b.upper()[axis])
+            .max(b.lower()[axis] - a.upper()[axis])
+            .max(T::zero())
+    };
+    gap(0).hypot(gap(1))
+}
+
+/// Orders by distance then id and keeps the first `k`.
+fn sort_nearest<T>(nearest: &mut Vec<(&Feature<T>, T)>, k: usize)
+where
+    T: Float,
+{
+    nearest.sort_by(|(a, a_distance), (b, b_distance)| {
+        a_distance
+            .partial_cmp(b_distance)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| compare_ids(a.id(), b.id()))
+    });
+    nearest.truncate(k);
+}
+
+/// A total order on feature ids: numbers in numeric order, then strings in
+/// lexicographic order, then features without an id.
+pub fn compare_ids(a: Option<&Id>, b: Option<&Id>) -> Ordering {
+    match (a, b) {
+        (Some(Id::Number(a)), Some(Id::Number(b))) => match (a.as_f64(), b.as_f64()) {
+            (Some(a_f64), Some(b_f64)) => a_f64
+                .partial_cmp(&b_f64)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.to_string().cmp(&b.to_string())),
+            _ => a.to_string().cmp(&b.to_string()),
+        },
+        (Some(Id::Number(_)), Some(Id::String(_))) => Ordering::Less,
+        (Some(Id::String(_)), Some(Id::Number(_))) => Ordering::Greater,
+        (Some(Id::String(a)), Some(Id::String(b))) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
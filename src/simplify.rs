@@ -0,0 +1,528 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simplifying the geometry of features as they are loaded, so indexes of
+//! detailed coastlines and boundaries are quicker to query at low zoom.
+
+use crate::{
+    conversion::{
+        create_geo_line_string, create_geo_multi_line_string, create_geo_multi_polygon,
+        create_geo_polygon, create_line_string_type, create_multi_line_string_type,
+        create_multi_polygon_type, create_polygon_type,
+    },
+    coordinates::ring_contains,
+    distance::geometry_envelope,
+    error::GeoJsonConversionError,
+    Feature, GeometryCollectionFeature, MultiPointFeature, PointFeature,
+};
+use geo::{
+    intersects::Intersects, simplify::Simplify, simplifyvw::SimplifyVWPreserve, Line, LineString,
+    MultiLineString, MultiPolygon, Polygon,
+};
+use geojson::Value;
+use num_traits::Float;
+use rstar::{Envelope, PointDistance, RTree, RTreeNum, RTreeObject, AABB};
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimplifyAlgorithm {
+    /// Ramer-Douglas-Peucker, with the tolerance being the largest distance
+    /// a removed position may be from the simplified line.
+    DouglasPeucker,
+    /// Visvalingam-Whyatt, with the tolerance being the smallest area of the
+    /// triangle a position makes with its neighbours for it to be kept.
+    /// Positions are only removed if that doesn't make the geometry cross
+    /// itself.
+    Visvalingam,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Simplification<T = f64> {
+    pub algorithm: SimplifyAlgorithm,
+    pub tolerance: T,
+}
+
+/// Features whose geometry can be simplified.
+///
+/// The simplified feature keeps the bbox of the full geometry, so it sits in
+/// the same place in an `RTree`. Geometries stay valid: a line never
+/// collapses to zero length, a ring never has fewer than four positions,
+/// rings don't come to cross each other, holes stay inside their exterior
+/// and the polygons of a multi polygon stay apart. Neither algorithm can
+/// promise all of that, so a line or polygon that simplifying would make
+/// invalid is kept as it is. Points are never simplified.
+pub trait SimplifyFeature<T>: Sized {
+    fn simplified(&self, simplification: &Simplification<T>) -> Self;
+
+    /// Converts a GeoJSON feature, simplifying its geometry on the way, for
+    /// when the full geometry isn't needed. The simplified geometry goes
+    /// through the same checks as `TryFrom`.
+    fn try_from_simplified(
+        mut feature: geojson::Feature,
+        simplification: &Simplification<T>,
+    ) -> Result<Self, GeoJsonConversionError>
+    where
+        Self: TryFrom<geojson::Feature, Error = GeoJsonConversionError>,
+        T: Float + RTreeNum,
+    {
+        simplify_geojson(&mut feature, simplification);
+        Self::try_from(feature)
+    }
+}
+
+/// Simplifies the geometry of a GeoJSON feature, first setting its bbox to
+/// that of the full geometry. A geometry that can't be read as `T` is left
+/// for the conversion to report.
+fn simplify_geojson<T>(feature: &mut geojson::Feature, simplification: &Simplification<T>)
+where
+    T: Float + RTreeNum,
+{
+    let geometry = match feature.geometry.as_mut() {
+        Some(geometry) => geometry,
+        None => return,
+    };
+    if !positions_are_points(&geometry.value) {
+        return;
+    }
+    let simplified = match &geometry.value {
+        Value::LineString(l) => create_geo_line_string::<f64, T>(l).map(|full| {
+            let simplified = simplify_line_string(&full, simplification);
+            (
+                full.into(),
+                create_line_string_type(&simplified).map(Value::LineString),
+            )
+        }),
+        Value::MultiLineString(l) => create_geo_multi_line_string::<f64, T>(l).map(|full| {
+            let simplified = MultiLineString(
+                full.0
+                    .iter()
+                    .map(|line| simplify_line_string(line, simplification))
+                    .collect(),
+            );
+            (
+                full.into(),
+                create_multi_line_string_type(&simplified).map(Value::MultiLineString),
+            )
+        }),
+        Value::Polygon(p) => create_geo_polygon::<f64, T>(p).map(|full| {
+            let simplified = simplify_polygon(&full, simplification);
+            (
+                full.into(),
+                create_polygon_type(&simplified).map(Value::Polygon),
+            )
+        }),
+        Value::MultiPolygon(p) => create_geo_multi_polygon::<f64, T>(p).map(|full| {
+            let simplified = simplify_multi_polygon(&full, simplification);
+            (
+                full.into(),
+                create_multi_polygon_type(&simplified).map(Value::MultiPolygon),
+            )
+        }),
+        _ => return,
+    };
+    let (full, value): (geo::Geometry<T>, _) = match simplified {
+        Ok((full, Ok(value))) => (full, value),
+        _ => return,
+    };
+    let envelope = match geometry_envelope(&full) {
+        Some(envelope) => envelope,
+        None => return,
+    };
+    if feature.bbox.is_none() {
+        let (lower, upper) = (envelope.lower(), envelope.upper());
+        let bbox = [lower[0], lower[1], upper[0], upper[1]];
+        match bbox.iter().map(|c| c.to_f64()).collect() {
+            Some(bbox) => feature.bbox = Some(bbox),
+            None => return,
+        }
+    }
+    geometry.value = value;
+}
+
+/// Whether every position of `value` has exactly two coordinates, which the
+/// conversion checks and simplifying could hide.
+fn positions_are_points(value: &Value) -> bool {
+    let two = |position: &Vec<f64>| position.len() == 2;
+    match value {
+        Value::LineString(l) => l.iter().all(two),
+        Value::MultiLineString(l) => l.iter().flatten().all(two),
+        Value::Polygon(p) => p.iter().flatten().all(two),
+        Value::MultiPolygon(p) => p.iter().flatten().flatten().all(two),
+        _ => false,
+    }
+}
+
+pub(crate) fn simplify_line_string<T>(
+    line_string: &LineString<T>,
+    simplification: &Simplification<T>,
+) -> LineString<T>
+where
+    T: Float + RTreeNum,
+{
+    let simplified = match simplification.algorithm {
+        SimplifyAlgorithm::DouglasPeucker => line_string.simplify(&simplification.tolerance),
+        SimplifyAlgorithm::Visvalingam => {
+            line_string.simplifyvw_preserve(&simplification.tolerance)
+        }
+    };
+    // The check `LineStringFeature` makes on conversion.
+    let length = simplified.lines().fold(T::zero(), |length, line| {
+        length + line.dx().hypot(line.dy())
+    });
+    if length.to_f64().is_none_or(|length| length < f64::EPSILON) {
+        line_string.clone()
+    } else {
+        simplified
+    }
+}
+
+pub(crate) fn simplify_polygon<T>(
+    polygon: &Polygon<T>,
+    simplification: &Simplification<T>,
+) -> Polygon<T>
+where
+    T: Float + RTreeNum,
+{
+    let simplified = match simplification.algorithm {
+        SimplifyAlgorithm::DouglasPeucker => polygon.simplify(&simplification.tolerance),
+        SimplifyAlgorithm::Visvalingam => polygon.simplifyvw_preserve(&simplification.tolerance),
+    };
+    if is_valid(&simplified) {
+        simplified
+    } else {
+        polygon.clone()
+    }
+}
+
+/// Simplifies each polygon, keeping any whose simplified version would
+/// overlap another polygon as it is.
+pub(crate) fn simplify_multi_polygon<T>(
+    multi_polygon: &MultiPolygon<T>,
+    simplification: &Simplification<T>,
+) -> MultiPolygon<T>
+where
+    T: Float + RTreeNum,
+{
+    let full = &multi_polygon.0;
+    let mut polygons: Vec<Polygon<T>> = full
+        .iter()
+        .map(|polygon| simplify_polygon(polygon, simplification))
+        .collect();
+    // Putting back a full polygon can make it overlap another simplified
+    // one, so this goes on until no simplified polygon overlaps.
+    loop {
+        let overlapping: Vec<usize> = overlapping_polygons(&polygons)
+            .into_iter()
+            .flat_map(|(a, b)| vec![a, b])
+            .filter(|&index| polygons[index] != full[index])
+            .collect();
+        if overlapping.is_empty() {
+            return MultiPolygon(polygons);
+        }
+        for index in overlapping {
+            polygons[index] = full[index].clone();
+        }
+    }
+}
+
+fn ring_positions<T>(ring: &LineString<T>) -> Vec<[T; 2]>
+where
+    T: Float,
+{
+    ring.0.iter().map(|c| [c.x, c.y]).collect()
+}
+
+/// Whether every ring has at least four positions, no two rings cross and
+/// each hole is inside the exterior but not inside another hole.
+fn is_valid<T>(polygon: &Polygon<T>) -> bool
+where
+    T: Float + RTreeNum,
+{
+    let rings: Vec<&LineString<T>> = std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .collect();
+    if rings.iter().any(|ring| ring.0.len() < 4) || !crossings(&[polygon]).is_empty() {
+        return false;
+    }
+    // As the rings don't cross, one position tells which side a hole is on.
+    let rings: Vec<Vec<[T; 2]>> = rings.into_iter().map(ring_positions).collect();
+    rings[1..].iter().enumerate().all(|(index, hole)| {
+        ring_contains(&rings[0], &hole[0])
+            && rings[1..]
+                .iter()
+                .enumerate()
+                .all(|(other, ring)| other == index || !ring_contains(ring, &hole[0]))
+    })
+}
+
+/// The pairs of different polygons whose rings cross, or where one is inside
+/// the other.
+fn overlapping_polygons<T>(polygons: &[Polygon<T>]) -> Vec<(usize, usize)>
+where
+    T: Float + RTreeNum,
+{
+    let parts: Vec<&Polygon<T>> = polygons.iter().collect();
+    let mut overlapping: Vec<(usize, usize)> = crossings(&parts)
+        .into_iter()
+        .filter(|(a, b)| a != b)
+        .collect();
+
+    let rings: Vec<Vec<Vec<[T; 2]>>> = polygons
+        .iter()
+        .map(|polygon| {
+            std::iter::once(polygon.exterior())
+                .chain(polygon.interiors())
+                .map(ring_positions)
+                .collect()
+        })
+        .collect();
+    let envelopes: Vec<AABB<[T; 2]>> = rings
+        .iter()
+        .map(|rings| AABB::from_points(rings[0].iter()))
+        .collect();
+    // Whether the first position of `inner` is inside the exterior of
+    // `outer` and not in one of its holes.
+    let inside = |inner: &[Vec<[T; 2]>], outer: &[Vec<[T; 2]>]| match inner[0].first() {
+        Some(position) => {
+            ring_contains(&outer[0], position)
+                && !outer[1..].iter().any(|hole| ring_contains(hole, position))
+        }
+        None => false,
+    };
+    for a in 0..polygons.len() {
+        for b in a + 1..polygons.len() {
+            if envelopes[a].intersects(&envelopes[b])
+                && (inside(&rings[a], &rings[b]) || inside(&rings[b], &rings[a]))
+            {
+                overlapping.push((a, b));
+            }
+        }
+    }
+    overlapping
+}
+
+/// One segment of a ring, knowing its place so that segments which share a
+/// position because they follow each other aren't taken to cross.
+struct RingSegment<T>
+where
+    T: Float + RTreeNum,
+{
+    line: Line<T>,
+    polygon: usize,
+    ring: usize,
+    index: usize,
+    ring_segments: usize,
+}
+
+impl<T> RingSegment<T>
+where
+    T: Float + RTreeNum,
+{
+    fn follows(&self, other: &RingSegment<T>) -> bool {
+        let last = self.ring_segments - 1;
+        self.polygon == other.polygon
+            && self.ring == other.ring
+            && (self.index == other.index
+                || self.index + 1 == other.index
+                || other.index + 1 == self.index
+                || (self.index == 0 && other.index == last)
+                || (other.index == 0 && self.index == last))
+    }
+}
+
+impl<T> RTreeObject for RingSegment<T>
+where
+    T: Float + RTreeNum,
+{
+    type Envelope = AABB<[T; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_corners(
+            [self.line.start.x, self.line.start.y],
+            [self.line.end.x, self.line.end.y],
+        )
+    }
+}
+
+/// The pairs of polygons, by index, with segments that touch, other than
+/// those that follow each other in a ring. A polygon whose own rings cross
+/// is paired with itself.
+fn crossings<T>(polygons: &[&Polygon<T>]) -> Vec<(usize, usize)>
+where
+    T: Float + RTreeNum,
+{
+    let segments: Vec<RingSegment<T>> = polygons
+        .iter()
+        .enumerate()
+        .flat_map(|(polygon, rings)| {
+            std::iter::once(rings.exterior())
+                .chain(rings.interiors())
+                .enumerate()
+                .flat_map(move |(ring, line_string)| {
+                    let ring_segments = line_string.0.len().saturating_sub(1);
+                    line_string
+                        .lines()
+                        .enumerate()
+                        .map(move |(index, line)| RingSegment {
+                            line,
+                            polygon,
+                            ring,
+                            index,
+                            ring_segments,
+                        })
+                })
+        })
+        .collect();
+    let tree = RTree::bulk_load(segments);
+
+    let mut crossing: Vec<(usize, usize)> = tree
+        .iter()
+        .flat_map(|segment| {
+            tree.locate_in_envelope_intersecting(&segment.envelope())
+                .filter(move |other| {
+                    segment.polygon <= other.polygon
+                        && !segment.follows(other)
+                        && segment.line.intersects(&other.line)
+                })
+                .map(move |other| (segment.polygon, other.polygon))
+        })
+        .collect();
+    crossing.sort_unstable();
+    crossing.dedup();
+    crossing
+}
+
+impl<T> SimplifyFeature<T> for PointFeature<T>
+where
+    T: Float,
+{
+    fn simplified(&self, _: &Simplification<T>) -> Self {
+        self.clone()
+    }
+}
+
+impl<T> SimplifyFeature<T> for MultiPointFeature<T>
+where
+    T: Float,
+{
+    fn simplified(&self, _: &Simplification<T>) -> Self {
+        self.clone()
+    }
+}
+
+/// Geometry collections are kept as they are, since their geometries are
+/// stored as GeoJSON.
+impl<T> SimplifyFeature<T> for GeometryCollectionFeature<T>
+where
+    T: Float,
+{
+    fn simplified(&self, _: &Simplification<T>) -> Self {
+        self.clone()
+    }
+}
+
+impl<T> SimplifyFeature<T> for Feature<T>
+where
+    T: Float + RTreeNum,
+{
+    fn simplified(&self, simplification: &Simplification<T>) -> Self {
+        match self {
+            Feature::Point(f) => Feature::Point(f.simplified(simplification)),
+            Feature::Polygon(f) => Feature::Polygon(f.simplified(simplification)),
+            Feature::LineString(f) => Feature::LineString(f.simplified(simplification)),
+            Feature::MultiPoint(f) => Feature::MultiPoint(f.simplified(simplification)),
+            Feature::MultiLineString(f) => Feature::MultiLineString(f.simplified(simplification)),
+            Feature::MultiPolygon(f) => Feature::MultiPolygon(f.simplified(simplification)),
+            Feature::GeometryCollection(f) => {
+                Feature::GeometryCollection(f.simplified(simplification))
+            }
+        }
+    }
+}
+
+/// A feature kept at two resolutions for coarse to fine queries: the `RTree`
+/// measures distance to the simplified `coarse` geometry, and the `full`
+/// geometry is there to refine the candidates with `nearest_full`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiResolution<F> {
+    pub coarse: F,
+    pub full: F,
+}
+
+impl<F> MultiResolution<F> {
+    pub fn new<T>(full: F, simplification: &Simplification<T>) -> Self
+    where
+        F: SimplifyFeature<T>,
+    {
+        MultiResolution {
+            coarse: full.simplified(simplification),
+            full,
+        }
+    }
+}
+
+impl<F> RTreeObject for MultiResolution<F>
+where
+    F: RTreeObject,
+{
+    type Envelope = F::Envelope;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.coarse.envelope()
+    }
+}
+
+impl<F> PointDistance for MultiResolution<F>
+where
+    F: PointDistance,
+{
+    fn distance_2(
+        &self,
+        point: &<Self::Envelope as Envelope>::Point,
+    ) -> <<Self::Envelope as Envelope>::Point as rstar::Point>::Scalar {
+        self.coarse.distance_2(point)
+    }
+}
+
+/// The feature whose `full` geometry is nearest to `point`, visiting the
+/// features of `tree` in order of their `coarse` distance.
+///
+/// `slack` is how much nearer the coarse geometry can be than the full one,
+/// the tolerance for Douglas-Peucker. The search stops once the coarse
+/// distance is more than `slack` past the best full distance found, so
+/// usually only a few full geometries are measured. Visvalingam-Whyatt gives
+/// no such bound, and with an infinite `slack` every feature is measured.
+pub fn nearest_full<'a, F, T>(
+    tree: &'a RTree<MultiResolution<F>>,
+    point: &[T; 2],
+    slack: T,
+) -> Option<&'a MultiResolution<F>>
+where
+    F: PointDistance + RTreeObject<Envelope = AABB<[T; 2]>>,
+    T: Float + RTreeNum,
+{
+    let mut best: Option<(&MultiResolution<F>, T)> = None;
+    for (feature, coarse) in tree.nearest_neighbor_iter_with_distance_2(point) {
+        if let Some((_, distance)) = best {
+            if coarse.sqrt() - slack > distance {
+                break;
+            }
+        }
+        let distance = feature.full.distance_2(point).sqrt();
+        if best.is_none_or(|(_, best)| distance < best) {
+            best = Some((feature, distance));
+        }
+    }
+    best.map(|(feature, _)| feature)
+}
@@ -0,0 +1,428 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading features from a tokio `AsyncRead`, with the `tokio` feature,
+//! without holding the whole document in memory.
+//!
+//! Only the bytes of the feature being read are buffered, up to a maximum
+//! feature size, and the reader is only polled when the next feature is
+//! asked for, so a slow consumer holds back the producer. Dropping a
+//! `FeatureStream`, or a future waiting on one, cancels the read.
+//!
+//! ```no_run
+//! # async fn load() -> Result<(), geojson_rstar::GeoJsonStreamError> {
+//! use geojson_rstar::stream::{load_tree, StreamFormat};
+//!
+//! let file = tokio::fs::File::open("parcels.geojson").await?;
+//! let tree = load_tree::<f64, _>(file, StreamFormat::FeatureCollection).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{error::GeoJsonStreamError, Feature};
+use futures_core::Stream;
+use num_traits::Float;
+use rstar::{RTree, RTreeNum};
+use std::{
+    convert::TryFrom,
+    future::poll_fn,
+    marker::PhantomData,
+    ops::Range,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, ReadBuf};
+
+const CHUNK_SIZE: usize = 8192;
+
+/// The default limit on the size of one feature, 16 MiB.
+pub const DEFAULT_MAX_FEATURE_SIZE: usize = 16 * 1024 * 1024;
+
+/// The record separator that starts each RFC 8142 text sequence record.
+const RECORD_SEPARATOR: u8 = 0x1E;
+
+/// How the features are laid out in the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// A single FeatureCollection object.
+    FeatureCollection,
+    /// GeoJSONSeq: one feature per line, each optionally preceded by the
+    /// RFC 8142 record separator.
+    GeoJsonSeq,
+}
+
+/// The features of a GeoJSON document read from an `AsyncRead`, converted
+/// one at a time.
+///
+/// A feature that isn't valid GeoJSON or can't be converted is returned as
+/// an `Err` and the stream carries on with the next one, so bad features can
+/// be skipped or reported by number. A read error, or a document that isn't
+/// a FeatureCollection or ends part way through, is returned as the last
+/// item.
+///
+/// A feature longer than the maximum feature size is returned as an
+/// `InvalidFeature` error instead of being buffered. In a GeoJSONSeq the
+/// rest of its line is skipped; in a FeatureCollection it's the last item.
+pub struct FeatureStream<T, R> {
+    reader: R,
+    format: StreamFormat,
+    max_feature_size: usize,
+    buffer: Vec<u8>,
+    /// Whether the rest of an oversized GeoJSONSeq line is being skipped.
+    skipping_line: bool,
+    /// How far into `buffer` has been scanned.
+    scanned: usize,
+    scanner: CollectionScanner,
+    features: usize,
+    end_of_input: bool,
+    finished: bool,
+    scalar: PhantomData<fn() -> T>,
+}
+
+enum Buffered<T> {
+    Item(Result<Feature<T>, GeoJsonStreamError>),
+    NeedMore,
+    End,
+}
+
+impl<T, R> FeatureStream<T, R>
+where
+    T: Float,
+    R: AsyncRead + Unpin,
+{
+    pub fn new(reader: R, format: StreamFormat) -> Self {
+        FeatureStream {
+            reader,
+            format,
+            max_feature_size: DEFAULT_MAX_FEATURE_SIZE,
+            buffer: Vec::new(),
+            skipping_line: false,
+            scanned: 0,
+            scanner: CollectionScanner::default(),
+            features: 0,
+            end_of_input: false,
+            finished: false,
+            scalar: PhantomData,
+        }
+    }
+
+    /// Sets the most bytes one feature can take, `DEFAULT_MAX_FEATURE_SIZE`
+    /// unless set.
+    pub fn with_max_feature_size(mut self, bytes: usize) -> Self {
+        self.max_feature_size = bytes;
+        self
+    }
+
+    /// The next feature, or `None` at the end of the document.
+    ///
+    /// This is cancel safe: if the future is dropped before it completes,
+    /// no feature is lost and the next call carries on where it left off.
+    pub async fn next_feature(&mut self) -> Option<Result<Feature<T>, GeoJsonStreamError>> {
+        poll_fn(|cx| self.poll_feature(cx)).await
+    }
+
+    /// The number of features read so far, including those that couldn't
+    /// be converted.
+    pub fn features_read(&self) -> usize {
+        self.features
+    }
+
+    fn poll_feature(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Feature<T>, GeoJsonStreamError>>> {
+        loop {
+            if self.finished {
+                return Poll::Ready(None);
+            }
+            let buffered = match self.format {
+                StreamFormat::FeatureCollection => self.next_in_collection(),
+                StreamFormat::GeoJsonSeq => self.next_in_sequence(),
+            };
+            match buffered {
+                Buffered::Item(item) => return Poll::Ready(Some(item)),
+                Buffered::End => {
+                    self.finished = true;
+                    return Poll::Ready(None);
+                }
+                Buffered::NeedMore => {}
+            }
+
+            let mut chunk = [0; CHUNK_SIZE];
+            let mut read = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut self.reader).poll_read(cx, &mut read) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(error)) => {
+                    self.finished = true;
+                    return Poll::Ready(Some(Err(error.into())));
+                }
+                Poll::Ready(Ok(())) if read.filled().is_empty() => self.end_of_input = true,
+                Poll::Ready(Ok(())) => self.buffer.extend_from_slice(read.filled()),
+            }
+        }
+    }
+
+    fn next_in_collection(&mut self) -> Buffered<T> {
+        let (scanned, scan) = self.scanner.scan(&self.buffer, self.scanned);
+        self.scanned = scanned;
+        match scan {
+            Scan::Feature(range) => {
+                let item = self.parse(range);
+                self.buffer.drain(..scanned);
+                self.scanned = 0;
+                Buffered::Item(item)
+            }
+            Scan::Done => Buffered::End,
+            Scan::Invalid(message) => {
+                self.finished = true;
+                Buffered::Item(Err(GeoJsonStreamError::InvalidDocument(message)))
+            }
+            Scan::NeedMore if self.end_of_input => {
+                self.finished = true;
+                Buffered::Item(Err(GeoJsonStreamError::InvalidDocument(
+                    "The FeatureCollection ends part way through".into(),
+                )))
+            }
+            Scan::NeedMore => {
+                // Only the feature being scanned has to be kept.
+                let keep = self.scanner.feature_start.unwrap_or(scanned);
+                self.buffer.drain(..keep);
+                self.scanned -= keep;
+                if let Some(start) = self.scanner.feature_start.as_mut() {
+                    *start = 0;
+                    if self.buffer.len() > self.max_feature_size {
+                        self.finished = true;
+                        return Buffered::Item(Err(self.too_large()));
+                    }
+                }
+                Buffered::NeedMore
+            }
+        }
+    }
+
+    fn next_in_sequence(&mut self) -> Buffered<T> {
+        if self.skipping_line {
+            match self.buffer.iter().position(|byte| *byte == b'\n') {
+                Some(end) => {
+                    self.buffer.drain(..=end);
+                    self.skipping_line = false;
+                }
+                None => {
+                    self.buffer.clear();
+                    self.scanned = 0;
+                    return if self.end_of_input {
+                        Buffered::End
+                    } else {
+                        Buffered::NeedMore
+                    };
+                }
+            }
+        }
+        loop {
+            let end = match self.buffer[self.scanned..]
+                .iter()
+                .position(|byte| *byte == b'\n')
+            {
+                Some(offset) => self.scanned + offset,
+                None if self.end_of_input && !self.buffer.is_empty() => self.buffer.len(),
+                None if self.end_of_input => return Buffered::End,
+                None if self.buffer.len() > self.max_feature_size => {
+                    self.buffer.clear();
+                    self.scanned = 0;
+                    self.skipping_line = true;
+                    return Buffered::Item(Err(self.too_large()));
+                }
+                None => {
+                    self.scanned = self.buffer.len();
+                    return Buffered::NeedMore;
+                }
+            };
+            let record = trim_record(&self.buffer[..end]);
+            let item = if record.is_empty() {
+                None
+            } else {
+                Some(self.parse(record))
+            };
+            self.buffer.drain(..(end + 1).min(self.buffer.len()));
+            self.scanned = 0;
+            if let Some(item) = item {
+                return Buffered::Item(item);
+            }
+        }
+    }
+
+    fn parse(&mut self, range: Range<usize>) -> Result<Feature<T>, GeoJsonStreamError> {
+        if range.len() > self.max_feature_size {
+            return Err(self.too_large());
+        }
+        let feature = self.features;
+        self.features += 1;
+        let geojson: geojson::Feature =
+            serde_json::from_slice(&self.buffer[range]).map_err(|error| {
+                GeoJsonStreamError::InvalidFeature {
+                    feature,
+                    message: error.to_string(),
+                }
+            })?;
+        Feature::try_from(geojson)
+            .map_err(|error| GeoJsonStreamError::Conversion { feature, error })
+    }
+
+    fn too_large(&mut self) -> GeoJsonStreamError {
+        let feature = self.features;
+        self.features += 1;
+        GeoJsonStreamError::InvalidFeature {
+            feature,
+            message: format!(
+                "The feature is longer than the maximum of {} bytes",
+                self.max_feature_size
+            ),
+        }
+    }
+}
+
+impl<T, R> Stream for FeatureStream<T, R>
+where
+    T: Float,
+    R: AsyncRead + Unpin,
+{
+    type Item = Result<Feature<T>, GeoJsonStreamError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_feature(cx)
+    }
+}
+
+/// Reads every feature into an `RTree`, stopping at the first error.
+pub async fn load_tree<T, R>(
+    reader: R,
+    format: StreamFormat,
+) -> Result<RTree<Feature<T>>, GeoJsonStreamError>
+where
+    T: Float + RTreeNum,
+    R: AsyncRead + Unpin,
+{
+    let mut stream = FeatureStream::new(reader, format);
+    let mut features = vec![];
+    while let Some(feature) = stream.next_feature().await {
+        features.push(feature?);
+    }
+    Ok(RTree::bulk_load(features))
+}
+
+/// The range of a GeoJSONSeq line without the record separator or
+/// surrounding whitespace.
+fn trim_record(line: &[u8]) -> Range<usize> {
+    let skip = |byte: &u8| *byte == RECORD_SEPARATOR || byte.is_ascii_whitespace();
+    let start = line.iter().position(|b| !skip(b)).unwrap_or(line.len());
+    let end = line
+        .iter()
+        .rposition(|b| !skip(b))
+        .map_or(start, |end| end + 1);
+    start..end
+}
+
+enum Scan {
+    Feature(Range<usize>),
+    NeedMore,
+    Done,
+    Invalid(String),
+}
+
+/// Finds the features of a FeatureCollection by tracking nesting and
+/// strings, leaving the parsing of each feature to serde_json.
+#[derive(Default)]
+struct CollectionScanner {
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// The string being read in the collection object, which may be a key.
+    string: Vec<u8>,
+    last_string: Option<Vec<u8>>,
+    key: Option<Vec<u8>>,
+    in_features: bool,
+    feature_start: Option<usize>,
+}
+
+impl CollectionScanner {
+    /// Scans `buffer` from `from`, returning how far it got.
+    fn scan(&mut self, buffer: &[u8], from: usize) -> (usize, Scan) {
+        let invalid = |index: usize, message: &str| (index, Scan::Invalid(message.into()));
+        for (index, byte) in buffer.iter().copied().enumerate().skip(from) {
+            let in_collection = self.depth == 1;
+            let in_array = self.in_features && self.depth == 2;
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                    if in_collection {
+                        self.last_string = Some(std::mem::take(&mut self.string));
+                    }
+                    continue;
+                }
+                if in_collection {
+                    self.string.push(byte);
+                }
+                continue;
+            }
+            match byte {
+                b'"' if in_array => return invalid(index, "A feature must be an object"),
+                b'"' => {
+                    self.in_string = true;
+                    self.string.clear();
+                }
+                b'{' if self.depth == 0 => self.depth += 1,
+                b'{' | b'[' if self.depth == 0 => {
+                    return invalid(index, "A FeatureCollection must be an object")
+                }
+                b'[' if in_collection && self.key.as_deref() == Some(b"features") => {
+                    self.in_features = true;
+                    self.depth += 1;
+                }
+                b'{' if in_array => {
+                    self.feature_start = Some(index);
+                    self.depth += 1;
+                }
+                b'[' if in_array => return invalid(index, "A feature must be an object"),
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' if self.depth == 0 => return invalid(index, "Unbalanced brackets"),
+                b']' if in_array => return (index + 1, Scan::Done),
+                b'}' | b']' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        return invalid(index, "The FeatureCollection has no features");
+                    }
+                    if self.in_features && self.depth == 2 {
+                        if let Some(start) = self.feature_start.take() {
+                            return (index + 1, Scan::Feature(start..index + 1));
+                        }
+                    }
+                }
+                b':' if in_collection => self.key = self.last_string.take(),
+                b',' if in_collection => self.key = None,
+                b',' => {}
+                byte if byte.is_ascii_whitespace() => {}
+                _ if self.depth == 0 => {
+                    return invalid(index, "A FeatureCollection must be an object")
+                }
+                _ if in_array => return invalid(index, "A feature must be an object"),
+                _ => {}
+            }
+        }
+        (buffer.len().max(from), Scan::NeedMore)
+    }
+}
@@ -0,0 +1,562 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encoding the features of an `RTree` that fall in a web map tile as a
+//! Mapbox Vector Tile, version 2.
+//!
+//! Feature coordinates are taken to be longitude and latitude, and are
+//! projected to Web Mercator like the tiles of slippy maps.
+
+use crate::{
+    clip::{clip_line, clip_ring, inside},
+    json::JsonObject,
+    Feature,
+};
+use geo::{Coordinate, Geometry, LineString, Polygon};
+use geojson::feature::Id;
+use num_traits::Float;
+use rstar::{RTree, RTreeNum, AABB};
+use serde_json::Value as JsonValue;
+use std::{collections::HashMap, f64::consts::PI};
+
+/// The latitude at which Web Mercator tiles end, which makes the world
+/// square.
+const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+const WIRE_VARINT: u32 = 0;
+const WIRE_FIXED64: u32 = 1;
+const WIRE_LEN: u32 = 2;
+
+const GEOM_POINT: u64 = 1;
+const GEOM_LINE_STRING: u64 = 2;
+const GEOM_POLYGON: u64 = 3;
+
+const MOVE_TO: u32 = 1;
+const LINE_TO: u32 = 2;
+const CLOSE_PATH: u32 = 7;
+
+/// A tile of the Web Mercator tile pyramid, with `x` growing east and `y`
+/// growing south from the top left tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileAddress {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileAddress {
+    pub fn new(z: u8, x: u32, y: u32) -> Self {
+        TileAddress { z, x, y }
+    }
+
+    /// The longitude and latitude bounds of the tile.
+    pub fn envelope(&self) -> AABB<[f64; 2]> {
+        self.buffered_envelope(0.0)
+    }
+
+    /// The bounds of the tile grown on every side by `buffer`, a fraction of
+    /// the tile's width.
+    fn buffered_envelope(&self, buffer: f64) -> AABB<[f64; 2]> {
+        AABB::from_corners(
+            self.lon_lat(-buffer, -buffer),
+            self.lon_lat(1.0 + buffer, 1.0 + buffer),
+        )
+    }
+
+    /// The tiles at zoom `z` that intersect a longitude and latitude box,
+    /// such as the tiles to encode again after the features in it change.
+    pub fn covering(envelope: &AABB<[f64; 2]>, z: u8) -> impl Iterator<Item = TileAddress> {
+        let world = TileAddress::new(z, 0, 0);
+        let last = (world.tiles() - 1.0) as u32;
+        let tile = move |value: f64| (value.floor().max(0.0) as u32).min(last);
+        let (lower, upper) = (envelope.lower(), envelope.upper());
+        // y grows south, so the top of the box is the lowest row.
+        let [min_x, max_y] = world.project(lower[0], lower[1]);
+        let [max_x, min_y] = world.project(upper[0], upper[1]);
+        (tile(min_y)..=tile(max_y))
+            .flat_map(move |y| (tile(min_x)..=tile(max_x)).map(move |x| TileAddress::new(z, x, y)))
+    }
+
+    fn tiles(&self) -> f64 {
+        2f64.powi(i32::from(self.z))
+    }
+
+    /// The longitude and latitude of a position given as a fraction of the
+    /// tile, with `[0, 0]` the top left corner and `[1, 1]` the bottom right.
+    fn lon_lat(&self, x: f64, y: f64) -> [f64; 2] {
+        let tiles = self.tiles();
+        let lon = (f64::from(self.x) + x) / tiles * 360.0 - 180.0;
+        let lat = (PI * (1.0 - 2.0 * (f64::from(self.y) + y) / tiles))
+            .sinh()
+            .atan()
+            .to_degrees();
+        [lon, lat.clamp(-MAX_LATITUDE, MAX_LATITUDE)]
+    }
+
+    /// The position of a longitude and latitude as a fraction of the tile.
+    fn project(&self, lon: f64, lat: f64) -> [f64; 2] {
+        let tiles = self.tiles();
+        let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+        let x = (lon + 180.0) / 360.0 * tiles - f64::from(self.x);
+        let y = (1.0 - lat.tan().asinh() / PI) / 2.0 * tiles - f64::from(self.y);
+        [x, y]
+    }
+}
+
+/// How a tile is encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileOptions {
+    /// The name of the one layer in the tile.
+    pub layer_name: String,
+    /// The width and height of the tile in integer tile coordinates.
+    pub extent: u32,
+    /// How far, in tile coordinates, geometries are kept past the edge of the
+    /// tile, so lines and polygon outlines don't end visibly at the edge.
+    pub buffer: u32,
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        TileOptions {
+            layer_name: "features".into(),
+            extent: 4096,
+            buffer: 64,
+        }
+    }
+}
+
+/// Encodes the features intersecting `tile` as a Mapbox Vector Tile with one
+/// layer.
+///
+/// Geometries are clipped to the buffered tile and rounded to tile
+/// coordinates, and parts that become empty are dropped. Properties become
+/// the tags of each feature, with `null`s left out and arrays and objects
+/// written as JSON strings. Ids that are unsigned integers are kept. Returns
+/// an empty tile when no feature is left.
+pub fn encode_tile<T>(tree: &RTree<Feature<T>>, tile: TileAddress, options: &TileOptions) -> Vec<u8>
+where
+    T: Float + RTreeNum,
+{
+    let extent = f64::from(options.extent);
+    let buffer = f64::from(options.buffer);
+    let clipper = TileClipper {
+        tile,
+        extent,
+        min: [-buffer, -buffer],
+        max: [extent + buffer, extent + buffer],
+    };
+
+    let envelope = tile.buffered_envelope(buffer / extent);
+    let to_t = |p: [f64; 2]| -> [T; 2] {
+        [
+            T::from(p[0]).expect("A longitude fits in the coordinate type"),
+            T::from(p[1]).expect("A latitude fits in the coordinate type"),
+        ]
+    };
+    let envelope = AABB::from_corners(to_t(envelope.lower()), to_t(envelope.upper()));
+
+    let mut layer = LayerBuilder::default();
+    for feature in tree.locate_in_envelope_intersecting(&envelope) {
+        let mut shapes = TileShapes::default();
+        clipper.add(&mut shapes, &feature.into());
+        layer.add_feature(feature.id(), feature.properties(), shapes);
+    }
+
+    let mut bytes = vec![];
+    if !layer.features.is_empty() {
+        write_bytes_field(&mut bytes, 3, &layer.encode(options));
+    }
+    bytes
+}
+
+/// The points, lines and polygons of one feature in tile coordinates.
+#[derive(Default)]
+struct TileShapes {
+    points: Vec<[i32; 2]>,
+    lines: Vec<Vec<[i32; 2]>>,
+    /// Each polygon's exterior followed by its interiors, without the closing
+    /// position.
+    polygons: Vec<Vec<Vec<[i32; 2]>>>,
+}
+
+impl TileShapes {
+    fn is_empty(&self) -> bool {
+        self.points.is_empty() && self.lines.is_empty() && self.polygons.is_empty()
+    }
+}
+
+struct TileClipper {
+    tile: TileAddress,
+    extent: f64,
+    min: [f64; 2],
+    max: [f64; 2],
+}
+
+impl TileClipper {
+    fn add<T>(&self, shapes: &mut TileShapes, geometry: &Geometry<T>)
+    where
+        T: Float,
+    {
+        match geometry {
+            Geometry::Point(p) => self.add_point(shapes, p.0),
+            Geometry::Line(l) => self.add_line_string(shapes, &LineString(vec![l.start, l.end])),
+            Geometry::LineString(l) => self.add_line_string(shapes, l),
+            Geometry::Polygon(p) => self.add_polygon(shapes, p),
+            Geometry::MultiPoint(p) => p.0.iter().for_each(|p| self.add_point(shapes, p.0)),
+            Geometry::MultiLineString(l) => {
+                l.0.iter().for_each(|l| self.add_line_string(shapes, l))
+            }
+            Geometry::MultiPolygon(p) => p.0.iter().for_each(|p| self.add_polygon(shapes, p)),
+            Geometry::GeometryCollection(g) => g.0.iter().for_each(|g| self.add(shapes, g)),
+            Geometry::Rect(r) => self.add_polygon(shapes, &(*r).into()),
+            Geometry::Triangle(t) => self.add_polygon(
+                shapes,
+                &Polygon::new(LineString(vec![t.0, t.1, t.2, t.0]), vec![]),
+            ),
+        }
+    }
+
+    fn project<T>(&self, coordinate: Coordinate<T>) -> [f64; 2]
+    where
+        T: Float,
+    {
+        let [x, y] = self.tile.project(
+            coordinate
+                .x
+                .to_f64()
+                .expect("A coordinate can be widened to f64"),
+            coordinate
+                .y
+                .to_f64()
+                .expect("A coordinate can be widened to f64"),
+        );
+        [x * self.extent, y * self.extent]
+    }
+
+    fn project_line<T>(&self, line: &LineString<T>) -> Vec<[f64; 2]>
+    where
+        T: Float,
+    {
+        line.0.iter().map(|c| self.project(*c)).collect()
+    }
+
+    fn add_point<T>(&self, shapes: &mut TileShapes, coordinate: Coordinate<T>)
+    where
+        T: Float,
+    {
+        let position = self.project(coordinate);
+        if inside(position, self.min, self.max) {
+            shapes.points.push(quantise(position));
+        }
+    }
+
+    fn add_line_string<T>(&self, shapes: &mut TileShapes, line: &LineString<T>)
+    where
+        T: Float,
+    {
+        for part in clip_line(&self.project_line(line), self.min, self.max) {
+            let part = quantise_all(&part);
+            if part.len() > 1 {
+                shapes.lines.push(part);
+            }
+        }
+    }
+
+    fn add_polygon<T>(&self, shapes: &mut TileShapes, polygon: &Polygon<T>)
+    where
+        T: Float,
+    {
+        let exterior = match self.tile_ring(polygon.exterior(), true) {
+            Some(exterior) => exterior,
+            None => return,
+        };
+        let mut rings = vec![exterior];
+        rings.extend(
+            polygon
+                .interiors()
+                .iter()
+                .filter_map(|interior| self.tile_ring(interior, false)),
+        );
+        shapes.polygons.push(rings);
+    }
+
+    /// Clips and rounds a ring, then winds it clockwise on screen if it's an
+    /// exterior and anticlockwise if not, as the spec asks. Returns `None`
+    /// for a ring with no area left.
+    fn tile_ring<T>(&self, ring: &LineString<T>, exterior: bool) -> Option<Vec<[i32; 2]>>
+    where
+        T: Float,
+    {
+        let mut ring = quantise_all(&clip_ring(&self.project_line(ring), self.min, self.max));
+        if ring.len() > 1 && ring.first() == ring.last() {
+            ring.pop();
+        }
+        let area = ring_area(&ring);
+        if ring.len() < 3 || area == 0 {
+            return None;
+        }
+        if (area > 0) != exterior {
+            ring.reverse();
+        }
+        Some(ring)
+    }
+}
+
+fn quantise(position: [f64; 2]) -> [i32; 2] {
+    [position[0].round() as i32, position[1].round() as i32]
+}
+
+/// Rounds the positions and drops those that round to the previous one.
+fn quantise_all(positions: &[[f64; 2]]) -> Vec<[i32; 2]> {
+    let mut quantised: Vec<[i32; 2]> = vec![];
+    for position in positions {
+        let position = quantise(*position);
+        if quantised.last() != Some(&position) {
+            quantised.push(position);
+        }
+    }
+    quantised
+}
+
+/// Twice the signed area of an open ring, positive when it runs clockwise
+/// with y growing down.
+fn ring_area(ring: &[[i32; 2]]) -> i64 {
+    (0..ring.len())
+        .map(|i| {
+            let a = ring[i];
+            let b = ring[(i + 1) % ring.len()];
+            i64::from(a[0]) * i64::from(b[1]) - i64::from(b[0]) * i64::from(a[1])
+        })
+        .sum()
+}
+
+/// A property value as the tile stores it, hashable so equal values share
+/// one entry in the layer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum TagValue {
+    String(String),
+    /// The bits of an `f64`.
+    Double(u64),
+    Sint(i64),
+    Uint(u64),
+    Bool(bool),
+}
+
+impl TagValue {
+    fn new(value: &JsonValue) -> Option<Self> {
+        match value {
+            JsonValue::Null => None,
+            JsonValue::Bool(b) => Some(TagValue::Bool(*b)),
+            JsonValue::Number(n) => Some(if let Some(u) = n.as_u64() {
+                TagValue::Uint(u)
+            } else if let Some(i) = n.as_i64() {
+                TagValue::Sint(i)
+            } else {
+                TagValue::Double(n.as_f64().unwrap_or(f64::NAN).to_bits())
+            }),
+            JsonValue::String(s) => Some(TagValue::String(s.clone())),
+            JsonValue::Array(_) | JsonValue::Object(_) => Some(TagValue::String(value.to_string())),
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        match self {
+            TagValue::String(s) => write_bytes_field(&mut bytes, 1, s.as_bytes()),
+            TagValue::Double(bits) => {
+                write_key(&mut bytes, 3, WIRE_FIXED64);
+                bytes.extend_from_slice(&bits.to_le_bytes());
+            }
+            TagValue::Uint(u) => write_varint_field(&mut bytes, 5, *u),
+            TagValue::Sint(i) => write_varint_field(&mut bytes, 6, zigzag(*i)),
+            TagValue::Bool(b) => write_varint_field(&mut bytes, 7, u64::from(*b)),
+        }
+        bytes
+    }
+}
+
+#[derive(Default)]
+struct LayerBuilder {
+    keys: Vec<String>,
+    key_indices: HashMap<String, u32>,
+    values: Vec<TagValue>,
+    value_indices: HashMap<TagValue, u32>,
+    /// The encoded `Feature` messages.
+    features: Vec<Vec<u8>>,
+}
+
+impl LayerBuilder {
+    fn add_feature(
+        &mut self,
+        id: Option<&Id>,
+        properties: Option<&JsonObject>,
+        shapes: TileShapes,
+    ) {
+        if shapes.is_empty() {
+            return;
+        }
+        let id = match id {
+            Some(Id::Number(n)) => n.as_u64(),
+            _ => None,
+        };
+        let tags = properties
+            .map(|properties| self.tags(properties))
+            .unwrap_or_default();
+
+        if !shapes.points.is_empty() {
+            let mut geometry = GeometryEncoder::default();
+            geometry.command(MOVE_TO, &shapes.points);
+            self.push_feature(id, &tags, GEOM_POINT, &geometry.commands);
+        }
+        if !shapes.lines.is_empty() {
+            let mut geometry = GeometryEncoder::default();
+            for line in &shapes.lines {
+                geometry.command(MOVE_TO, &line[..1]);
+                geometry.command(LINE_TO, &line[1..]);
+            }
+            self.push_feature(id, &tags, GEOM_LINE_STRING, &geometry.commands);
+        }
+        if !shapes.polygons.is_empty() {
+            let mut geometry = GeometryEncoder::default();
+            for ring in shapes.polygons.iter().flatten() {
+                geometry.command(MOVE_TO, &ring[..1]);
+                geometry.command(LINE_TO, &ring[1..]);
+                geometry.command(CLOSE_PATH, &[]);
+            }
+            self.push_feature(id, &tags, GEOM_POLYGON, &geometry.commands);
+        }
+    }
+
+    fn tags(&mut self, properties: &JsonObject) -> Vec<u32> {
+        let mut tags = vec![];
+        for (key, value) in properties {
+            let value = match TagValue::new(value) {
+                Some(value) => value,
+                None => continue,
+            };
+            let key_index = match self.key_indices.get(key) {
+                Some(index) => *index,
+                None => {
+                    let index = self.keys.len() as u32;
+                    self.keys.push(key.clone());
+                    self.key_indices.insert(key.clone(), index);
+                    index
+                }
+            };
+            let value_index = match self.value_indices.get(&value) {
+                Some(index) => *index,
+                None => {
+                    let index = self.values.len() as u32;
+                    self.values.push(value.clone());
+                    self.value_indices.insert(value, index);
+                    index
+                }
+            };
+            tags.push(key_index);
+            tags.push(value_index);
+        }
+        tags
+    }
+
+    fn push_feature(&mut self, id: Option<u64>, tags: &[u32], geom_type: u64, geometry: &[u32]) {
+        let mut bytes = vec![];
+        if let Some(id) = id {
+            write_varint_field(&mut bytes, 1, id);
+        }
+        write_packed_field(&mut bytes, 2, tags);
+        write_varint_field(&mut bytes, 3, geom_type);
+        write_packed_field(&mut bytes, 4, geometry);
+        self.features.push(bytes);
+    }
+
+    fn encode(&self, options: &TileOptions) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_varint_field(&mut bytes, 15, 2);
+        write_bytes_field(&mut bytes, 1, options.layer_name.as_bytes());
+        for feature in &self.features {
+            write_bytes_field(&mut bytes, 2, feature);
+        }
+        for key in &self.keys {
+            write_bytes_field(&mut bytes, 3, key.as_bytes());
+        }
+        for value in &self.values {
+            write_bytes_field(&mut bytes, 4, &value.encode());
+        }
+        write_varint_field(&mut bytes, 5, u64::from(options.extent));
+        bytes
+    }
+}
+
+/// Writes geometry commands, whose positions are relative to the end of the
+/// previous command.
+#[derive(Default)]
+struct GeometryEncoder {
+    commands: Vec<u32>,
+    cursor: [i32; 2],
+}
+
+impl GeometryEncoder {
+    fn command(&mut self, id: u32, positions: &[[i32; 2]]) {
+        let count = if id == CLOSE_PATH { 1 } else { positions.len() };
+        if count == 0 {
+            return;
+        }
+        self.commands.push((id & 0x7) | ((count as u32) << 3));
+        for position in positions {
+            for (coordinate, cursor) in position.iter().zip(self.cursor.iter()) {
+                let delta = i64::from(*coordinate) - i64::from(*cursor);
+                self.commands.push(zigzag(delta) as u32);
+            }
+            self.cursor = *position;
+        }
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn write_key(bytes: &mut Vec<u8>, field: u32, wire_type: u32) {
+    write_varint(bytes, u64::from((field << 3) | wire_type));
+}
+
+fn write_varint_field(bytes: &mut Vec<u8>, field: u32, value: u64) {
+    write_key(bytes, field, WIRE_VARINT);
+    write_varint(bytes, value);
+}
+
+fn write_bytes_field(bytes: &mut Vec<u8>, field: u32, data: &[u8]) {
+    write_key(bytes, field, WIRE_LEN);
+    write_varint(bytes, data.len() as u64);
+    bytes.extend_from_slice(data);
+}
+
+fn write_packed_field(bytes: &mut Vec<u8>, field: u32, values: &[u32]) {
+    if values.is_empty() {
+        return;
+    }
+    let mut packed = vec![];
+    for value in values {
+        write_varint(&mut packed, u64::from(*value));
+    }
+    write_bytes_field(bytes, field, &packed);
+}
@@ -0,0 +1,104 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A JavaScript front end for `wasm32-unknown-unknown`, with the `wasm`
+//! feature. GeoJSON goes in and comes out as strings, so results can be
+//! handed to `JSON.parse` or straight to a map library. CI builds it with
+//! `cargo build --target wasm32-unknown-unknown --features wasm`.
+//!
+//! ```js
+//! import { Index } from "geojson-rstar";
+//!
+//! const index = new Index(await (await fetch("parcels.geojson")).text());
+//! const hits = JSON.parse(index.pointInPolygon(-122.4, 37.8)).features;
+//! ```
+
+use crate::{
+    query::{locate_containing_point, nearest_k_to_geometry},
+    Feature, GeometryKind,
+};
+use geojson::{FeatureCollection, GeoJson};
+use rstar::{RTree, AABB};
+use wasm_bindgen::prelude::*;
+
+/// An `RTree` of the features of a GeoJSON document. Every query returns a
+/// FeatureCollection as a GeoJSON string.
+#[wasm_bindgen(js_name = Index)]
+pub struct WasmIndex {
+    tree: RTree<Feature<f64>>,
+}
+
+#[wasm_bindgen(js_class = Index)]
+impl WasmIndex {
+    /// Loads a FeatureCollection, a Feature or a bare geometry, throwing an
+    /// `Error` if the text isn't GeoJSON or a feature can't be converted.
+    #[wasm_bindgen(constructor)]
+    pub fn new(geojson: &str) -> Result<WasmIndex, JsError> {
+        let geojson: GeoJson = geojson
+            .parse()
+            .map_err(|error| JsError::new(&format!("Invalid GeoJSON: {}", error)))?;
+        let features = Feature::from_geojson(geojson)
+            .map_err(|error| JsError::new(&format!("{:?}", error)))?;
+        Ok(WasmIndex {
+            tree: RTree::bulk_load(features),
+        })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.tree.size()
+    }
+
+    /// The `k` features nearest to the point, closest first.
+    pub fn nearest(&self, x: f64, y: f64, k: usize) -> String {
+        let point = geo::Geometry::Point(geo::Point::new(x, y));
+        to_collection(
+            nearest_k_to_geometry(&self.tree, point, k, None)
+                .into_iter()
+                .map(|(feature, _)| feature),
+        )
+    }
+
+    /// The features whose bbox intersects the box.
+    pub fn bbox(&self, min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> String {
+        let envelope = AABB::from_corners([min_x, min_y], [max_x, max_y]);
+        to_collection(self.tree.locate_in_envelope_intersecting(&envelope))
+    }
+
+    /// The polygons and multi polygons that contain the point, their
+    /// boundary included.
+    #[wasm_bindgen(js_name = pointInPolygon)]
+    pub fn point_in_polygon(&self, x: f64, y: f64) -> String {
+        to_collection(
+            locate_containing_point(&self.tree, &[x, y]).filter(|feature| {
+                matches!(
+                    feature.kind(),
+                    GeometryKind::Polygon | GeometryKind::MultiPolygon
+                )
+            }),
+        )
+    }
+}
+
+fn to_collection<'a, I>(features: I) -> String
+where
+    I: Iterator<Item = &'a Feature<f64>>,
+{
+    GeoJson::FeatureCollection(FeatureCollection {
+        bbox: None,
+        features: features.cloned().map(Into::into).collect(),
+        foreign_members: None,
+    })
+    .to_string()
+}
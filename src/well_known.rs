@@ -0,0 +1,543 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading and writing features as Well-Known Text (WKT) and Well-Known
+//! Binary (WKB), the formats databases such as PostGIS hand out geometries in.
+
+use crate::{
+    error::GeoJsonConversionError, generic::create_feature, json::JsonObject, Feature,
+    GeometryCollectionFeature, LineStringFeature, MultiLineStringFeature, MultiPointFeature,
+    MultiPolygonFeature, PointFeature, PolygonFeature,
+};
+use geojson::{feature::Id, Geometry, LineStringType, PointType, PolygonType, Value};
+use num_traits::Float;
+use std::{convert::TryFrom, str::FromStr};
+use wkt::{
+    types::{
+        Coord, GeometryCollection as WktCollection, LineString as WktLineString,
+        MultiLineString as WktMultiLineString, MultiPoint as WktMultiPoint,
+        MultiPolygon as WktMultiPolygon, Point as WktPoint, Polygon as WktPolygon,
+    },
+    Wkt,
+};
+
+const WKB_POINT: u32 = 1;
+const WKB_LINE_STRING: u32 = 2;
+const WKB_POLYGON: u32 = 3;
+const WKB_MULTI_POINT: u32 = 4;
+const WKB_MULTI_LINE_STRING: u32 = 5;
+const WKB_MULTI_POLYGON: u32 = 6;
+const WKB_GEOMETRY_COLLECTION: u32 = 7;
+
+/// Flags PostGIS sets on the geometry type of Extended WKB.
+const EWKB_Z: u32 = 0x8000_0000;
+const EWKB_M: u32 = 0x4000_0000;
+const EWKB_SRID: u32 = 0x2000_0000;
+
+/// Building a feature from WKT or WKB plus its id and properties, and
+/// writing its geometry back out.
+///
+/// The geometry goes through `TryFrom<geojson::Feature>`, so it is checked
+/// the same way as a GeoJSON geometry: positions with a Z or M coordinate,
+/// empty points and non-finite coordinates are rejected.
+pub trait WellKnown: TryFrom<geojson::Feature, Error = GeoJsonConversionError> {
+    /// The geometry of the feature as a GeoJSON `Value`.
+    fn geometry_value(&self) -> Value;
+
+    fn from_wkt(
+        text: &str,
+        id: Option<Id>,
+        properties: Option<JsonObject>,
+    ) -> Result<Self, GeoJsonConversionError> {
+        Self::try_from(create_feature(wkt_to_value(text)?, id, properties))
+    }
+
+    fn from_wkb(
+        bytes: &[u8],
+        id: Option<Id>,
+        properties: Option<JsonObject>,
+    ) -> Result<Self, GeoJsonConversionError> {
+        Self::try_from(create_feature(wkb_to_value(bytes)?, id, properties))
+    }
+
+    fn to_wkt(&self) -> String {
+        value_to_wkt(&self.geometry_value())
+    }
+
+    /// Little endian WKB with two dimensional ISO geometry types.
+    fn to_wkb(&self) -> Vec<u8> {
+        value_to_wkb(&self.geometry_value())
+    }
+}
+
+impl<T> WellKnown for PointFeature<T>
+where
+    T: Float,
+{
+    fn geometry_value(&self) -> Value {
+        Value::Point(self.point())
+    }
+}
+
+impl<T> WellKnown for LineStringFeature<T>
+where
+    T: Float,
+{
+    fn geometry_value(&self) -> Value {
+        Value::LineString(self.line())
+    }
+}
+
+impl<T> WellKnown for PolygonFeature<T>
+where
+    T: Float,
+{
+    fn geometry_value(&self) -> Value {
+        Value::Polygon(self.polygon())
+    }
+}
+
+impl<T> WellKnown for MultiPointFeature<T>
+where
+    T: Float,
+{
+    fn geometry_value(&self) -> Value {
+        Value::MultiPoint(self.points())
+    }
+}
+
+impl<T> WellKnown for MultiLineStringFeature<T>
+where
+    T: Float,
+{
+    fn geometry_value(&self) -> Value {
+        Value::MultiLineString(self.lines())
+    }
+}
+
+impl<T> WellKnown for MultiPolygonFeature<T>
+where
+    T: Float,
+{
+    fn geometry_value(&self) -> Value {
+        Value::MultiPolygon(self.polygons())
+    }
+}
+
+impl<T> WellKnown for GeometryCollectionFeature<T>
+where
+    T: Float,
+{
+    fn geometry_value(&self) -> Value {
+        Value::GeometryCollection(self.geometries().to_vec())
+    }
+}
+
+impl<T> WellKnown for Feature<T>
+where
+    T: Float,
+{
+    fn geometry_value(&self) -> Value {
+        match self {
+            Feature::Point(f) => f.geometry_value(),
+            Feature::Polygon(f) => f.geometry_value(),
+            Feature::LineString(f) => f.geometry_value(),
+            Feature::MultiPoint(f) => f.geometry_value(),
+            Feature::MultiLineString(f) => f.geometry_value(),
+            Feature::MultiPolygon(f) => f.geometry_value(),
+            Feature::GeometryCollection(f) => f.geometry_value(),
+        }
+    }
+}
+
+/// Parses WKT into a GeoJSON `Value`. Z and M coordinates are kept, after X
+/// and Y, and an empty point becomes an empty position.
+pub fn wkt_to_value(text: &str) -> Result<Value, GeoJsonConversionError> {
+    let wkt = Wkt::<f64>::from_str(text)
+        .map_err(|e| GeoJsonConversionError::InvalidWkt(e.to_string()))?;
+    Ok(wkt_geometry_to_value(wkt))
+}
+
+fn wkt_geometry_to_value(geometry: Wkt<f64>) -> Value {
+    match geometry {
+        Wkt::Point(p) => Value::Point(wkt_point_to_position(p)),
+        Wkt::LineString(l) => Value::LineString(wkt_line_to_positions(l)),
+        Wkt::Polygon(p) => Value::Polygon(wkt_polygon_to_rings(p)),
+        Wkt::MultiPoint(p) => {
+            Value::MultiPoint(p.0.into_iter().map(wkt_point_to_position).collect())
+        }
+        Wkt::MultiLineString(l) => {
+            Value::MultiLineString(l.0.into_iter().map(wkt_line_to_positions).collect())
+        }
+        Wkt::MultiPolygon(p) => {
+            Value::MultiPolygon(p.0.into_iter().map(wkt_polygon_to_rings).collect())
+        }
+        Wkt::GeometryCollection(g) => Value::GeometryCollection(
+            g.0.into_iter()
+                .map(|geometry| Geometry::new(wkt_geometry_to_value(geometry)))
+                .collect(),
+        ),
+    }
+}
+
+fn coord_to_position(coord: Coord<f64>) -> PointType {
+    let mut position = vec![coord.x, coord.y];
+    position.extend(coord.z);
+    position.extend(coord.m);
+    position
+}
+
+fn wkt_point_to_position(point: WktPoint<f64>) -> PointType {
+    point.0.map(coord_to_position).unwrap_or_default()
+}
+
+fn wkt_line_to_positions(line: WktLineString<f64>) -> LineStringType {
+    line.0.into_iter().map(coord_to_position).collect()
+}
+
+fn wkt_polygon_to_rings(polygon: WktPolygon<f64>) -> PolygonType {
+    polygon.0.into_iter().map(wkt_line_to_positions).collect()
+}
+
+/// Writes a GeoJSON `Value` as WKT.
+///
+/// Only the first two coordinates of each position are written. A position
+/// with fewer is written as an empty point, or left out of lines and rings.
+pub fn value_to_wkt(value: &Value) -> String {
+    value_to_wkt_geometry(value).to_string()
+}
+
+fn value_to_wkt_geometry(value: &Value) -> Wkt<f64> {
+    match value {
+        Value::Point(p) => Wkt::Point(position_to_wkt_point(p)),
+        Value::LineString(l) => Wkt::LineString(positions_to_wkt_line(l)),
+        Value::Polygon(p) => Wkt::Polygon(rings_to_wkt_polygon(p)),
+        Value::MultiPoint(p) => Wkt::MultiPoint(WktMultiPoint(
+            p.iter().map(|p| position_to_wkt_point(p)).collect(),
+        )),
+        Value::MultiLineString(l) => Wkt::MultiLineString(WktMultiLineString(
+            l.iter().map(|l| positions_to_wkt_line(l)).collect(),
+        )),
+        Value::MultiPolygon(p) => Wkt::MultiPolygon(WktMultiPolygon(
+            p.iter().map(|p| rings_to_wkt_polygon(p)).collect(),
+        )),
+        Value::GeometryCollection(g) => Wkt::GeometryCollection(WktCollection(
+            g.iter()
+                .map(|geometry| value_to_wkt_geometry(&geometry.value))
+                .collect(),
+        )),
+    }
+}
+
+fn position_to_coord(position: &[f64]) -> Option<Coord<f64>> {
+    match position {
+        [x, y, ..] => Some(Coord {
+            x: *x,
+            y: *y,
+            z: None,
+            m: None,
+        }),
+        _ => None,
+    }
+}
+
+fn position_to_wkt_point(position: &[f64]) -> WktPoint<f64> {
+    WktPoint(position_to_coord(position))
+}
+
+fn positions_to_wkt_line(positions: &[PointType]) -> WktLineString<f64> {
+    WktLineString(
+        positions
+            .iter()
+            .filter_map(|p| position_to_coord(p))
+            .collect(),
+    )
+}
+
+fn rings_to_wkt_polygon(rings: &[LineStringType]) -> WktPolygon<f64> {
+    WktPolygon(
+        rings
+            .iter()
+            .map(|ring| positions_to_wkt_line(ring))
+            .collect(),
+    )
+}
+
+/// Reads WKB, or the Extended WKB PostGIS uses, into a GeoJSON `Value`.
+///
+/// Either byte order is accepted. Z and M coordinates are kept, after X and
+/// Y, and an SRID is skipped. Multi geometries and collections nested more
+/// than 64 deep are rejected.
+pub fn wkb_to_value(bytes: &[u8]) -> Result<Value, GeoJsonConversionError> {
+    let mut reader = WkbReader { bytes, offset: 0 };
+    let value = reader.read_geometry(0)?;
+    if reader.offset != bytes.len() {
+        return Err(invalid_wkb("Trailing bytes after the geometry"));
+    }
+    Ok(value)
+}
+
+/// How deeply multi geometries and collections may be nested, so untrusted
+/// WKB can't overflow the stack.
+const MAX_WKB_DEPTH: usize = 64;
+
+fn invalid_wkb(message: &str) -> GeoJsonConversionError {
+    GeoJsonConversionError::InvalidWkb(message.into())
+}
+
+struct WkbReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> WkbReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], GeoJsonConversionError> {
+        let end = self
+            .offset
+            .checked_add(count)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| invalid_wkb("Unexpected end of the bytes"))?;
+        let taken = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(taken)
+    }
+
+    fn read_u32(&mut self, little_endian: bool) -> Result<u32, GeoJsonConversionError> {
+        let mut word = [0; 4];
+        word.copy_from_slice(self.take(4)?);
+        Ok(if little_endian {
+            u32::from_le_bytes(word)
+        } else {
+            u32::from_be_bytes(word)
+        })
+    }
+
+    fn read_f64(&mut self, little_endian: bool) -> Result<f64, GeoJsonConversionError> {
+        let mut word = [0; 8];
+        word.copy_from_slice(self.take(8)?);
+        Ok(if little_endian {
+            f64::from_le_bytes(word)
+        } else {
+            f64::from_be_bytes(word)
+        })
+    }
+
+    /// Reads a geometry nested `depth` multi geometries or collections deep.
+    fn read_geometry(&mut self, depth: usize) -> Result<Value, GeoJsonConversionError> {
+        let little_endian = match self.take(1)?[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid_wkb("Unknown byte order")),
+        };
+        let code = self.read_u32(little_endian)?;
+        if code & EWKB_SRID != 0 {
+            self.read_u32(little_endian)?;
+        }
+        let mut dimensions = 2;
+        if code & EWKB_Z != 0 {
+            dimensions += 1;
+        }
+        if code & EWKB_M != 0 {
+            dimensions += 1;
+        }
+        // ISO WKB adds 1000 for Z, 2000 for M and 3000 for both to the type.
+        let code = code & !(EWKB_Z | EWKB_M | EWKB_SRID);
+        dimensions += match code / 1000 {
+            0 => 0,
+            1 | 2 => 1,
+            3 => 2,
+            _ => return Err(invalid_wkb("Unknown geometry type")),
+        };
+
+        let reading = Reading {
+            little_endian,
+            dimensions,
+        };
+        let depth = depth + 1;
+        Ok(match code % 1000 {
+            WKB_POINT => Value::Point(self.read_position(&reading)?),
+            WKB_LINE_STRING => Value::LineString(self.read_positions(&reading)?),
+            WKB_POLYGON => Value::Polygon(self.read_rings(&reading)?),
+            WKB_MULTI_POINT => {
+                Value::MultiPoint(self.read_members(depth, little_endian, |v| match v {
+                    Value::Point(p) => Some(p),
+                    _ => None,
+                })?)
+            }
+            WKB_MULTI_LINE_STRING => {
+                Value::MultiLineString(self.read_members(depth, little_endian, |v| match v {
+                    Value::LineString(l) => Some(l),
+                    _ => None,
+                })?)
+            }
+            WKB_MULTI_POLYGON => {
+                Value::MultiPolygon(self.read_members(depth, little_endian, |v| match v {
+                    Value::Polygon(p) => Some(p),
+                    _ => None,
+                })?)
+            }
+            WKB_GEOMETRY_COLLECTION => {
+                Value::GeometryCollection(
+                    self.read_members(depth, little_endian, |v| Some(Geometry::new(v)))?,
+                )
+            }
+            _ => return Err(invalid_wkb("Unknown geometry type")),
+        })
+    }
+
+    /// An empty point is written as NaN coordinates, which are rejected when
+    /// the feature is created.
+    fn read_position(&mut self, reading: &Reading) -> Result<PointType, GeoJsonConversionError> {
+        (0..reading.dimensions)
+            .map(|_| self.read_f64(reading.little_endian))
+            .collect()
+    }
+
+    fn read_positions(
+        &mut self,
+        reading: &Reading,
+    ) -> Result<LineStringType, GeoJsonConversionError> {
+        let count = self.read_u32(reading.little_endian)?;
+        (0..count).map(|_| self.read_position(reading)).collect()
+    }
+
+    fn read_rings(&mut self, reading: &Reading) -> Result<PolygonType, GeoJsonConversionError> {
+        let count = self.read_u32(reading.little_endian)?;
+        (0..count).map(|_| self.read_positions(reading)).collect()
+    }
+
+    /// Reads the geometries of a multi geometry or collection, each of which
+    /// has its own byte order and type.
+    fn read_members<M, F>(
+        &mut self,
+        depth: usize,
+        little_endian: bool,
+        member: F,
+    ) -> Result<Vec<M>, GeoJsonConversionError>
+    where
+        F: Fn(Value) -> Option<M>,
+    {
+        if depth > MAX_WKB_DEPTH {
+            return Err(invalid_wkb("Geometry collections nested too deeply"));
+        }
+        let count = self.read_u32(little_endian)?;
+        (0..count)
+            .map(|_| {
+                let value = self.read_geometry(depth)?;
+                member(value).ok_or_else(|| invalid_wkb("Wrong geometry type in a multi geometry"))
+            })
+            .collect()
+    }
+}
+
+/// How the coordinates of one geometry are laid out.
+struct Reading {
+    little_endian: bool,
+    dimensions: usize,
+}
+
+/// Writes a GeoJSON `Value` as little endian, two dimensional WKB.
+///
+/// Only the first two coordinates of each position are written, and a
+/// missing coordinate is written as NaN, which is how WKB marks an empty
+/// point.
+pub fn value_to_wkb(value: &Value) -> Vec<u8> {
+    let mut bytes = vec![];
+    write_geometry(&mut bytes, value);
+    bytes
+}
+
+fn write_geometry(bytes: &mut Vec<u8>, value: &Value) {
+    bytes.push(1);
+    match value {
+        Value::Point(p) => {
+            write_u32(bytes, WKB_POINT);
+            write_position(bytes, p);
+        }
+        Value::LineString(l) => {
+            write_u32(bytes, WKB_LINE_STRING);
+            write_positions(bytes, l);
+        }
+        Value::Polygon(p) => {
+            write_u32(bytes, WKB_POLYGON);
+            write_rings(bytes, p);
+        }
+        Value::MultiPoint(p) => {
+            write_u32(bytes, WKB_MULTI_POINT);
+            write_count(bytes, p.len());
+            for point in p {
+                bytes.push(1);
+                write_u32(bytes, WKB_POINT);
+                write_position(bytes, point);
+            }
+        }
+        Value::MultiLineString(l) => {
+            write_u32(bytes, WKB_MULTI_LINE_STRING);
+            write_count(bytes, l.len());
+            for line in l {
+                bytes.push(1);
+                write_u32(bytes, WKB_LINE_STRING);
+                write_positions(bytes, line);
+            }
+        }
+        Value::MultiPolygon(p) => {
+            write_u32(bytes, WKB_MULTI_POLYGON);
+            write_count(bytes, p.len());
+            for polygon in p {
+                bytes.push(1);
+                write_u32(bytes, WKB_POLYGON);
+                write_rings(bytes, polygon);
+            }
+        }
+        Value::GeometryCollection(g) => {
+            write_u32(bytes, WKB_GEOMETRY_COLLECTION);
+            write_count(bytes, g.len());
+            for geometry in g {
+                write_geometry(bytes, &geometry.value);
+            }
+        }
+    }
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_count(bytes: &mut Vec<u8>, count: usize) {
+    write_u32(
+        bytes,
+        u32::try_from(count).expect("WKB can't hold more than u32::MAX parts"),
+    );
+}
+
+fn write_position(bytes: &mut Vec<u8>, position: &[f64]) {
+    for index in 0..2 {
+        let coordinate = position.get(index).copied().unwrap_or(f64::NAN);
+        bytes.extend_from_slice(&coordinate.to_le_bytes());
+    }
+}
+
+fn write_positions(bytes: &mut Vec<u8>, positions: &[PointType]) {
+    write_count(bytes, positions.len());
+    for position in positions {
+        write_position(bytes, position);
+    }
+}
+
+fn write_rings(bytes: &mut Vec<u8>, rings: &[LineStringType]) {
+    write_count(bytes, rings.len());
+    for ring in rings {
+        write_positions(bytes, ring);
+    }
+}
@@ -0,0 +1,35 @@
+// Copyright 2020 Boyd Johnson
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fixtures shared by the integration tests. Each test file uses only some
+//! of them.
+#![allow(dead_code)]
+
+use geojson::GeoJson;
+
+/// Parses `geojson`, which has to be a Feature.
+pub fn parse_feature(geojson: &str) -> geojson::Feature {
+    match geojson.parse::<GeoJson>() {
+        Ok(GeoJson::Feature(feature)) => feature,
+        _ => panic!("The geojson did not parse correctly"),
+    }
+}
+
+/// A Feature without properties for the GeoJSON `geometry`.
+pub fn geometry_feature(geometry: &str) -> geojson::Feature {
+    parse_feature(&format!(
+        r#"{{ "type": "Feature", "properties": null, "geometry": {} }}"#,
+        geometry
+    ))
+}
@@ -0,0 +1,156 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+extern crate serde_json;
+
+use geojson_rstar::{
+    aggregate::{aggregate, aggregate_to_collection, Aggregation, CellShape},
+    AggregateError, Feature, PolygonFeature,
+};
+use rstar::{RTree, AABB};
+use serde_json::{json, Value};
+use std::convert::TryInto;
+
+mod common;
+
+fn feature(geometry: &str, properties: Value) -> Feature {
+    let mut feature = common::geometry_feature(geometry);
+    feature.properties = properties.as_object().cloned();
+    feature.try_into().expect("The feature is valid")
+}
+
+fn point(x: f64, y: f64, properties: Value) -> Feature {
+    feature(
+        &format!(r#"{{ "type": "Point", "coordinates": [{}, {}] }}"#, x, y),
+        properties,
+    )
+}
+
+fn property<'a>(cell: &'a PolygonFeature, key: &str) -> &'a Value {
+    &cell.properties.as_ref().unwrap()[key]
+}
+
+#[test]
+fn test_square_aggregation() {
+    let tree = RTree::bulk_load(vec![
+        point(1.0, 1.0, json!({ "injured": 10 })),
+        point(1.5, 0.5, json!({ "injured": 20 })),
+        // On the edge of the extent, which is still in the first cell.
+        point(0.0, 1.0, json!({ "injured": 6 })),
+        point(3.0, 1.0, json!({ "injured": "n/a" })),
+        point(9.0, 9.0, json!({ "injured": 100 })),
+        feature(
+            r#"{ "type": "LineString", "coordinates": [[1.0, 1.5], [3.0, 1.5]] }"#,
+            json!({}),
+        ),
+    ]);
+    let aggregation = Aggregation {
+        shape: CellShape::Square,
+        cell_size: 2.0,
+        extent: AABB::from_corners([0.0, 0.0], [4.0, 2.0]),
+        property: Some("injured".into()),
+        include_empty: false,
+    };
+    let cells = aggregate(&tree, &aggregation).expect("The extent is small enough");
+    assert_eq!(cells.len(), 2);
+
+    let first = cells
+        .iter()
+        .find(|cell| property(cell, "column") == &json!(0))
+        .expect("The first cell has features");
+    assert_eq!(
+        first.polygon(),
+        vec![vec![
+            vec![0.0, 0.0],
+            vec![2.0, 0.0],
+            vec![2.0, 2.0],
+            vec![0.0, 2.0],
+            vec![0.0, 0.0]
+        ]]
+    );
+    assert_eq!(property(first, "row"), &json!(0));
+    assert_eq!(property(first, "count"), &json!(4));
+    assert_eq!(property(first, "sum"), &json!(36.0));
+    assert_eq!(property(first, "mean"), &json!(12.0));
+
+    let second = cells
+        .iter()
+        .find(|cell| property(cell, "column") == &json!(1))
+        .expect("The second cell has features");
+    assert_eq!(property(second, "count"), &json!(2));
+    assert_eq!(property(second, "sum"), &json!(0.0));
+    assert_eq!(property(second, "mean"), &Value::Null);
+}
+
+#[test]
+fn test_hexagon_aggregation_counts_points_once() {
+    let points: Vec<Feature> = (0..=20)
+        .flat_map(|i| (0..=20).map(move |j| point(i as f64 * 0.5, j as f64 * 0.5, json!({}))))
+        .collect();
+    let total = points.len();
+    let tree = RTree::bulk_load(points);
+    let aggregation = Aggregation {
+        shape: CellShape::Hexagon,
+        cell_size: 1.0,
+        extent: AABB::from_corners([0.0, 0.0], [10.0, 10.0]),
+        property: None,
+        include_empty: true,
+    };
+
+    let collection =
+        aggregate_to_collection(&tree, &aggregation).expect("The extent is small enough");
+    let counted: u64 = collection
+        .features
+        .iter()
+        .map(|cell| {
+            let properties = cell.properties.as_ref().unwrap();
+            assert!(!properties.contains_key("sum"));
+            properties["count"].as_u64().unwrap()
+        })
+        .sum();
+    assert_eq!(counted as usize, total);
+
+    match &collection.features[0].geometry.as_ref().unwrap().value {
+        geojson::Value::Polygon(rings) => assert_eq!(rings[0].len(), 7),
+        _ => panic!("A cell is a polygon"),
+    }
+}
+
+#[test]
+fn test_extents_that_cant_be_covered() {
+    let tree = RTree::bulk_load(vec![point(1.0, 1.0, json!({}))]);
+    let aggregation = |shape: CellShape, lower: [f64; 2], upper: [f64; 2]| Aggregation {
+        shape,
+        cell_size: 1.0,
+        extent: AABB::from_corners(lower, upper),
+        property: None,
+        include_empty: true,
+    };
+
+    for shape in &[CellShape::Square, CellShape::Hexagon] {
+        for (lower, upper) in &[
+            ([0.0, 0.0], [f64::INFINITY, 1.0]),
+            ([f64::NEG_INFINITY, 0.0], [1.0, 1.0]),
+            ([0.0, f64::NAN], [1.0, f64::NAN]),
+            ([-f64::MAX, 0.0], [f64::MAX, 1.0]),
+        ] {
+            assert_eq!(
+                aggregate(&tree, &aggregation(*shape, *lower, *upper)),
+                Err(AggregateError::NonFiniteExtent)
+            );
+        }
+
+        // The last two have few enough cells along each axis, but not in
+        // total.
+        for (lower, upper) in &[
+            ([0.0, 0.0], [1e300, 1.0]),
+            ([0.0, 0.0], [1e6, 1e6]),
+            ([0.0, 0.0], [2048.0, 2048.0]),
+        ] {
+            assert_eq!(
+                aggregate(&tree, &aggregation(*shape, *lower, *upper)),
+                Err(AggregateError::TooManyCells)
+            );
+        }
+    }
+}
@@ -0,0 +1,233 @@
+extern crate geo;
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+extern crate serde_json;
+
+use geo::{LineString, Polygon};
+use geojson::feature::Id;
+use geojson_rstar::{
+    clip::{clip_feature_to_polygon, clip_feature_to_rect, clip_to_polygon, clip_to_rect},
+    Feature, GeometryKind, Measure,
+};
+use rstar::{RTree, RTreeObject, AABB};
+use serde_json::json;
+use std::convert::TryInto;
+
+mod common;
+
+fn named(geometry: &str, name: &str) -> Feature {
+    let mut feature = common::geometry_feature(geometry);
+    feature.id = Some(Id::String(name.into()));
+    feature.properties = json!({ "name": name }).as_object().cloned();
+    feature.try_into().expect("The feature is valid")
+}
+
+fn tree() -> RTree<Feature> {
+    RTree::bulk_load(vec![
+        named(
+            r#"{ "type": "LineString", "coordinates": [[-5.0, 5.0], [5.0, 5.0], [5.0, 15.0], [15.0, 15.0]] }"#,
+            "road",
+        ),
+        named(
+            r#"{ "type": "Polygon", "coordinates": [[[5.0, 5.0], [15.0, 5.0], [15.0, 15.0], [5.0, 15.0], [5.0, 5.0]]] }"#,
+            "park",
+        ),
+        named(r#"{ "type": "Point", "coordinates": [2.0, 2.0] }"#, "stop"),
+        named(r#"{ "type": "Point", "coordinates": [20.0, 20.0] }"#, "far"),
+    ])
+}
+
+fn find<'a>(features: &'a [Feature], name: &str) -> Option<&'a Feature> {
+    features
+        .iter()
+        .find(|f| f.properties().and_then(|p| p.get("name")) == Some(&name.into()))
+}
+
+fn to_geojson(feature: &Feature) -> geojson::Feature {
+    feature.clone().into()
+}
+
+#[test]
+fn test_clip_to_rect() {
+    let clipped = clip_to_rect(&tree(), &AABB::from_corners([0.0, 0.0], [10.0, 10.0]))
+        .expect("The clipped features can be converted");
+    assert_eq!(clipped.len(), 3);
+    assert!(find(&clipped, "far").is_none());
+
+    let road = find(&clipped, "road").expect("The road crosses the rect");
+    assert_eq!(
+        road.id(),
+        Some(&geojson::feature::Id::String("road".into()))
+    );
+    assert_eq!(
+        to_geojson(road).geometry.unwrap().value,
+        geojson::Value::LineString(vec![vec![0.0, 5.0], vec![5.0, 5.0], vec![5.0, 10.0]])
+    );
+    assert_eq!(road.envelope(), AABB::from_corners([0.0, 5.0], [5.0, 10.0]));
+
+    let park = find(&clipped, "park").expect("The park overlaps the rect");
+    assert_eq!(park.kind(), GeometryKind::Polygon);
+    assert_eq!(
+        park.envelope(),
+        AABB::from_corners([5.0, 5.0], [10.0, 10.0])
+    );
+}
+
+/// A U shape, open at the top.
+fn city() -> Polygon<f64> {
+    Polygon::new(
+        LineString::from(vec![
+            (0.0, 0.0),
+            (12.0, 0.0),
+            (12.0, 12.0),
+            (8.0, 12.0),
+            (8.0, 4.0),
+            (4.0, 4.0),
+            (4.0, 12.0),
+            (0.0, 12.0),
+            (0.0, 0.0),
+        ]),
+        vec![],
+    )
+}
+
+#[test]
+fn test_clip_line_to_concave_polygon() {
+    // The road goes in and out of the U.
+    let city = city();
+    let road = named(
+        r#"{ "type": "LineString", "coordinates": [[-2.0, 8.0], [14.0, 8.0]] }"#,
+        "road",
+    );
+
+    let clipped = clip_feature_to_polygon(&road, &city)
+        .expect("Lines can be clipped to any polygon")
+        .expect("The road crosses the city");
+    assert_eq!(clipped.kind(), GeometryKind::MultiLineString);
+    assert_eq!(
+        to_geojson(&clipped).geometry.unwrap().value,
+        geojson::Value::MultiLineString(vec![
+            vec![vec![0.0, 8.0], vec![4.0, 8.0]],
+            vec![vec![8.0, 8.0], vec![12.0, 8.0]],
+        ])
+    );
+    assert_eq!(
+        clipped.envelope(),
+        AABB::from_corners([0.0, 8.0], [12.0, 8.0])
+    );
+}
+
+#[test]
+fn test_clip_polygon_to_concave_polygon() {
+    // A block across both arms of the U is cut in two.
+    let block = named(
+        r#"{ "type": "Polygon", "coordinates": [[[2.0, 6.0], [10.0, 6.0], [10.0, 10.0], [2.0, 10.0], [2.0, 6.0]]] }"#,
+        "block",
+    );
+    let clipped = clip_feature_to_polygon(&block, &city())
+        .expect("Polygons can be clipped to any polygon")
+        .expect("The block overlaps the city");
+    assert_eq!(clipped.kind(), GeometryKind::MultiPolygon);
+    assert_eq!(clipped.area(), 16.0);
+    assert_eq!(
+        clipped.envelope(),
+        AABB::from_corners([2.0, 6.0], [10.0, 10.0])
+    );
+
+    // A block across the bottom of the U keeps its shape where it is inside.
+    let base = named(
+        r#"{ "type": "Polygon", "coordinates": [[[2.0, 2.0], [10.0, 2.0], [10.0, 6.0], [2.0, 6.0], [2.0, 2.0]]] }"#,
+        "base",
+    );
+    let clipped = clip_feature_to_polygon(&base, &city())
+        .expect("Polygons can be clipped to any polygon")
+        .expect("The base overlaps the city");
+    assert_eq!(clipped.kind(), GeometryKind::Polygon);
+    assert_eq!(clipped.area(), 24.0);
+
+    // Inside the gap of the U nothing is left.
+    let gap = named(
+        r#"{ "type": "Polygon", "coordinates": [[[5.0, 5.0], [7.0, 5.0], [7.0, 11.0], [5.0, 11.0], [5.0, 5.0]]] }"#,
+        "gap",
+    );
+    assert!(matches!(clip_feature_to_polygon(&gap, &city()), Ok(None)));
+}
+
+#[test]
+fn test_clip_polygon_to_polygon_with_hole() {
+    let area = Polygon::new(
+        LineString::from(vec![
+            (0.0, 0.0),
+            (20.0, 0.0),
+            (20.0, 20.0),
+            (0.0, 20.0),
+            (0.0, 0.0),
+        ]),
+        vec![LineString::from(vec![
+            (8.0, 8.0),
+            (12.0, 8.0),
+            (12.0, 12.0),
+            (8.0, 12.0),
+            (8.0, 8.0),
+        ])],
+    );
+    let clipped = clip_to_polygon(&tree(), &area).expect("Polygons can be clipped to any polygon");
+    let park = find(&clipped, "park").expect("The park overlaps the area");
+    match to_geojson(park).geometry.unwrap().value {
+        geojson::Value::Polygon(rings) => assert_eq!(rings.len(), 2),
+        _ => panic!("The park is still a polygon"),
+    }
+    assert_eq!(park.area(), 84.0);
+
+    // A polygon sharing the edge of the hole, but outside it, is unchanged.
+    let ring = named(
+        r#"{ "type": "Polygon", "coordinates": [[[12.0, 8.0], [16.0, 8.0], [16.0, 12.0], [12.0, 12.0], [12.0, 8.0]]] }"#,
+        "ring",
+    );
+    let clipped = clip_feature_to_polygon(&ring, &area)
+        .expect("Polygons can be clipped to any polygon")
+        .expect("The polygon is inside the area");
+    assert_eq!(clipped.area(), 16.0);
+    assert_eq!(
+        clipped.envelope(),
+        AABB::from_corners([12.0, 8.0], [16.0, 12.0])
+    );
+}
+
+#[test]
+fn test_clip_polygon_to_convex_polygon() {
+    // A triangle, clockwise, over the lower left of the park.
+    let area = Polygon::new(
+        LineString::from(vec![(0.0, 0.0), (0.0, 20.0), (20.0, 0.0), (0.0, 0.0)]),
+        vec![],
+    );
+    let clipped = clip_to_polygon(&tree(), &area).expect("The park can be clipped");
+    assert!(find(&clipped, "far").is_none());
+    assert!(find(&clipped, "stop").is_some());
+
+    let park = find(&clipped, "park").expect("The park overlaps the triangle");
+    match to_geojson(park).geometry.unwrap().value {
+        geojson::Value::Polygon(rings) => {
+            assert_eq!(rings.len(), 1);
+            assert_eq!(rings[0].len(), 4);
+            assert_eq!(rings[0].first(), rings[0].last());
+        }
+        _ => panic!("The park is still a polygon"),
+    }
+    assert_eq!(
+        park.envelope(),
+        AABB::from_corners([5.0, 5.0], [15.0, 15.0])
+    );
+}
+
+#[test]
+fn test_degenerate_clips_are_dropped() {
+    // Only the corner of the park is left, which has no area and is dropped.
+    let corner = AABB::from_corners([15.0, 15.0], [20.0, 20.0]);
+    let park = named(
+        r#"{ "type": "Polygon", "coordinates": [[[5.0, 5.0], [15.0, 5.0], [15.0, 15.0], [5.0, 15.0], [5.0, 5.0]]] }"#,
+        "park",
+    );
+    assert!(matches!(clip_feature_to_rect(&park, &corner), Ok(None)));
+}
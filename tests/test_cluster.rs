@@ -0,0 +1,126 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+extern crate serde_json;
+
+use geojson::GeoJson;
+use geojson_rstar::{
+    cluster::{dbscan, label_clusters, ClusterDistance, ClusterLabel, Dbscan},
+    PointFeature,
+};
+use rstar::RTree;
+use std::{collections::HashMap, convert::TryInto};
+
+fn tree(points: &[(&str, f64, f64)]) -> RTree<PointFeature> {
+    RTree::bulk_load(
+        points
+            .iter()
+            .map(|(name, x, y)| {
+                let geojson = format!(
+                    r#"{{ "type": "Feature", "properties": {{ "name": "{}" }}, "geometry": {{ "type": "Point", "coordinates": [{}, {}] }} }}"#,
+                    name, x, y
+                );
+                match geojson.parse::<GeoJson>() {
+                    Ok(GeoJson::Feature(feature)) => feature.try_into().unwrap(),
+                    _ => panic!("The geojson did not parse correctly"),
+                }
+            })
+            .collect(),
+    )
+}
+
+fn name(point: &PointFeature) -> String {
+    point.properties.as_ref().unwrap()["name"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+fn labels_by_name(tree: &RTree<PointFeature>, options: &Dbscan) -> HashMap<String, ClusterLabel> {
+    dbscan(tree, options)
+        .into_iter()
+        .map(|(point, label)| (name(point), label))
+        .collect()
+}
+
+#[test]
+fn test_dbscan_planar() {
+    let tree = tree(&[
+        ("a1", 0.0, 0.0),
+        ("a2", 0.5, 0.0),
+        ("a3", 1.0, 0.0),
+        ("a4", 1.5, 0.0),
+        ("b1", 10.0, 10.0),
+        ("b2", 10.0, 10.5),
+        ("b3", 10.5, 10.0),
+        ("lone", 5.0, 5.0),
+        // Only near a4, so it is on the border of the first cluster.
+        ("edge", 2.0, 0.0),
+    ]);
+    let options = Dbscan {
+        eps: 0.5,
+        min_points: 3,
+        distance: ClusterDistance::Planar,
+    };
+    let labels = labels_by_name(&tree, &options);
+
+    assert_eq!(labels.len(), 9);
+    assert_eq!(labels["lone"], ClusterLabel::Noise);
+    let a = labels["a1"];
+    let b = labels["b1"];
+    assert_ne!(a, ClusterLabel::Noise);
+    assert_ne!(b, ClusterLabel::Noise);
+    assert_ne!(a, b);
+    for name in &["a2", "a3", "a4", "edge"] {
+        assert_eq!(labels[*name], a);
+    }
+    for name in &["b2", "b3"] {
+        assert_eq!(labels[*name], b);
+    }
+
+    // The same tree always gets the same labels.
+    assert_eq!(labels_by_name(&tree, &options), labels);
+}
+
+#[test]
+fn test_dbscan_geodesic_across_antimeridian() {
+    // 0.005 degrees of longitude at the equator is about 556 meters.
+    let tree = tree(&[
+        ("east", 179.998, 0.0),
+        ("west", -179.998, 0.0),
+        ("also west", -179.996, 0.0),
+        ("greenwich", 0.0, 0.0),
+    ]);
+    let options = Dbscan {
+        eps: 500.0,
+        min_points: 2,
+        distance: ClusterDistance::Geodesic,
+    };
+    let labels = labels_by_name(&tree, &options);
+
+    assert_eq!(labels["greenwich"], ClusterLabel::Noise);
+    assert_eq!(labels["east"], ClusterLabel::Cluster(0));
+    assert_eq!(labels["west"], ClusterLabel::Cluster(0));
+    assert_eq!(labels["also west"], ClusterLabel::Cluster(0));
+}
+
+#[test]
+fn test_label_clusters_writes_properties() {
+    let mut tree = tree(&[("a1", 0.0, 0.0), ("a2", 0.1, 0.0), ("lone", 5.0, 5.0)]);
+    let options = Dbscan {
+        eps: 0.5,
+        min_points: 2,
+        distance: ClusterDistance::Planar,
+    };
+    assert_eq!(label_clusters(&mut tree, &options, "cluster"), 1);
+
+    for point in tree.iter() {
+        let properties = point.properties.as_ref().unwrap();
+        let expected = if name(point) == "lone" {
+            serde_json::Value::Null
+        } else {
+            0.into()
+        };
+        assert_eq!(properties["cluster"], expected);
+    }
+}
@@ -0,0 +1,157 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+extern crate serde_json;
+
+use geojson::GeoJson;
+use geojson_rstar::{columnar::Column, ColumnValue, ColumnarTree, Feature};
+use rstar::AABB;
+use serde_json::json;
+use std::convert::TryInto;
+
+fn address(x: f64, properties: &str) -> Feature {
+    let geojson = format!(
+        r#"{{ "type": "Feature", "properties": {}, "geometry": {{ "type": "Point", "coordinates": [{}, 0.0] }} }}"#,
+        properties, x
+    );
+    match geojson.parse::<GeoJson>() {
+        Ok(GeoJson::Feature(feature)) => feature.try_into().unwrap(),
+        _ => panic!("The geojson did not parse correctly"),
+    }
+}
+
+fn addresses() -> Vec<Feature> {
+    vec![
+        address(
+            0.0,
+            r#"{ "street": "Main", "number": 10, "vacant": false }"#,
+        ),
+        address(1.0, r#"{ "street": "Main", "number": 12, "unit": "B" }"#),
+        address(
+            2.0,
+            r#"{ "street": null, "number": "12A", "vacant": true }"#,
+        ),
+        address(3.0, "null"),
+    ]
+}
+
+#[test]
+fn test_columns() {
+    let tree = ColumnarTree::bulk_load(addresses());
+    let table = tree.table();
+    assert_eq!(table.len(), 4);
+    assert_eq!(table.keys(), ["number", "street", "vacant", "unit"]);
+
+    assert_eq!(
+        table.column("street"),
+        Some(&Column::String(vec![
+            Some("Main".into()),
+            Some("Main".into()),
+            None,
+            None
+        ]))
+    );
+    assert_eq!(
+        table.column("number"),
+        Some(&Column::Mixed(vec![
+            json!(10),
+            json!(12),
+            json!("12A"),
+            json!(null)
+        ]))
+    );
+    assert_eq!(
+        table.column("vacant"),
+        Some(&Column::Bool(vec![Some(false), None, Some(true), None]))
+    );
+    assert_eq!(table.get(1, "unit"), Some(json!("B")));
+    assert_eq!(table.get(0, "unit"), None);
+    assert_eq!(table.get(2, "street"), Some(json!(null)));
+
+    let vacant: Vec<usize> = match table.column("vacant") {
+        Some(Column::Bool(values)) => values
+            .iter()
+            .enumerate()
+            .filter(|(_, vacant)| **vacant == Some(true))
+            .map(|(row, _)| row)
+            .collect(),
+        _ => panic!("vacant is a bool column"),
+    };
+    assert_eq!(vacant, vec![2]);
+}
+
+#[test]
+fn test_rows_rebuild_features() {
+    let originals: Vec<geojson::Feature> = addresses().into_iter().map(Into::into).collect();
+    let tree = ColumnarTree::bulk_load(addresses());
+
+    for row in tree.tree().iter() {
+        assert_eq!(row.feature().properties(), None);
+        let rebuilt: geojson::Feature = row.into();
+        assert_eq!(rebuilt, originals[row.row()]);
+    }
+
+    let nearest = tree
+        .tree()
+        .nearest_neighbor(&[1.2, 0.5])
+        .expect("The tree isn't empty");
+    assert_eq!(nearest.property("unit"), Some(json!("B")));
+}
+
+#[test]
+fn test_filters_scan_one_column() {
+    let tree = ColumnarTree::bulk_load(addresses());
+    let table = tree.table();
+
+    let is_main = |value: ColumnValue<'_>| value == ColumnValue::String("Main");
+    assert_eq!(table.rows_where("street", is_main), vec![0, 1]);
+    // A null is a value, but a missing key isn't.
+    assert_eq!(
+        table.rows_where("street", |value| value == ColumnValue::Null),
+        vec![2]
+    );
+    assert_eq!(table.rows_where("unit", |_| true), vec![1]);
+    assert!(table.rows_where("owner", |_| true).is_empty());
+    assert_eq!(
+        table.rows_where("number", |value| matches!(
+            value,
+            ColumnValue::Number(n) if n.as_i64() == Some(12)
+        )),
+        vec![1]
+    );
+
+    let mut filtered: Vec<usize> = tree
+        .filter("street", is_main)
+        .map(|row| row.row())
+        .collect();
+    filtered.sort_unstable();
+    assert_eq!(filtered, vec![0, 1]);
+
+    let envelope = AABB::from_corners([0.5, -1.0], [3.5, 1.0]);
+    let located: Vec<usize> = tree
+        .locate_in_envelope_intersecting_where(&envelope, "street", is_main)
+        .map(|row| row.row())
+        .collect();
+    assert_eq!(located, vec![1]);
+}
+
+#[test]
+fn test_presence_across_many_rows() {
+    let features = (0..150)
+        .map(|row| match row % 3 {
+            0 => address(row as f64, "null"),
+            1 => address(row as f64, r#"{ "flag": true }"#),
+            _ => address(row as f64, r#"{ "other": 1 }"#),
+        })
+        .collect();
+    let tree = ColumnarTree::bulk_load(features);
+    let table = tree.table();
+
+    let flagged = table.rows_where("flag", |_| true);
+    assert_eq!(flagged.len(), 50);
+    assert!(flagged.iter().all(|row| row % 3 == 1));
+    assert_eq!(table.get(149, "flag"), None);
+    assert_eq!(table.get(149, "other"), Some(json!(1)));
+    assert_eq!(table.properties(99), None);
+    assert_eq!(tree.filter("other", |_| true).count(), 50);
+}
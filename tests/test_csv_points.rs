@@ -0,0 +1,91 @@
+#![cfg(feature = "csv")]
+
+extern crate csv;
+extern crate geojson_rstar;
+extern crate rstar;
+extern crate serde_json;
+
+use geojson_rstar::{CsvError, CsvPoints, GeoJsonConversionError, PointFeature};
+use rstar::RTree;
+use serde_json::json;
+
+#[test]
+fn test_csv_points_into_tree() {
+    let csv = "name,lat,lon,fips,enrolled,open,note
+Central,44.07,-85.08,035,512,true,
+\"North, Upper\",46.38,-123.72,069,87.5,false,new
+";
+
+    let points = CsvPoints::new(csv.as_bytes(), "lon", "lat")
+        .expect("The header has both columns")
+        .collect::<Result<Vec<PointFeature>, _>>()
+        .expect("Every row is valid");
+    assert_eq!(points[1].point(), vec![-123.72, 46.38]);
+
+    let properties = points[0].properties.as_ref().unwrap();
+    assert_eq!(properties.get("lat"), None);
+    assert_eq!(properties.get("name"), Some(&json!("Central")));
+    assert_eq!(properties.get("fips"), Some(&json!("035")));
+    assert_eq!(properties.get("enrolled"), Some(&json!(512)));
+    assert_eq!(properties.get("open"), Some(&json!(true)));
+    assert_eq!(properties.get("note"), Some(&json!(null)));
+    assert_eq!(
+        points[1].properties.as_ref().unwrap().get("enrolled"),
+        Some(&json!(87.5))
+    );
+
+    let tree = RTree::bulk_load(points);
+    let nearest = tree
+        .nearest_neighbor(&[-120.0, 45.0])
+        .expect("The tree isn't empty");
+    assert_eq!(
+        nearest.properties.as_ref().unwrap().get("name"),
+        Some(&json!("North, Upper"))
+    );
+}
+
+#[test]
+fn test_csv_bad_rows_have_line_numbers() {
+    let csv = "x;y;name
+1;2;a
+one;2;b
+3;1e40;c
+4;5;d
+";
+    let reader = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .from_reader(csv.as_bytes());
+
+    let rows: Vec<Result<PointFeature<f32>, _>> = CsvPoints::from_csv_reader(reader, "x", "y")
+        .expect("The header has both columns")
+        .collect();
+
+    assert_eq!(rows.len(), 4);
+    assert!(rows[0].is_ok());
+    match &rows[1] {
+        Err(CsvError::InvalidCoordinate {
+            line,
+            column,
+            value,
+        }) => {
+            assert_eq!(*line, 3);
+            assert_eq!(column, "x");
+            assert_eq!(value, "one");
+        }
+        other => panic!("Expected an invalid coordinate, got {:?}", other),
+    }
+    assert!(matches!(
+        rows[2],
+        Err(CsvError::Conversion {
+            line: 4,
+            error: GeoJsonConversionError::NonFiniteCoordinate(None)
+        })
+    ));
+    assert!(rows[3].is_ok());
+}
+
+#[test]
+fn test_csv_missing_column() {
+    let result = CsvPoints::<f64, _>::new("x,lat\n1,2\n".as_bytes(), "lon", "lat");
+    assert!(matches!(result, Err(CsvError::MissingColumn(ref c)) if c == "lon"));
+}
@@ -0,0 +1,173 @@
+#[macro_use]
+extern crate geojson_rstar;
+extern crate geo;
+extern crate geojson;
+extern crate rstar;
+extern crate serde_json;
+
+use geo::bounding_rect::BoundingRect;
+use geojson::{feature::Id, Bbox, Geometry, PolygonType, Value};
+use geojson_rstar::{
+    conversion::create_geo_polygon,
+    generic::{check_position, create_bbox, GenericFeature},
+    GeoJsonConversionError, IndexableFeature,
+};
+use rstar::{PointDistance, RTree};
+use serde_json::{Map, Value as JsonValue};
+use std::convert::{TryFrom, TryInto};
+
+/// A parcel that only keeps its parcel number.
+#[derive(Debug, Clone, PartialEq)]
+struct Parcel {
+    bbox: [f64; 4],
+    polygon: PolygonType,
+    id: Option<Id>,
+    properties: Option<Map<String, JsonValue>>,
+}
+
+impl GenericFeature<Parcel, PolygonType> for Parcel {
+    fn take_geometry_type(
+        feature: &mut geojson::Feature,
+    ) -> Result<PolygonType, GeoJsonConversionError> {
+        match feature.geometry.take().map(|g| g.value) {
+            Some(Value::Polygon(polygon)) => Ok(polygon),
+            Some(_) => Err(GeoJsonConversionError::IncorrectGeometryValue(
+                "A parcel is a polygon".into(),
+            )),
+            None => Err(GeoJsonConversionError::MissingGeometry(feature.id.clone())),
+        }
+    }
+
+    fn check_geometry(
+        geometry: &PolygonType,
+        feature: &geojson::Feature,
+    ) -> Result<(), GeoJsonConversionError> {
+        for ring in geometry {
+            if ring.len() < 4 {
+                return Err(GeoJsonConversionError::MalformedGeometry(
+                    feature.id.clone(),
+                ));
+            }
+            for position in ring {
+                check_position::<f64>(position, feature)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compute_bbox(_: &mut geojson::Feature, geometry: &PolygonType) -> Bbox {
+        let rect = create_geo_polygon::<f64, f64>(geometry)
+            .expect("The polygon was checked")
+            .bounding_rect()
+            .expect("A checked polygon has a bounding rect");
+        vec![rect.min().x, rect.min().y, rect.max().x, rect.max().y]
+    }
+
+    fn create_self(
+        feature: geojson::Feature,
+        bbox: Bbox,
+        geometry: PolygonType,
+    ) -> Result<Parcel, GeoJsonConversionError> {
+        let properties = feature.properties.as_ref().map(|properties| {
+            properties
+                .iter()
+                .filter(|(key, _)| key.as_str() == "apn")
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()
+        });
+        Ok(Parcel {
+            bbox: create_bbox(&bbox, &feature)?,
+            polygon: geometry,
+            id: feature.id,
+            properties,
+        })
+    }
+}
+
+impl From<Parcel> for geojson::Feature {
+    fn from(parcel: Parcel) -> geojson::Feature {
+        geojson::Feature {
+            bbox: Some(parcel.bbox.to_vec()),
+            geometry: Some(Geometry::new(Value::Polygon(parcel.polygon))),
+            id: parcel.id,
+            properties: parcel.properties,
+            foreign_members: None,
+        }
+    }
+}
+
+impl IndexableFeature for Parcel {
+    fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+
+    fn properties(&self) -> Option<&Map<String, JsonValue>> {
+        self.properties.as_ref()
+    }
+
+    fn bbox(&self) -> &[f64; 4] {
+        &self.bbox
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::new(Value::Polygon(self.polygon.clone()))
+    }
+
+    fn geo_geometry(&self) -> geo::Geometry<f64> {
+        geo::Geometry::Polygon(create_geo_polygon(&self.polygon).expect("The polygon was checked"))
+    }
+
+    fn into_geojson(self) -> geojson::Feature {
+        self.into()
+    }
+}
+
+impl_rtree_feature!(Parcel, PolygonType);
+
+mod common;
+
+use common::parse_feature;
+
+fn parcel(apn: &str, x: f64) -> geojson::Feature {
+    parse_feature(&format!(
+        r#"{{ "type": "Feature", "properties": {{ "apn": "{}", "owner": "someone", "zoning": "R1" }}, "geometry": {{ "type": "Polygon", "coordinates": [[[{x}, 0.0], [{x1}, 0.0], [{x1}, 1.0], [{x}, 1.0], [{x}, 0.0]]] }} }}"#,
+        apn,
+        x = x,
+        x1 = x + 1.0
+    ))
+}
+
+#[test]
+fn test_custom_feature_in_rtree() {
+    let parcels: Vec<Parcel> = vec![parcel("001", 0.0), parcel("002", 5.0)]
+        .into_iter()
+        .map(|f| f.try_into().expect("The parcel is valid"))
+        .collect();
+    assert_eq!(parcels[1].bbox, [5.0, 0.0, 6.0, 1.0]);
+    assert_eq!(parcels[0].properties.as_ref().unwrap().len(), 1);
+
+    let tree = RTree::bulk_load(parcels);
+    let nearest = tree.nearest_neighbor(&[4.0, 0.5]).unwrap();
+    assert_eq!(nearest.properties.as_ref().unwrap()["apn"], "002");
+    assert_eq!(nearest.distance_2(&[4.0, 0.5]), 1.0);
+    assert_eq!(nearest.distance_2(&[5.5, 0.5]), 0.0);
+}
+
+#[test]
+fn test_custom_feature_validation() {
+    let unclosed = parse_feature(
+        r#"{ "type": "Feature", "properties": null, "geometry": { "type": "Polygon", "coordinates": [[[0.0, 0.0], [1.0, 0.0], [0.0, 0.0]]] } }"#,
+    );
+    match <Parcel as TryFrom<_>>::try_from(unclosed) {
+        Err(GeoJsonConversionError::MalformedGeometry(_)) => {}
+        other => panic!("Expected a malformed geometry, got {:?}", other),
+    }
+
+    let point = parse_feature(
+        r#"{ "type": "Feature", "properties": null, "geometry": { "type": "Point", "coordinates": [0.0, 0.0] } }"#,
+    );
+    match <Parcel as TryFrom<_>>::try_from(point) {
+        Err(GeoJsonConversionError::IncorrectGeometryValue(_)) => {}
+        other => panic!("Expected an incorrect geometry, got {:?}", other),
+    }
+}
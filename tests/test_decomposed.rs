@@ -0,0 +1,101 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+
+use geojson::GeoJson;
+use geojson_rstar::{DecomposedTree, Feature};
+use rstar::{RTree, AABB};
+use std::convert::TryInto;
+
+fn features() -> Vec<Feature> {
+    let geojson = r#"{
+"type": "FeatureCollection",
+"features": [
+{ "type": "Feature", "id": "islands", "properties": {}, "geometry": { "type": "MultiPolygon", "coordinates": [
+    [ [ [ 0.0, 0.0 ], [ 1.0, 0.0 ], [ 1.0, 1.0 ], [ 0.0, 1.0 ], [ 0.0, 0.0 ] ] ],
+    [ [ [ 9.0, 9.0 ], [ 10.0, 9.0 ], [ 10.0, 10.0 ], [ 9.0, 10.0 ], [ 9.0, 9.0 ] ] ] ] } },
+{ "type": "Feature", "id": "interstate", "properties": {}, "geometry": { "type": "LineString", "coordinates": [
+    [ 0.0, 20.0 ], [ 2.0, 20.0 ], [ 4.0, 20.0 ], [ 6.0, 20.0 ], [ 8.0, 20.0 ], [ 10.0, 20.0 ], [ 10.0, 30.0 ] ] } }
+]
+}"#;
+    if let Ok(GeoJson::FeatureCollection(collection)) = geojson.parse::<GeoJson>() {
+        collection
+            .features
+            .into_iter()
+            .map(|f| f.try_into())
+            .collect::<Result<Vec<Feature>, _>>()
+            .expect("The features were correctly converted")
+    } else {
+        panic!("The geojson did not parse as a FeatureCollection");
+    }
+}
+
+#[test]
+fn test_envelope_query_skips_gap_between_parts() {
+    let gap = AABB::from_corners([4.0, 4.0], [6.0, 6.0]);
+
+    let whole = RTree::bulk_load(features());
+    assert_eq!(whole.locate_in_envelope_intersecting(&gap).count(), 1);
+
+    let decomposed = DecomposedTree::bulk_load(features(), 2);
+    assert_eq!(decomposed.locate_in_envelope_intersecting(&gap).count(), 0);
+
+    let both_islands = AABB::from_corners([0.5, 0.5], [9.5, 9.5]);
+    let found: Vec<_> = decomposed
+        .locate_in_envelope_intersecting(&both_islands)
+        .collect();
+    assert_eq!(found.len(), 1, "The parent is returned once");
+    assert!(matches!(found[0], Feature::MultiPolygon(_)));
+}
+
+#[test]
+fn test_nearest_neighbor_iter_returns_parents_once() {
+    let decomposed = DecomposedTree::bulk_load(features(), 2);
+    assert_eq!(decomposed.tree().size(), 2 + 3);
+
+    let nearest: Vec<_> = decomposed.nearest_neighbor_iter(&[5.0, 19.0]).collect();
+    assert_eq!(nearest.len(), 2);
+    assert!(matches!(nearest[0], Feature::LineString(_)));
+    assert!(matches!(nearest[1], Feature::MultiPolygon(_)));
+
+    assert!(matches!(
+        decomposed.nearest_neighbor(&[0.5, 0.5]),
+        Some(Feature::MultiPolygon(_))
+    ));
+}
+
+#[test]
+fn test_parts_of_collections() {
+    let geojson = r#"{
+"type": "FeatureCollection",
+"features": [
+{ "type": "Feature", "id": "campus", "properties": {}, "geometry": { "type": "GeometryCollection", "geometries": [
+    { "type": "Point", "coordinates": [ 0.0, 0.0 ] },
+    { "type": "Polygon", "coordinates": [ [ [ 10.0, 10.0 ], [ 12.0, 10.0 ], [ 12.0, 12.0 ], [ 10.0, 10.0 ] ] ] } ] } }
+]
+}"#;
+    let features = match geojson.parse::<GeoJson>() {
+        Ok(GeoJson::FeatureCollection(collection)) => collection
+            .features
+            .into_iter()
+            .map(|f| f.try_into())
+            .collect::<Result<Vec<Feature>, _>>()
+            .expect("The features were correctly converted"),
+        _ => panic!("The geojson did not parse as a FeatureCollection"),
+    };
+
+    let decomposed = DecomposedTree::bulk_load(features, 2);
+    assert_eq!(decomposed.tree().size(), 2);
+    assert_eq!(decomposed.nearest_neighbor_iter(&[0.5, 0.5]).count(), 1);
+
+    let gap = AABB::from_corners([4.0, 4.0], [6.0, 6.0]);
+    assert_eq!(decomposed.locate_in_envelope_intersecting(&gap).count(), 0);
+    assert_eq!(decomposed.locate_all_at_point(&[11.5, 11.0]).count(), 1);
+
+    let distances: Vec<f64> = decomposed
+        .tree()
+        .nearest_neighbor_iter_with_distance_2(&[12.0, 9.0])
+        .map(|(_, distance)| distance)
+        .collect();
+    assert_eq!(distances, vec![1.0, 225.0]);
+}
@@ -0,0 +1,222 @@
+#![cfg(feature = "arrow")]
+
+extern crate arrow_array;
+extern crate arrow_schema;
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+extern crate serde_json;
+
+use arrow_array::{
+    builder::{FixedSizeListBuilder, Float64Builder, ListBuilder},
+    Array, ArrayRef, BinaryArray, Float64Array, Int32Array, RecordBatch, StringArray, StructArray,
+};
+use arrow_schema::{DataType, Field, Schema};
+use geojson_rstar::{
+    geoarrow::{
+        bulk_load, read_ipc_file, read_record_batch, to_record_batch, write_ipc_file,
+        EXTENSION_NAME_KEY,
+    },
+    Feature, GeoArrowError, GeometryKind,
+};
+use serde_json::json;
+use std::{collections::HashMap, convert::TryInto, sync::Arc};
+
+fn geometry_field(name: &str, extension: &str, column: &ArrayRef) -> Field {
+    let metadata: HashMap<String, String> = vec![(EXTENSION_NAME_KEY.into(), extension.into())]
+        .into_iter()
+        .collect();
+    Field::new(name, column.data_type().clone(), true).with_metadata(metadata)
+}
+
+/// Two squares as a native `geoarrow.polygon` column, with interleaved
+/// coordinates.
+fn parcels() -> RecordBatch {
+    let mut builder = ListBuilder::new(ListBuilder::new(FixedSizeListBuilder::new(
+        Float64Builder::new(),
+        2,
+    )));
+    for offset in &[0.0, 10.0] {
+        let rings = builder.values();
+        let ring = rings.values();
+        for (x, y) in &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)] {
+            ring.values().append_value(x + offset);
+            ring.values().append_value(*y);
+            ring.append(true);
+        }
+        rings.append(true);
+        builder.append(true);
+    }
+    let geometry: ArrayRef = Arc::new(builder.finish());
+    let apn: ArrayRef = Arc::new(StringArray::from(vec!["001", "002"]));
+    let value: ArrayRef = Arc::new(Float64Array::from(vec![Some(250_000.0), None]));
+    let schema = Schema::new(vec![
+        Field::new("apn", DataType::Utf8, false),
+        geometry_field("geom", "geoarrow.polygon", &geometry),
+        Field::new("value", DataType::Float64, true),
+    ]);
+    RecordBatch::try_new(Arc::new(schema), vec![apn, geometry, value]).unwrap()
+}
+
+mod common;
+
+use common::parse_feature;
+
+#[test]
+fn test_read_native_batches() {
+    let features: Vec<Feature> = read_record_batch(&parcels(), "geom").unwrap();
+    assert_eq!(features.len(), 2);
+    assert_eq!(
+        features[1],
+        parse_feature(
+            r#"{ "type": "Feature", "properties": { "apn": "002", "value": null }, "geometry": { "type": "Polygon", "coordinates": [[[10.0, 0.0], [11.0, 0.0], [11.0, 1.0], [10.0, 1.0], [10.0, 0.0]]] } }"#
+        )
+        .try_into()
+        .unwrap()
+    );
+
+    let tree = bulk_load::<f64>(&[parcels()], "geom").unwrap();
+    let found: Vec<&Feature> = tree.locate_all_at_point(&[10.5, 0.5]).collect();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].properties().unwrap()["apn"], json!("002"));
+
+    // Separated coordinates, with y first, in a column with a null point.
+    let x: ArrayRef = Arc::new(Float64Array::from(vec![1.0, 0.0]));
+    let y: ArrayRef = Arc::new(Float64Array::from(vec![2.0, 0.0]));
+    let fields = vec![
+        Arc::new(Field::new("y", DataType::Float64, false)),
+        Arc::new(Field::new("x", DataType::Float64, false)),
+    ];
+    let points: ArrayRef = Arc::new(StructArray::new(
+        fields.into(),
+        vec![y, x],
+        Some(vec![true, false].into()),
+    ));
+    let batch = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![geometry_field(
+            "point",
+            "geoarrow.point",
+            &points,
+        )])),
+        vec![points.clone()],
+    )
+    .unwrap();
+    match read_record_batch::<f64>(&batch.slice(0, 1), "point") {
+        Ok(features) => match &features[0] {
+            Feature::Point(point) => assert_eq!(point.point(), vec![1.0, 2.0]),
+            _ => panic!("The feature is a point"),
+        },
+        Err(error) => panic!("The point didn't convert: {:?}", error),
+    }
+    assert!(matches!(
+        read_record_batch::<f64>(&batch, "point"),
+        Err(GeoArrowError::Conversion { row: 1, .. })
+    ));
+}
+
+#[test]
+fn test_read_errors() {
+    assert!(matches!(
+        read_record_batch::<f64>(&parcels(), "geometry"),
+        Err(GeoArrowError::MissingColumn(_))
+    ));
+    assert!(matches!(
+        read_record_batch::<f64>(&parcels(), "value"),
+        Err(GeoArrowError::UnsupportedGeometry(_))
+    ));
+
+    // Separated coordinates have to be named x and y.
+    let fields = vec![
+        Arc::new(Field::new("lon", DataType::Float64, false)),
+        Arc::new(Field::new("lat", DataType::Float64, false)),
+    ];
+    let values: Vec<ArrayRef> = vec![
+        Arc::new(Float64Array::from(vec![1.0])),
+        Arc::new(Float64Array::from(vec![2.0])),
+    ];
+    let points: ArrayRef = Arc::new(StructArray::new(fields.into(), values, None));
+    let batch = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![geometry_field(
+            "point",
+            "geoarrow.point",
+            &points,
+        )])),
+        vec![points],
+    )
+    .unwrap();
+    assert!(matches!(
+        read_record_batch::<f64>(&batch, "point"),
+        Err(GeoArrowError::UnsupportedGeometry(_))
+    ));
+
+    let wkb: ArrayRef = Arc::new(BinaryArray::from_vec(vec![&[1, 2, 3]]));
+    let counts: ArrayRef = Arc::new(Int32Array::from(vec![4]));
+    let batch = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![
+            Field::new("wkb", DataType::Binary, false),
+            Field::new("count", DataType::Int32, false),
+        ])),
+        vec![wkb, counts],
+    )
+    .unwrap();
+    assert!(matches!(
+        read_record_batch::<f64>(&batch, "wkb"),
+        Err(GeoArrowError::Conversion { row: 0, .. })
+    ));
+}
+
+#[test]
+fn test_write_and_read_back() {
+    let features: Vec<Feature> = vec![
+        parse_feature(
+            r#"{ "type": "Feature", "properties": { "name": "well", "depth": 12, "active": true, "tags": ["a"], "serial": 18446744073709551615, "offset": -1 }, "geometry": { "type": "Point", "coordinates": [1.0, 2.0] } }"#,
+        )
+        .try_into()
+        .unwrap(),
+        parse_feature(
+            r#"{ "type": "Feature", "properties": { "name": "pipe", "depth": 2.5, "active": null, "tags": "b", "serial": 7, "offset": 18446744073709551615 }, "geometry": { "type": "LineString", "coordinates": [[0.0, 0.0], [3.0, 4.0]] } }"#,
+        )
+        .try_into()
+        .unwrap(),
+    ];
+
+    let batch = to_record_batch(&features).unwrap();
+    let types: Vec<(&str, &DataType)> = batch
+        .schema_ref()
+        .fields()
+        .iter()
+        .map(|field| (field.name().as_str(), field.data_type()))
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            ("geometry", &DataType::Binary),
+            ("active", &DataType::Boolean),
+            ("depth", &DataType::Float64),
+            ("name", &DataType::Utf8),
+            ("offset", &DataType::Utf8),
+            ("serial", &DataType::UInt64),
+            ("tags", &DataType::Utf8),
+        ]
+    );
+
+    let read: Vec<Feature> = read_record_batch(&batch, "geometry").unwrap();
+    assert_eq!(read[0].properties().unwrap()["depth"], json!(12.0));
+    assert_eq!(read[0].properties().unwrap()["tags"], json!(r#"["a"]"#));
+    assert_eq!(read[1].properties().unwrap()["active"], json!(null));
+    assert_eq!(read[1].properties().unwrap()["tags"], json!(r#""b""#));
+    // Integers above i64::MAX aren't read back as floats.
+    assert_eq!(read[0].properties().unwrap()["serial"], json!(u64::MAX));
+    assert_eq!(
+        read[1].properties().unwrap()["offset"],
+        json!("18446744073709551615")
+    );
+
+    let path =
+        std::env::temp_dir().join(format!("geojson-rstar-test-{}.arrow", std::process::id()));
+    write_ipc_file(&path, &read).unwrap();
+    let from_file: Vec<Feature> = read_ipc_file(&path, "geometry").unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(from_file, read);
+    assert_eq!(from_file[1].kind(), GeometryKind::LineString);
+}
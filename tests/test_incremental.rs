@@ -0,0 +1,132 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+
+use geojson::{feature::Id, GeoJson};
+use geojson_rstar::{ChangeEvent, Feature, FeatureKey, IncrementalTree};
+use rstar::AABB;
+use std::{
+    convert::TryInto,
+    sync::{Arc, Mutex},
+};
+
+fn point(id: &str, x: f64, y: f64) -> Feature {
+    let geojson = format!(
+        r#"{{ "type": "Feature", "id": "{}", "properties": {{}}, "geometry": {{ "type": "Point", "coordinates": [{:?}, {:?}] }} }}"#,
+        id, x, y
+    );
+    match geojson.parse::<GeoJson>() {
+        Ok(GeoJson::Feature(feature)) => feature.try_into().unwrap(),
+        _ => panic!("The geojson did not parse correctly"),
+    }
+}
+
+fn at(x: f64, y: f64) -> AABB<[f64; 2]> {
+    AABB::from_point([x, y])
+}
+
+fn recorded(tree: &mut IncrementalTree) -> Arc<Mutex<Vec<ChangeEvent>>> {
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = Arc::clone(&events);
+    tree.subscribe(move |event| sink.lock().unwrap().push(event.clone()));
+    events
+}
+
+#[test]
+fn test_insert_update_remove() {
+    let mut tree = IncrementalTree::bulk_load(vec![point("a", 0.0, 0.0), point("b", 5.0, 5.0)]);
+    let events = recorded(&mut tree);
+    let a = tree.key_of(&Id::String("a".into())).unwrap();
+
+    let c = tree.insert(point("c", 9.0, 9.0));
+    assert_eq!(tree.len(), 3);
+    assert_eq!(tree.tree().nearest_neighbor(&[8.0, 8.0]).unwrap().key(), c);
+
+    let old = tree.update(a, point("a", 1.0, 1.0)).unwrap();
+    assert_eq!(old, point("a", 0.0, 0.0));
+    assert_eq!(tree.tree().locate_all_at_point(&[0.0, 0.0]).count(), 0);
+    assert_eq!(tree.tree().locate_at_point(&[1.0, 1.0]).unwrap().key(), a);
+
+    assert_eq!(tree.remove(c), Some(point("c", 9.0, 9.0)));
+    assert_eq!(tree.remove(c), None);
+    assert!(tree.get(c).is_none());
+    assert_eq!(tree.tree().size(), 2);
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            ChangeEvent::Added {
+                key: c,
+                envelope: at(9.0, 9.0)
+            },
+            ChangeEvent::Modified {
+                key: a,
+                old_envelope: at(0.0, 0.0),
+                new_envelope: at(1.0, 1.0)
+            },
+            ChangeEvent::Removed {
+                key: c,
+                envelope: at(9.0, 9.0)
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_ids_stay_unique() {
+    let mut tree = IncrementalTree::new();
+    let events = recorded(&mut tree);
+    let a = tree.insert(point("a", 0.0, 0.0));
+    let b = tree.insert(point("b", 2.0, 2.0));
+
+    // Inserting a known id replaces the feature.
+    assert_eq!(tree.insert(point("a", 3.0, 3.0)), a);
+    assert_eq!(tree.len(), 2);
+
+    // Giving b the id of a removes a.
+    tree.update(b, point("a", 4.0, 4.0));
+    assert_eq!(tree.len(), 1);
+    assert_eq!(tree.key_of(&Id::String("a".into())), Some(b));
+    assert!(tree.get(a).is_none());
+    assert_eq!(tree.tree().size(), 1);
+
+    let changes: Vec<(FeatureKey, Vec<AABB<[f64; 2]>>)> = events
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|event| (event.key(), event.envelopes().collect()))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (a, vec![at(0.0, 0.0)]),
+            (b, vec![at(2.0, 2.0)]),
+            (a, vec![at(0.0, 0.0), at(3.0, 3.0)]),
+            (a, vec![at(3.0, 3.0)]),
+            (b, vec![at(2.0, 2.0), at(4.0, 4.0)]),
+        ]
+    );
+    assert!(tree.update(a, point("z", 0.0, 0.0)).is_none());
+}
+
+#[test]
+fn test_rebalance_and_unsubscribe() {
+    let mut tree = IncrementalTree::new().rebalance_every(10);
+    let events = recorded(&mut tree);
+    let keys: Vec<FeatureKey> = (0..100)
+        .map(|i| tree.insert(point(&i.to_string(), f64::from(i), 0.0)))
+        .collect();
+    assert_eq!(tree.tree().size(), 100);
+    for (i, key) in keys.iter().enumerate() {
+        let found = tree.tree().locate_at_point(&[i as f64, 0.0]).unwrap();
+        assert_eq!(found.key(), *key);
+        assert_eq!(tree.get(*key), Some(found.feature()));
+    }
+    assert_eq!(events.lock().unwrap().len(), 100);
+
+    let subscription = tree.subscribe(|_| {});
+    assert!(tree.unsubscribe(subscription));
+    assert!(!tree.unsubscribe(subscription));
+    tree.remove(keys[0]);
+    assert_eq!(events.lock().unwrap().len(), 101);
+}
@@ -0,0 +1,126 @@
+extern crate geo;
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+
+use geojson::{feature::Id, GeoJson};
+use geojson_rstar::{query::nearest_k, Feature, IndexableFeature, PointFeature};
+use rstar::{RTree, RTreeObject, AABB};
+use std::convert::{TryFrom, TryInto};
+
+fn id(number: u64) -> Id {
+    Id::Number(number.into())
+}
+
+fn parse_features(geojson: &str) -> Vec<geojson::Feature> {
+    match geojson.parse::<GeoJson>() {
+        Ok(GeoJson::FeatureCollection(collection)) => collection.features,
+        _ => panic!("The geojson did not parse correctly"),
+    }
+}
+
+const COLLECTION: &str = r#"{
+"type": "FeatureCollection",
+"features": [
+{ "type": "Feature", "id": 1, "properties": { "name": "stop" }, "geometry": { "type": "Point", "coordinates": [1.0, 2.0] } },
+{ "type": "Feature", "id": 2, "properties": { "name": "depot" }, "geometry": { "type": "GeometryCollection", "geometries": [
+    { "type": "Point", "coordinates": [5.0, 5.0] },
+    { "type": "LineString", "coordinates": [[4.0, 4.0], [8.0, 6.0]] }
+] } }
+]
+}"#;
+
+/// Generic code that only knows it has some kind of feature.
+fn ids_in<F>(tree: &RTree<F>, envelope: &AABB<[f64; 2]>) -> Vec<Id>
+where
+    F: IndexableFeature,
+{
+    let mut ids: Vec<_> = tree
+        .locate_in_envelope_intersecting(envelope)
+        .filter_map(|f| f.id().cloned())
+        .collect();
+    ids.sort_by_key(|id| format!("{:?}", id));
+    ids
+}
+
+#[test]
+fn test_generic_over_feature_types() {
+    let features = parse_features(COLLECTION);
+    let points: RTree<PointFeature> =
+        RTree::bulk_load(vec![features[0].clone().try_into().unwrap()]);
+    let all: RTree<Feature> = RTree::bulk_load(
+        features
+            .iter()
+            .map(|f| f.clone().try_into().unwrap())
+            .collect(),
+    );
+
+    let everywhere = AABB::from_corners([0.0, 0.0], [10.0, 10.0]);
+    assert_eq!(ids_in(&points, &everywhere), vec![id(1)]);
+    assert_eq!(ids_in(&all, &everywhere), vec![id(1), id(2)]);
+    // The geometry collection is found by its bbox.
+    assert_eq!(
+        ids_in(&all, &AABB::from_corners([7.0, 5.5], [9.0, 9.0])),
+        vec![id(2)]
+    );
+}
+
+#[test]
+fn test_point_queries_on_geometry_collection() {
+    let tree: RTree<Feature> = RTree::bulk_load(
+        parse_features(COLLECTION)
+            .into_iter()
+            .map(|f| f.try_into().unwrap())
+            .collect(),
+    );
+
+    let nearest = nearest_k(&tree, &[8.0, 7.0], 2, None);
+    assert_eq!(nearest[0].0.id(), Some(&id(2)));
+    assert_eq!(nearest[0].1, 1.0);
+    assert_eq!(
+        tree.nearest_neighbor(&[8.0, 7.0]).and_then(|f| f.id()),
+        Some(&id(2))
+    );
+
+    let at_point: Vec<_> = tree
+        .locate_all_at_point(&[6.0, 5.0])
+        .filter_map(|f| f.id())
+        .collect();
+    assert_eq!(at_point, vec![&id(2)]);
+    assert_eq!(tree.locate_all_at_point(&[7.0, 4.0]).count(), 0);
+}
+
+#[test]
+fn test_indexable_accessors() {
+    let features = parse_features(COLLECTION);
+    for geojson_feature in features {
+        let feature = Feature::<f64>::try_from(geojson_feature.clone()).unwrap();
+        assert_eq!(IndexableFeature::id(&feature), geojson_feature.id.as_ref());
+        assert_eq!(
+            IndexableFeature::properties(&feature),
+            geojson_feature.properties.as_ref()
+        );
+        assert_eq!(
+            feature.geometry(),
+            geojson_feature.geometry.clone().unwrap()
+        );
+        let bbox = *feature.bbox();
+        assert_eq!(
+            feature.envelope(),
+            AABB::from_corners([bbox[0], bbox[1]], [bbox[2], bbox[3]])
+        );
+
+        match (
+            &geojson_feature.geometry.as_ref().unwrap().value,
+            feature.geo_geometry(),
+        ) {
+            (geojson::Value::Point(_), geo::Geometry::Point(_))
+            | (geojson::Value::GeometryCollection(_), geo::Geometry::GeometryCollection(_)) => {}
+            _ => panic!("The geo geometry is of the same kind"),
+        }
+
+        let exported = feature.into_geojson();
+        assert_eq!(exported.id, geojson_feature.id);
+        assert_eq!(exported.geometry, geojson_feature.geometry);
+    }
+}
@@ -0,0 +1,108 @@
+extern crate geo;
+extern crate geojson;
+extern crate geojson_rstar;
+
+use geo::{contains::Contains, Point};
+use geojson_rstar::{
+    Feature, GeoJsonConversionError, GeometryCollectionFeature, LineStringFeature, Measure,
+    PolygonFeature,
+};
+use std::convert::TryInto;
+
+mod common;
+
+use common::geometry_feature;
+
+#[test]
+fn test_polygon_measures() {
+    let holed: PolygonFeature = geometry_feature(
+        r#"{ "type": "Polygon", "coordinates": [[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0], [0.0, 0.0]], [[4.0, 4.0], [4.0, 6.0], [6.0, 6.0], [6.0, 4.0], [4.0, 4.0]]] }"#,
+    )
+    .try_into()
+    .unwrap();
+    assert_eq!(holed.area(), 96.0);
+    assert_eq!(holed.length(), 0.0);
+    assert_eq!(holed.centroid(), Some(Point::new(5.0, 5.0)));
+    assert_eq!(holed.convex_hull().unwrap().exterior().0.len(), 5);
+
+    // The centroid of a U falls in its gap.
+    let u: PolygonFeature = geometry_feature(
+        r#"{ "type": "Polygon", "coordinates": [[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [8.0, 10.0], [8.0, 2.0], [2.0, 2.0], [2.0, 10.0], [0.0, 10.0], [0.0, 0.0]]] }"#,
+    )
+    .try_into()
+    .unwrap();
+    let polygon = u.geo_polygon();
+    assert!(!polygon.contains(&u.centroid().unwrap()));
+    assert!(polygon.contains(&u.representative_point().unwrap()));
+    assert_eq!(u.convex_hull().unwrap().exterior().0.len(), 5);
+}
+
+#[test]
+fn test_line_measures() {
+    let line: LineStringFeature = geometry_feature(
+        r#"{ "type": "LineString", "coordinates": [[0.0, 0.0], [3.0, 0.0], [3.0, 4.0]] }"#,
+    )
+    .try_into()
+    .unwrap();
+    assert_eq!(line.length(), 7.0);
+    assert_eq!(line.area(), 0.0);
+    assert_eq!(line.representative_point(), Some(Point::new(3.0, 0.5)));
+
+    let feature: Feature = geometry_feature(
+        r#"{ "type": "LineString", "coordinates": [[0.0, 0.0], [3.0, 0.0], [3.0, 4.0]] }"#,
+    )
+    .try_into()
+    .unwrap();
+    assert_eq!(feature.length(), line.length());
+    assert_eq!(feature.centroid(), line.centroid());
+}
+
+#[test]
+fn test_geometry_collection_measures() {
+    let collection: GeometryCollectionFeature = geometry_feature(
+        r#"{ "type": "GeometryCollection", "geometries": [
+            { "type": "Polygon", "coordinates": [[[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0], [0.0, 0.0]]] },
+            { "type": "Polygon", "coordinates": [[[4.0, 0.0], [6.0, 0.0], [6.0, 2.0], [4.0, 2.0], [4.0, 0.0]]] },
+            { "type": "LineString", "coordinates": [[0.0, 5.0], [10.0, 5.0]] },
+            { "type": "Point", "coordinates": [100.0, 100.0] }
+        ] }"#,
+    )
+    .try_into()
+    .unwrap();
+    assert_eq!(collection.area(), 8.0);
+    assert_eq!(collection.length(), 10.0);
+    // Only the polygons count towards the centroid.
+    assert_eq!(collection.centroid(), Some(Point::new(3.0, 1.0)));
+    assert_eq!(
+        collection.representative_point(),
+        Some(Point::new(1.0, 1.0))
+    );
+    let hull = collection.convex_hull().unwrap();
+    assert!(hull.contains(&Point::new(50.0, 50.0)));
+
+    // Empty collections are rejected, even with a bbox, so there is always
+    // something to measure.
+    let mut empty = geometry_feature(r#"{ "type": "GeometryCollection", "geometries": [] }"#);
+    empty.bbox = Some(vec![0.0, 0.0, 0.0, 0.0]);
+    assert!(matches!(
+        TryInto::<GeometryCollectionFeature>::try_into(empty),
+        Err(GeoJsonConversionError::MalformedGeometry(None))
+    ));
+}
+
+#[test]
+fn test_geodesic_measures() {
+    let line: LineStringFeature =
+        geometry_feature(r#"{ "type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 0.0]] }"#)
+            .try_into()
+            .unwrap();
+    assert!((line.geodesic_length() - 111_195.0).abs() < 1.0);
+
+    let square: PolygonFeature = geometry_feature(
+        r#"{ "type": "Polygon", "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]]] }"#,
+    )
+    .try_into()
+    .unwrap();
+    // A one degree square at the equator is about 12,364 square kilometres.
+    assert!((square.geodesic_area() / 1.2364e10 - 1.0).abs() < 0.01);
+}
@@ -0,0 +1,73 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+
+use geojson_rstar::MultiPolygonFeature;
+use rstar::{PointDistance, RTree};
+use std::convert::TryInto;
+
+#[test]
+fn test_multipolygon_round_trip() {
+    let polygons = vec![
+        vec![
+            vec![
+                vec![0.0, 0.0],
+                vec![4.0, 0.0],
+                vec![4.0, 4.0],
+                vec![0.0, 0.0],
+            ],
+            vec![
+                vec![1.0, 0.5],
+                vec![2.0, 0.5],
+                vec![2.0, 1.5],
+                vec![1.0, 0.5],
+            ],
+        ],
+        vec![vec![
+            vec![10.0, 10.0],
+            vec![12.0, 10.0],
+            vec![12.0, 12.0],
+            vec![10.0, 12.0],
+            vec![10.0, 10.0],
+        ]],
+    ];
+    let feature = geojson::Feature {
+        bbox: None,
+        geometry: Some(geojson::Geometry::new(geojson::Value::MultiPolygon(
+            polygons.clone(),
+        ))),
+        id: None,
+        properties: None,
+        foreign_members: None,
+    };
+
+    let multipolygon_feature: MultiPolygonFeature = feature
+        .try_into()
+        .expect("The multipolygon can be converted");
+    assert_eq!(multipolygon_feature.polygons(), polygons);
+
+    let geo_polygons = multipolygon_feature.geo_polygons();
+    assert_eq!(geo_polygons.0.len(), 2);
+    assert_eq!(geo_polygons.0[0].interiors().len(), 1);
+    assert_eq!(geo_polygons.0[1].interiors().len(), 0);
+
+    assert_eq!(multipolygon_feature.len(), 2);
+    assert_eq!(multipolygon_feature.rings(0).count(), 2);
+    assert_eq!(
+        multipolygon_feature.rings(1).next().unwrap()[2],
+        [12.0, 12.0]
+    );
+    assert_eq!(multipolygon_feature.distance_2(&[3.0, 1.0]), 0.0);
+    assert!((multipolygon_feature.distance_2(&[1.8, 0.8]) - 0.04).abs() < 1e-9);
+    assert_eq!(multipolygon_feature.distance_2(&[13.0, 11.0]), 1.0);
+    assert_eq!(multipolygon_feature.distance_2(&[4.0, 7.0]), 9.0);
+
+    let tree = RTree::bulk_load(vec![multipolygon_feature.clone()]);
+    assert!(tree.locate_at_point(&[11.0, 11.0]).is_some());
+
+    let feature: geojson::Feature = multipolygon_feature.into();
+    assert_eq!(
+        feature.geometry.map(|g| g.value),
+        Some(geojson::Value::MultiPolygon(polygons))
+    );
+}
@@ -4,7 +4,7 @@ extern crate rstar;
 extern crate serde_json;
 
 use geojson::GeoJson;
-use geojson_rstar::PointFeature;
+use geojson_rstar::{GeoJsonConversionError, PointFeature};
 use rstar::RTree;
 use std::convert::TryInto;
 
@@ -74,3 +74,88 @@ fn test_nearest_neighbor() {
         panic!("The geojson did not parse as a FeatureCollection correctly");
     }
 }
+
+#[test]
+fn test_try_into_point_feature_non_finite() {
+    let feature = geojson::Feature {
+        bbox: None,
+        geometry: Some(geojson::Geometry::new(geojson::Value::Point(vec![
+            f64::NAN,
+            45.87291786000003,
+        ]))),
+        id: Some(geojson::feature::Id::String("nan".into())),
+        properties: None,
+        foreign_members: None,
+    };
+
+    let point_feature: Result<PointFeature, _> = feature.try_into();
+    match point_feature {
+        Err(GeoJsonConversionError::NonFiniteCoordinate(Some(id))) => {
+            assert_eq!(id, geojson::feature::Id::String("nan".into()))
+        }
+        other => panic!("Expected a NonFiniteCoordinate error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_nearest_neighbor_f32() {
+    let points_geojson = r#"{
+"type": "FeatureCollection",
+"features": [
+{ "type": "Feature", "properties": { "NAME": "FERGUS FALLS" }, "geometry": { "type": "Point", "coordinates": [ -96.096365834999972, 46.287388581000073 ] } },
+{ "type": "Feature", "properties": { "NAME": "GRANITE FALLS" }, "geometry": { "type": "Point", "coordinates": [ -95.561709067999971, 44.807550251000066 ] } }
+]
+}"#;
+
+    if let Ok(GeoJson::FeatureCollection(feature_collection)) = points_geojson.parse::<GeoJson>() {
+        let feature_points = feature_collection
+            .features
+            .into_iter()
+            .map(|f| f.try_into())
+            .collect::<Result<Vec<PointFeature<f32>>, _>>()
+            .expect("The features were correctly converted as f32 PointFeatures");
+        let r_tree = RTree::bulk_load(feature_points);
+        let nearest = r_tree
+            .nearest_neighbor(&[-95.6f32, 44.9])
+            .expect("There is a nearest point in the RTree");
+
+        assert_eq!(
+            nearest.properties.as_ref().unwrap().get("NAME"),
+            Some(&serde_json::Value::String("GRANITE FALLS".to_string()))
+        );
+
+        let expected = nearest.point();
+        let feature: geojson::Feature = nearest.clone().into();
+        assert_eq!(
+            feature.geometry.map(|g| g.value),
+            Some(geojson::Value::Point(expected))
+        );
+    } else {
+        panic!("The geojson did not parse as a FeatureCollection correctly");
+    }
+}
+
+#[test]
+fn test_try_into_point_feature_f32_overflow() {
+    let feature = geojson::Feature {
+        bbox: None,
+        geometry: Some(geojson::Geometry::new(geojson::Value::Point(vec![
+            1e300, 45.0,
+        ]))),
+        id: None,
+        properties: None,
+        foreign_members: None,
+    };
+
+    let point_feature: Result<PointFeature<f32>, _> = feature.clone().try_into();
+    assert!(
+        matches!(
+            point_feature,
+            Err(GeoJsonConversionError::NonFiniteCoordinate(None))
+        ),
+        "A coordinate that overflows f32 is rejected"
+    );
+
+    let point_feature: Result<PointFeature, _> = feature.try_into();
+    assert!(point_feature.is_ok(), "The same coordinate fits in f64");
+}
@@ -1,11 +1,13 @@
+extern crate geo;
 extern crate geojson;
 extern crate geojson_rstar;
 extern crate rstar;
 extern crate serde_json;
 
+use geo::euclidean_distance::EuclideanDistance;
 use geojson::GeoJson;
-use geojson_rstar::PolygonFeature;
-use rstar::RTree;
+use geojson_rstar::{GeoJsonConversionError, PolygonFeature};
+use rstar::{PointDistance, RTree};
 use std::convert::TryInto;
 
 #[test]
@@ -60,3 +62,117 @@ fn test_nearest_neighbor() {
         panic!("The geojson did not parse correctly");
     }
 }
+
+#[test]
+fn test_try_from_polygon_infinite_coordinate() {
+    let feature = geojson::Feature {
+        bbox: None,
+        geometry: Some(geojson::Geometry::new(geojson::Value::Polygon(vec![vec![
+            vec![-85.087602, 44.07366],
+            vec![-84.608104, f64::INFINITY],
+            vec![-84.606037, 43.815365],
+            vec![-85.087602, 44.07366],
+        ]]))),
+        id: None,
+        properties: None,
+        foreign_members: None,
+    };
+
+    let polygon_feature: Result<PolygonFeature, _> = feature.try_into();
+    assert!(
+        matches!(
+            polygon_feature,
+            Err(GeoJsonConversionError::NonFiniteCoordinate(None))
+        ),
+        "A polygon with an infinite coordinate is rejected"
+    );
+}
+
+#[test]
+fn test_polygon_with_hole_round_trip() {
+    let rings = vec![
+        vec![
+            vec![0.0, 0.0],
+            vec![10.0, 0.0],
+            vec![10.0, 10.0],
+            vec![0.0, 10.0],
+            vec![0.0, 0.0],
+        ],
+        vec![
+            vec![4.0, 4.0],
+            vec![6.0, 4.0],
+            vec![6.0, 6.0],
+            vec![4.0, 4.0],
+        ],
+    ];
+    let feature = geojson::Feature {
+        bbox: None,
+        geometry: Some(geojson::Geometry::new(geojson::Value::Polygon(
+            rings.clone(),
+        ))),
+        id: None,
+        properties: None,
+        foreign_members: None,
+    };
+
+    let polygon_feature: PolygonFeature = feature
+        .try_into()
+        .expect("The polygon with a hole can be converted");
+    assert_eq!(polygon_feature.polygon(), rings);
+    assert_eq!(polygon_feature.geo_polygon().interiors().len(), 1);
+
+    let feature: geojson::Feature = polygon_feature.into();
+    assert_eq!(
+        feature.geometry.map(|g| g.value),
+        Some(geojson::Value::Polygon(rings))
+    );
+    assert_eq!(feature.bbox, Some(vec![0.0, 0.0, 10.0, 10.0]));
+}
+
+#[test]
+fn test_polygon_distance_from_rings() {
+    let feature = geojson::Feature {
+        bbox: None,
+        geometry: Some(geojson::Geometry::new(geojson::Value::Polygon(vec![
+            vec![
+                vec![0.0, 0.0],
+                vec![10.0, 0.0],
+                vec![10.0, 10.0],
+                vec![5.0, 4.0],
+                vec![0.0, 10.0],
+                vec![0.0, 0.0],
+            ],
+            vec![
+                vec![2.0, 1.0],
+                vec![8.0, 1.0],
+                vec![8.0, 3.0],
+                vec![2.0, 3.0],
+                vec![2.0, 1.0],
+            ],
+        ]))),
+        id: None,
+        properties: None,
+        foreign_members: None,
+    };
+    let polygon_feature: PolygonFeature = feature.try_into().unwrap();
+    assert_eq!(polygon_feature.rings().count(), 2);
+    assert_eq!(polygon_feature.rings().next().unwrap()[3], [5.0, 4.0]);
+
+    let geo_polygon = polygon_feature.geo_polygon();
+    // Inside, in the hole, in the notch, on an edge and outside.
+    for point in &[
+        [1.0, 5.0],
+        [5.0, 2.0],
+        [5.0, 8.0],
+        [10.0, 5.0],
+        [-3.0, -4.0],
+        [12.0, 12.0],
+    ] {
+        let expected = geo_polygon
+            .euclidean_distance(&geo::Point::new(point[0], point[1]))
+            .powi(2);
+        assert!((polygon_feature.distance_2(point) - expected).abs() < 1e-9);
+    }
+    assert_eq!(polygon_feature.distance_2(&[1.0, 5.0]), 0.0);
+    assert_eq!(polygon_feature.distance_2(&[5.0, 2.0]), 1.0);
+}
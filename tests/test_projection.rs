@@ -0,0 +1,61 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+
+use geojson::GeoJson;
+use geojson_rstar::{Feature, PolygonFeature, ProjectFeature, Projection, PropertyFilter};
+use rstar::RTree;
+
+fn county() -> geojson::Feature {
+    let geojson = r#"{ "type": "Feature", "properties": { "name": "Adams", "fips": "001", "population": 20000, "water_area": 12.5 }, "source": "census", "geometry": { "type": "Polygon", "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]] } }"#;
+    match geojson.parse::<GeoJson>() {
+        Ok(GeoJson::Feature(feature)) => feature,
+        _ => panic!("The geojson did not parse correctly"),
+    }
+}
+
+#[test]
+fn test_projected_on_load() {
+    let projection = Projection {
+        properties: PropertyFilter::only(vec!["name", "fips", "area"]),
+        foreign_members: false,
+    };
+    let projected = PolygonFeature::<f64>::try_from_projected(county(), &projection).unwrap();
+    let properties = projected.properties.expect("Some properties are kept");
+    let mut keys: Vec<&String> = properties.keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["fips", "name"]);
+    assert_eq!(projected.foreign_members, None);
+
+    let everything =
+        PolygonFeature::<f64>::try_from_projected(county(), &Projection::default()).unwrap();
+    assert_eq!(everything.properties.map(|p| p.len()), Some(4));
+    assert!(everything.foreign_members.is_some());
+}
+
+#[test]
+fn test_projected_in_tree() {
+    let feature: Feature = Feature::try_from_projected(
+        county(),
+        &Projection {
+            properties: PropertyFilter::except(vec!["water_area"]),
+            foreign_members: true,
+        },
+    )
+    .unwrap();
+    assert_eq!(feature.properties().map(|p| p.len()), Some(3));
+
+    let mut tree = RTree::bulk_load(vec![feature]);
+    let nothing = Projection {
+        properties: PropertyFilter::None,
+        foreign_members: false,
+    };
+    tree.iter_mut()
+        .for_each(|feature| feature.project(&nothing));
+    let feature = tree.iter().next().expect("The tree isn't empty");
+    assert_eq!(feature.properties(), None);
+    match feature {
+        Feature::Polygon(polygon) => assert_eq!(polygon.foreign_members, None),
+        _ => panic!("The county is a polygon"),
+    }
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "python")]
+
+extern crate geojson_rstar;
+extern crate pyo3;
+
+use pyo3::{prelude::*, types::PyDict, wrap_pymodule};
+use std::ffi::CString;
+
+const PARCELS: &str = r#"{ "type": "FeatureCollection", "features": [
+    { "type": "Feature", "id": "a", "properties": { "apn": "001" }, "geometry": { "type": "Polygon", "coordinates": [[[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0], [0.0, 0.0]]] } },
+    { "type": "Feature", "id": "b", "properties": { "apn": "002" }, "geometry": { "type": "Point", "coordinates": [10.0, 10.0] } },
+    { "type": "Feature", "id": "c", "properties": { "apn": "003" }, "geometry": { "type": "LineString", "coordinates": [[5.0, 0.0], [5.0, 20.0]] } }
+] }"#;
+
+/// Runs `code` with the module imported as `geojson_rstar`, and `parcels`
+/// and `path` set.
+fn run_python(code: &str, path: &str) {
+    Python::initialize();
+    Python::attach(|py| {
+        let module = wrap_pymodule!(geojson_rstar::python::geojson_rstar)(py);
+        py.import("sys")
+            .and_then(|sys| sys.getattr("modules"))
+            .and_then(|modules| modules.set_item("geojson_rstar", module))
+            .expect("The module can be added to sys.modules");
+
+        let globals = PyDict::new(py);
+        globals.set_item("parcels", PARCELS).unwrap();
+        globals.set_item("path", path).unwrap();
+        let code = CString::new(code).unwrap();
+        if let Err(error) = py.run(&code, Some(&globals), None) {
+            error.display(py);
+            panic!("The Python code failed");
+        }
+    });
+}
+
+#[test]
+fn test_python_queries() {
+    let path = std::env::temp_dir().join(format!(
+        "geojson-rstar-python-{}.geojson",
+        std::process::id()
+    ));
+    std::fs::write(&path, PARCELS).unwrap();
+    run_python(
+        r#"
+from geojson_rstar import Index
+
+index = Index.from_geojson(parcels)
+assert len(index) == 3
+
+[nearest] = index.nearest(9.0, 9.0)
+assert nearest["id"] == "b", nearest
+assert [f["id"] for f in index.nearest(4.5, 2.0, k=2)] == ["a", "c"]
+
+assert sorted(f["id"] for f in index.bbox(3.0, 3.0, 11.0, 11.0)) == ["a", "b", "c"]
+assert sorted(f["id"] for f in index.within(-1.0, -1.0, 11.0, 11.0)) == ["a", "b"]
+
+[parcel] = index.contains(1.0, 1.0)
+assert parcel["properties"] == {"apn": "001"}
+assert parcel["geometry"]["type"] == "Polygon"
+assert index.contains(4.5, 1.0) == []
+
+assert len(Index.from_file(path)) == 3
+"#,
+        path.to_str().unwrap(),
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_python_errors() {
+    run_python(
+        r#"
+from geojson_rstar import ConversionError, Index
+
+try:
+    Index.from_geojson('{ "type": "Feature", "id": 7, "properties": {}, "geometry": { "type": "Point", "coordinates": [1.0] } }')
+except ConversionError as error:
+    assert error.id == 7
+else:
+    raise AssertionError("The point has one coordinate")
+
+try:
+    Index.from_geojson("not json")
+except ValueError as error:
+    assert not isinstance(error, ConversionError)
+else:
+    raise AssertionError("The text isn't GeoJSON")
+
+try:
+    Index.from_file(path)
+except OSError:
+    pass
+else:
+    raise AssertionError("The file doesn't exist")
+"#,
+        "/nonexistent/parcels.geojson",
+    );
+}
@@ -0,0 +1,184 @@
+extern crate geo;
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+
+use geo::{LineString, Polygon};
+use geojson::{feature::Id, GeoJson};
+use geojson_rstar::{
+    query::{
+        locate_in_envelope_intersecting_of_kinds, nearest_k, nearest_k_of_kinds,
+        nearest_k_to_geometry, nearest_to_geometry,
+    },
+    Feature, GeometryKind,
+};
+use rstar::{RTree, AABB};
+use std::convert::TryInto;
+
+fn schools() -> RTree<Feature> {
+    let geojson = r#"{
+"type": "FeatureCollection",
+"features": [
+{ "type": "Feature", "id": "c", "properties": {}, "geometry": { "type": "Point", "coordinates": [ 3.0, 0.0 ] } },
+{ "type": "Feature", "id": "b", "properties": {}, "geometry": { "type": "Point", "coordinates": [ 0.0, 3.0 ] } },
+{ "type": "Feature", "id": "a", "properties": {}, "geometry": { "type": "Point", "coordinates": [ -3.0, 0.0 ] } },
+{ "type": "Feature", "id": 7, "properties": {}, "geometry": { "type": "Point", "coordinates": [ 1.0, 0.0 ] } },
+{ "type": "Feature", "id": "far", "properties": {}, "geometry": { "type": "LineString", "coordinates": [ [ 10.0, 0.0 ], [ 10.0, 5.0 ] ] } },
+{ "type": "Feature", "id": "campus", "properties": {}, "geometry": { "type": "GeometryCollection", "geometries": [
+    { "type": "Point", "coordinates": [ 0.0, -20.0 ] },
+    { "type": "LineString", "coordinates": [ [ -5.0, -25.0 ], [ 5.0, -25.0 ] ] }
+] } }
+]
+}"#;
+    if let Ok(GeoJson::FeatureCollection(collection)) = geojson.parse::<GeoJson>() {
+        RTree::bulk_load(
+            collection
+                .features
+                .into_iter()
+                .map(|f| f.try_into())
+                .collect::<Result<Vec<Feature>, _>>()
+                .expect("The features were correctly converted"),
+        )
+    } else {
+        panic!("The geojson did not parse as a FeatureCollection");
+    }
+}
+
+fn id(feature: &Feature) -> Option<Id> {
+    let feature: geojson::Feature = feature.clone().into();
+    feature.id
+}
+
+#[test]
+fn test_nearest_k_distances_and_ties() {
+    let tree = schools();
+
+    let nearest = nearest_k(&tree, &[0.0, 0.0], 3, None);
+    let ids: Vec<_> = nearest.iter().map(|(f, _)| id(f)).collect();
+    assert_eq!(
+        ids,
+        vec![
+            Some(Id::Number(7.into())),
+            Some(Id::String("a".into())),
+            Some(Id::String("b".into())),
+        ],
+        "Ties at distance 3 are broken by id"
+    );
+    let distances: Vec<_> = nearest.iter().map(|(_, d)| *d).collect();
+    assert_eq!(distances, vec![1.0, 3.0, 3.0]);
+}
+
+#[test]
+fn test_nearest_k_max_distance() {
+    let tree = schools();
+
+    let nearest = nearest_k(&tree, &[0.0, 0.0], 10, Some(5.0));
+    assert_eq!(nearest.len(), 4);
+
+    let nearest = nearest_k(&tree, &[0.0, 0.0], 10, None);
+    assert_eq!(nearest.len(), 6);
+    assert_eq!(nearest[4].1, 10.0);
+    assert_eq!(id(nearest[5].0), Some(Id::String("campus".into())));
+    assert_eq!(nearest[5].1, 20.0);
+
+    // The line of the collection is nearer than its point from below.
+    let nearest = nearest_k(&tree, &[1.0, -30.0], 1, None);
+    assert_eq!(nearest[0].0.kind(), GeometryKind::GeometryCollection);
+    assert_eq!(nearest[0].1, 5.0);
+
+    assert!(nearest_k(&tree, &[0.0, 0.0], 0, None).is_empty());
+}
+
+#[test]
+fn test_nearest_to_polygon() {
+    let tree = schools();
+    // A parcel whose far edge reaches past "b" towards "c".
+    let parcel = Polygon::new(
+        LineString::from(vec![
+            (2.5, 0.5),
+            (2.5, 5.0),
+            (8.0, 5.0),
+            (8.0, 0.5),
+            (2.5, 0.5),
+        ]),
+        vec![],
+    );
+
+    let (nearest, distance) =
+        nearest_to_geometry(&tree, geo::Geometry::Polygon(parcel)).expect("There is a nearest");
+    assert_eq!(id(nearest), Some(Id::String("c".into())));
+    assert!((distance - 0.5).abs() < 1e-12);
+}
+
+#[test]
+fn test_nearest_k_to_feature() {
+    let tree = schools();
+    let far = tree
+        .iter()
+        .find(|f| id(f) == Some(Id::String("far".into())))
+        .expect("The far line is in the tree");
+
+    let nearest = nearest_k_to_geometry(&tree, far, 2, None);
+    let ids: Vec<_> = nearest.iter().map(|(f, _)| id(f)).collect();
+    assert_eq!(
+        ids,
+        vec![Some(Id::String("far".into())), Some(Id::String("c".into()))]
+    );
+    assert_eq!(nearest[0].1, 0.0);
+    assert_eq!(nearest[1].1, 7.0);
+
+    let line = geo::Geometry::LineString(LineString::from(vec![(-10.0, 10.0), (10.0, 10.0)]));
+    assert!(nearest_k_to_geometry(&tree, line, 5, Some(4.0)).is_empty());
+}
+
+#[test]
+fn test_nearest_of_kinds() {
+    let tree = schools();
+
+    let nearest = nearest_k_of_kinds(&tree, &[9.0, 1.0], &[GeometryKind::Point], 1, None);
+    assert_eq!(nearest.len(), 1);
+    assert_eq!(id(nearest[0].0), Some(Id::String("c".into())));
+    assert_eq!(nearest[0].1, 6.0_f64.hypot(1.0));
+
+    let nearest = nearest_k_of_kinds(
+        &tree,
+        &[9.0, 1.0],
+        &[GeometryKind::LineString, GeometryKind::Polygon],
+        5,
+        None,
+    );
+    assert_eq!(nearest.len(), 1);
+    assert_eq!(nearest[0].0.kind(), GeometryKind::LineString);
+
+    assert!(nearest_k_of_kinds(&tree, &[9.0, 1.0], &[GeometryKind::Polygon], 1, None).is_empty());
+
+    let nearest = nearest_k_of_kinds(
+        &tree,
+        &[0.0, 0.0],
+        &[GeometryKind::GeometryCollection],
+        1,
+        None,
+    );
+    assert_eq!(id(nearest[0].0), Some(Id::String("campus".into())));
+    assert_eq!(nearest[0].1, 20.0);
+}
+
+#[test]
+fn test_locate_in_envelope_of_kinds() {
+    let tree = schools();
+    let everything = AABB::from_corners([-20.0, -20.0], [20.0, 20.0]);
+
+    let points: Vec<_> =
+        locate_in_envelope_intersecting_of_kinds(&tree, &everything, &[GeometryKind::Point])
+            .collect();
+    assert_eq!(points.len(), 4);
+    assert!(points.iter().all(|f| f.kind() == GeometryKind::Point));
+
+    let lines: Vec<_> = locate_in_envelope_intersecting_of_kinds(
+        &tree,
+        &AABB::from_corners([2.0, -1.0], [12.0, 1.0]),
+        &[GeometryKind::LineString],
+    )
+    .collect();
+    assert_eq!(lines.len(), 1);
+}
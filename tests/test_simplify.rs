@@ -0,0 +1,232 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+
+use geojson_rstar::{
+    simplify::{nearest_full, MultiResolution, Simplification, SimplifyAlgorithm, SimplifyFeature},
+    Feature, GeoJsonConversionError, LineStringFeature, MultiPolygonFeature, PolygonFeature,
+};
+use rstar::{RTree, RTreeObject};
+use std::convert::TryInto;
+
+mod common;
+
+use common::geometry_feature;
+
+fn wiggly_line() -> geojson::Feature {
+    geometry_feature(
+        r#"{ "type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 0.1], [2.0, -0.1], [3.0, 0.1], [4.0, 0.0], [5.0, 3.0], [6.0, 0.0]] }"#,
+    )
+}
+
+const DOUGLAS_PEUCKER: Simplification = Simplification {
+    algorithm: SimplifyAlgorithm::DouglasPeucker,
+    tolerance: 0.5,
+};
+
+#[test]
+fn test_simplified_line_keeps_bbox() {
+    let full: LineStringFeature = wiggly_line().try_into().unwrap();
+    let simplified = LineStringFeature::try_from_simplified(wiggly_line(), &DOUGLAS_PEUCKER)
+        .expect("The line is valid");
+
+    assert_eq!(
+        simplified.line(),
+        vec![
+            vec![0.0, 0.0],
+            vec![4.0, 0.0],
+            vec![5.0, 3.0],
+            vec![6.0, 0.0]
+        ]
+    );
+    assert_eq!(simplified.envelope(), full.envelope());
+    assert_eq!(simplified.properties, full.properties);
+
+    let visvalingam = Simplification {
+        algorithm: SimplifyAlgorithm::Visvalingam,
+        tolerance: 0.5,
+    };
+    assert_eq!(full.simplified(&visvalingam).line().len(), 4);
+}
+
+#[test]
+fn test_simplified_polygon_stays_valid() {
+    let exterior = "[[0.0, 0.0], [20.0, 0.0], [20.0, 10.0], [10.0, 12.5], [0.0, 10.0], [0.0, 0.0]]";
+    let simplification = Simplification {
+        algorithm: SimplifyAlgorithm::DouglasPeucker,
+        tolerance: 3.0,
+    };
+
+    let solid: PolygonFeature = geometry_feature(&format!(
+        r#"{{ "type": "Polygon", "coordinates": [{}] }}"#,
+        exterior
+    ))
+    .try_into()
+    .unwrap();
+    assert_eq!(solid.simplified(&simplification).polygon()[0].len(), 5);
+
+    // Taking the bump off the top would leave the hole poking out.
+    let holed: PolygonFeature = geometry_feature(&format!(
+        r#"{{ "type": "Polygon", "coordinates": [{}, [[7.0, 5.0], [13.0, 5.0], [13.0, 11.0], [7.0, 11.0], [7.0, 5.0]]] }}"#,
+        exterior
+    ))
+    .try_into()
+    .unwrap();
+    assert_eq!(holed.simplified(&simplification), holed);
+
+    // A ring that would collapse is kept as it is.
+    let small: PolygonFeature = geometry_feature(
+        r#"{ "type": "Polygon", "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]] }"#,
+    )
+    .try_into()
+    .unwrap();
+    assert_eq!(small.simplified(&simplification), small);
+}
+
+#[test]
+fn test_simplified_polygons_keep_their_topology() {
+    let visvalingam = Simplification {
+        algorithm: SimplifyAlgorithm::Visvalingam,
+        tolerance: 25.0,
+    };
+    let spike = "[[0.0, 0.0], [20.0, 0.0], [20.0, 10.0], [11.0, 10.0], [10.0, 30.0], [9.0, 10.0], [0.0, 10.0], [0.0, 0.0]]";
+    let solid: PolygonFeature = geometry_feature(&format!(
+        r#"{{ "type": "Polygon", "coordinates": [{}] }}"#,
+        spike
+    ))
+    .try_into()
+    .unwrap();
+    assert_eq!(solid.simplified(&visvalingam).polygon()[0].len(), 5);
+
+    // Taking the spike off would leave the hole in it outside the polygon.
+    let holed: PolygonFeature = geometry_feature(&format!(
+        r#"{{ "type": "Polygon", "coordinates": [{}, [[9.8, 18.0], [10.2, 18.0], [10.0, 19.0], [9.8, 18.0]]] }}"#,
+        spike
+    ))
+    .try_into()
+    .unwrap();
+    assert_eq!(holed.simplified(&visvalingam), holed);
+
+    // Filling in the notch would cover the island in it, so the notched
+    // polygon is kept as it is while the far one is still simplified.
+    let notched = "[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [6.0, 10.0], [5.0, 6.0], [4.0, 10.0], [0.0, 10.0], [0.0, 0.0]]";
+    let island = "[[4.8, 8.0], [5.2, 8.0], [5.0, 9.0], [4.8, 8.0]]";
+    let far = "[[40.0, 0.0], [50.0, 0.0], [50.0, 10.0], [45.0, 10.1], [40.0, 10.0], [40.0, 0.0]]";
+    let multi: MultiPolygonFeature = geometry_feature(&format!(
+        r#"{{ "type": "MultiPolygon", "coordinates": [[{}], [{}], [{}]] }}"#,
+        notched, island, far
+    ))
+    .try_into()
+    .unwrap();
+    let simplified = multi.simplified(&Simplification {
+        algorithm: SimplifyAlgorithm::Visvalingam,
+        tolerance: 5.0,
+    });
+    let polygons = simplified.polygons();
+    assert_eq!(polygons, {
+        let mut expected = multi.polygons();
+        expected[2][0].remove(3);
+        expected
+    });
+    assert_eq!(
+        MultiPolygonFeature::try_from_simplified(
+            geometry_feature(&format!(
+                r#"{{ "type": "MultiPolygon", "coordinates": [[{}], [{}]] }}"#,
+                notched, island
+            )),
+            &visvalingam
+        )
+        .unwrap()
+        .polygons()[0],
+        multi.polygons()[0]
+    );
+}
+
+#[test]
+fn test_multi_resolution_tree() {
+    let full: Feature = wiggly_line().try_into().unwrap();
+    let tree = RTree::bulk_load(vec![MultiResolution::new(full.clone(), &DOUGLAS_PEUCKER)]);
+
+    let nearest = tree
+        .nearest_neighbor(&[2.0, -1.0])
+        .expect("The tree isn't empty");
+    assert_eq!(nearest.full, full);
+    match &nearest.coarse {
+        Feature::LineString(line) => assert_eq!(line.line().len(), 4),
+        _ => panic!("The coarse feature is a line"),
+    }
+}
+
+#[test]
+fn test_simplified_line_never_collapses() {
+    // Douglas-Peucker takes a closed loop down to its two equal ends.
+    let loop_line = geometry_feature(
+        r#"{ "type": "LineString", "coordinates": [[0.0, 0.0], [0.2, 0.0], [0.2, 0.2], [0.0, 0.0]] }"#,
+    );
+    let full: LineStringFeature = loop_line.clone().try_into().unwrap();
+    assert_eq!(full.simplified(&DOUGLAS_PEUCKER), full);
+    assert_eq!(
+        LineStringFeature::try_from_simplified(loop_line, &DOUGLAS_PEUCKER).unwrap(),
+        full
+    );
+}
+
+#[test]
+fn test_try_from_simplified_checks_geometry() {
+    let nan = geometry_feature(
+        r#"{ "type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 0.1], [2.0, 1e300]] }"#,
+    );
+    assert!(matches!(
+        LineStringFeature::<f32>::try_from_simplified(
+            nan,
+            &Simplification {
+                algorithm: SimplifyAlgorithm::DouglasPeucker,
+                tolerance: 0.5,
+            }
+        ),
+        Err(GeoJsonConversionError::NonFiniteCoordinate(_))
+    ));
+
+    let three_d = geometry_feature(
+        r#"{ "type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 0.1, 5.0], [2.0, 0.0]] }"#,
+    );
+    assert!(matches!(
+        LineStringFeature::<f64>::try_from_simplified(three_d, &DOUGLAS_PEUCKER),
+        Err(GeoJsonConversionError::MalformedGeometry(_))
+    ));
+
+    let feature: Feature = Feature::try_from_simplified(wiggly_line(), &DOUGLAS_PEUCKER).unwrap();
+    let full: Feature = wiggly_line().try_into().unwrap();
+    assert_eq!(feature, full.simplified(&DOUGLAS_PEUCKER));
+}
+
+#[test]
+fn test_nearest_full_refines_coarse_candidates() {
+    // The spike of the comb is simplified away, so the coarse line is
+    // further from the query point than the flat line even though the full
+    // comb is nearer.
+    let comb = geometry_feature(
+        r#"{ "type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 0.0], [1.1, 0.4], [1.2, 0.0], [3.0, 0.0]] }"#,
+    );
+    let flat =
+        geometry_feature(r#"{ "type": "LineString", "coordinates": [[0.0, 0.7], [3.0, 0.7]] }"#);
+    let tree = RTree::bulk_load(
+        vec![comb, flat]
+            .into_iter()
+            .map(|feature| {
+                let full: Feature = feature.try_into().unwrap();
+                MultiResolution::new(full, &DOUGLAS_PEUCKER)
+            })
+            .collect(),
+    );
+    let point = [1.1, 0.5];
+
+    let coarse = tree.nearest_neighbor(&point).unwrap();
+    let nearest = nearest_full(&tree, &point, DOUGLAS_PEUCKER.tolerance).unwrap();
+    assert_ne!(coarse, nearest);
+    match &nearest.full {
+        Feature::LineString(line) => assert_eq!(line.line().len(), 5),
+        _ => panic!("The nearest feature is the comb"),
+    }
+    assert!(nearest_full(&RTree::<MultiResolution<Feature>>::new(), &point, 0.5).is_none());
+}
@@ -0,0 +1,234 @@
+#![cfg(feature = "tokio")]
+
+extern crate futures_core;
+extern crate geojson_rstar;
+extern crate tokio;
+
+use futures_core::Stream;
+use geojson_rstar::{
+    stream::{load_tree, FeatureStream, StreamFormat, DEFAULT_MAX_FEATURE_SIZE},
+    Feature, GeoJsonStreamError, GeometryKind,
+};
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+};
+use tokio::io::{AsyncRead, AsyncWriteExt, ReadBuf};
+
+const PARCELS: &str = r#"{ "type": "FeatureCollection", "name": "parcels", "features": [
+    { "type": "Feature", "id": "a", "properties": { "note": "a \"quoted\" ] }" }, "geometry": { "type": "Polygon", "coordinates": [[[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0], [0.0, 0.0]]] } },
+    { "type": "Feature", "id": "b", "properties": { "features": [1, 2] }, "geometry": { "type": "Point", "coordinates": [10.0, 10.0] } },
+    { "type": "Feature", "id": "c", "properties": {}, "geometry": { "type": "LineString", "coordinates": [[5.0, 0.0], [5.0, 20.0]] } }
+], "crs": null }"#;
+
+/// Reads `chunk` bytes at a time, so features are split across reads.
+struct Chunked<'a> {
+    bytes: &'a [u8],
+    chunk: usize,
+}
+
+impl AsyncRead for Chunked<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let length = self.chunk.min(self.bytes.len()).min(buf.remaining());
+        buf.put_slice(&self.bytes[..length]);
+        self.bytes = &self.bytes[length..];
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn chunked(text: &str, chunk: usize) -> Chunked<'_> {
+    Chunked {
+        bytes: text.as_bytes(),
+        chunk,
+    }
+}
+
+async fn read_all(
+    reader: Chunked<'_>,
+    format: StreamFormat,
+) -> Vec<Result<Feature, GeoJsonStreamError>> {
+    let mut stream = FeatureStream::new(reader, format);
+    let mut features = vec![];
+    while let Some(feature) = stream.next_feature().await {
+        features.push(feature);
+    }
+    features
+}
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+fn kinds(features: Vec<Result<Feature, GeoJsonStreamError>>) -> Vec<GeometryKind> {
+    features
+        .into_iter()
+        .map(|feature| feature.unwrap().kind())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_feature_collection() {
+    for chunk in &[1, 7, 8192] {
+        let features = read_all(chunked(PARCELS, *chunk), StreamFormat::FeatureCollection).await;
+        assert_eq!(
+            kinds(features),
+            vec![
+                GeometryKind::Polygon,
+                GeometryKind::Point,
+                GeometryKind::LineString
+            ]
+        );
+    }
+
+    let tree = load_tree::<f64, _>(chunked(PARCELS, 5), StreamFormat::FeatureCollection)
+        .await
+        .unwrap();
+    assert_eq!(tree.size(), 3);
+    assert_eq!(tree.locate_all_at_point(&[10.0, 10.0]).count(), 1);
+
+    let empty = r#"{ "features": [], "type": "FeatureCollection" }"#;
+    assert!(read_all(chunked(empty, 3), StreamFormat::FeatureCollection)
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_geojson_seq() {
+    let seq = "\u{1e}{ \"type\": \"Feature\", \"properties\": {}, \"geometry\": { \"type\": \"Point\", \"coordinates\": [1.0, 2.0] } }\r\n\n\
+        { \"type\": \"Feature\", \"properties\": {}, \"geometry\": { \"type\": \"Point\", \"coordinates\": [1.0] } }\n\
+        not json\n\
+        { \"type\": \"Feature\", \"properties\": {}, \"geometry\": { \"type\": \"MultiPoint\", \"coordinates\": [[1.0, 2.0]] } }";
+    let features = read_all(chunked(seq, 10), StreamFormat::GeoJsonSeq).await;
+    assert_eq!(features.len(), 4);
+    assert_eq!(features[0].as_ref().unwrap().kind(), GeometryKind::Point);
+    assert!(matches!(
+        features[1],
+        Err(GeoJsonStreamError::Conversion { feature: 1, .. })
+    ));
+    assert!(matches!(
+        features[2],
+        Err(GeoJsonStreamError::InvalidFeature { feature: 2, .. })
+    ));
+    assert_eq!(
+        features[3].as_ref().unwrap().kind(),
+        GeometryKind::MultiPoint
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_documents() {
+    let truncated = &PARCELS[..PARCELS.find("\"c\"").unwrap()];
+    let features = read_all(chunked(truncated, 16), StreamFormat::FeatureCollection).await;
+    assert_eq!(features.len(), 3);
+    assert!(features[1].is_ok());
+    assert!(matches!(
+        features[2],
+        Err(GeoJsonStreamError::InvalidDocument(_))
+    ));
+
+    for text in &[
+        "[1, 2]",
+        r#"{ "type": "FeatureCollection" }"#,
+        r#"{ "features": [1] }"#,
+    ] {
+        let features = read_all(chunked(text, 4), StreamFormat::FeatureCollection).await;
+        assert!(matches!(
+            features.as_slice(),
+            [Err(GeoJsonStreamError::InvalidDocument(_))]
+        ));
+    }
+
+    let error = load_tree::<f64, _>(chunked(truncated, 16), StreamFormat::FeatureCollection).await;
+    assert!(matches!(error, Err(GeoJsonStreamError::InvalidDocument(_))));
+}
+
+#[tokio::test]
+async fn test_backpressure_and_cancellation() {
+    let (mut writer, reader) = tokio::io::duplex(16);
+    let mut stream: FeatureStream<f64, _> = FeatureStream::new(reader, StreamFormat::GeoJsonSeq);
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+
+    // Nothing is read until a feature is asked for, so the writer fills the
+    // pipe and then has to wait.
+    let line = "{ \"type\": \"Feature\", \"properties\": {}, \"geometry\": { \"type\": \"Point\", \"coordinates\": [1.0, 2.0] } }\n";
+    let mut write = Box::pin(writer.write_all(line.as_bytes()));
+    assert!(write.as_mut().poll(&mut cx).is_pending());
+
+    // A feature that's only partly written isn't returned, and dropping the
+    // future waiting on it loses nothing.
+    {
+        let mut next = Box::pin(stream.next_feature());
+        assert!(next.as_mut().poll(&mut cx).is_pending());
+    }
+    let (written, point) = tokio::join!(write, stream.next_feature());
+    written.unwrap();
+    drop(writer);
+
+    let point = point.unwrap().unwrap();
+    assert_eq!(point.kind(), GeometryKind::Point);
+    assert!(stream.next_feature().await.is_none());
+    assert!(Pin::new(&mut stream).poll_next(&mut cx).is_ready());
+    assert_eq!(stream.features_read(), 1);
+}
+
+#[tokio::test]
+async fn test_max_feature_size() {
+    let point = "{ \"type\": \"Feature\", \"properties\": {}, \"geometry\": { \"type\": \"Point\", \"coordinates\": [1.0, 2.0] } }";
+    let padded = format!(
+        "{{ \"type\": \"Feature\", \"properties\": {{ \"note\": \"{}\" }}, \"geometry\": {{ \"type\": \"Point\", \"coordinates\": [3.0, 4.0] }} }}",
+        "x".repeat(20_000)
+    );
+    let seq = format!("{}\n{}\n{}\n", point, padded, point);
+    let mut stream: FeatureStream<f64, _> =
+        FeatureStream::new(chunked(&seq, 4096), StreamFormat::GeoJsonSeq)
+            .with_max_feature_size(1024);
+    let mut features = vec![];
+    while let Some(feature) = stream.next_feature().await {
+        features.push(feature);
+    }
+    assert_eq!(features.len(), 3);
+    assert!(features[0].is_ok());
+    assert!(matches!(
+        features[1],
+        Err(GeoJsonStreamError::InvalidFeature { feature: 1, .. })
+    ));
+    assert!(features[2].is_ok());
+
+    // A short line read in one chunk is still held to the limit.
+    let mut stream: FeatureStream<f64, _> =
+        FeatureStream::new(chunked(point, 8192), StreamFormat::GeoJsonSeq)
+            .with_max_feature_size(16);
+    assert!(matches!(
+        stream.next_feature().await,
+        Some(Err(GeoJsonStreamError::InvalidFeature { feature: 0, .. }))
+    ));
+    assert!(stream.next_feature().await.is_none());
+
+    let collection = format!(
+        "{{ \"type\": \"FeatureCollection\", \"features\": [{}, {}] }}",
+        point, padded
+    );
+    let mut stream: FeatureStream<f64, _> =
+        FeatureStream::new(chunked(&collection, 512), StreamFormat::FeatureCollection)
+            .with_max_feature_size(1024);
+    assert!(stream.next_feature().await.unwrap().is_ok());
+    assert!(matches!(
+        stream.next_feature().await,
+        Some(Err(GeoJsonStreamError::InvalidFeature { feature: 1, .. }))
+    ));
+    assert!(stream.next_feature().await.is_none());
+
+    let features = read_all(chunked(&collection, 8192), StreamFormat::FeatureCollection).await;
+    assert!(padded.len() < DEFAULT_MAX_FEATURE_SIZE);
+    assert!(features.iter().all(Result::is_ok));
+}
@@ -0,0 +1,343 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate rstar;
+
+use geojson::GeoJson;
+use geojson_rstar::{
+    vector_tile::{encode_tile, TileAddress, TileOptions},
+    Feature,
+};
+use rstar::{RTree, AABB};
+use std::{collections::HashMap, convert::TryInto};
+
+/// One field of a protobuf message.
+enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+}
+
+fn read_varint(bytes: &[u8], offset: &mut usize) -> u64 {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*offset];
+        *offset += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+fn read_message(bytes: &[u8]) -> Vec<(u64, Field<'_>)> {
+    let mut fields = vec![];
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let key = read_varint(bytes, &mut offset);
+        let field = match key & 0x7 {
+            0 => Field::Varint(read_varint(bytes, &mut offset)),
+            1 => {
+                let mut word = [0; 8];
+                word.copy_from_slice(&bytes[offset..offset + 8]);
+                offset += 8;
+                Field::Fixed64(u64::from_le_bytes(word))
+            }
+            2 => {
+                let length = read_varint(bytes, &mut offset) as usize;
+                offset += length;
+                Field::Bytes(&bytes[offset - length..offset])
+            }
+            wire_type => panic!("Unexpected wire type {}", wire_type),
+        };
+        fields.push((key >> 3, field));
+    }
+    fields
+}
+
+fn read_packed(bytes: &[u8]) -> Vec<u32> {
+    let mut values = vec![];
+    let mut offset = 0;
+    while offset < bytes.len() {
+        values.push(read_varint(bytes, &mut offset) as u32);
+    }
+    values
+}
+
+fn unzigzag(value: u32) -> i32 {
+    ((value >> 1) as i32) ^ -((value & 1) as i32)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Double(f64),
+    Int(i64),
+    Bool(bool),
+}
+
+#[derive(Debug)]
+struct DecodedFeature {
+    id: Option<u64>,
+    geom_type: u64,
+    properties: HashMap<String, Value>,
+    /// The positions after each MoveTo, with whether the part was closed.
+    parts: Vec<(Vec<[i32; 2]>, bool)>,
+}
+
+struct DecodedLayer {
+    name: String,
+    keys: Vec<String>,
+    extent: u64,
+    features: Vec<DecodedFeature>,
+}
+
+fn decode_geometry(commands: &[u32]) -> Vec<(Vec<[i32; 2]>, bool)> {
+    let mut parts: Vec<(Vec<[i32; 2]>, bool)> = vec![];
+    let mut cursor = [0, 0];
+    let mut index = 0;
+    while index < commands.len() {
+        let id = commands[index] & 0x7;
+        let count = commands[index] >> 3;
+        index += 1;
+        if id == 7 {
+            parts.last_mut().unwrap().1 = true;
+            continue;
+        }
+        for _ in 0..count {
+            cursor[0] += unzigzag(commands[index]);
+            cursor[1] += unzigzag(commands[index + 1]);
+            index += 2;
+            if id == 1 {
+                parts.push((vec![], false));
+            }
+            parts.last_mut().unwrap().0.push(cursor);
+        }
+    }
+    parts
+}
+
+fn decode_tile(bytes: &[u8]) -> Vec<DecodedLayer> {
+    read_message(bytes)
+        .into_iter()
+        .map(|(number, field)| match (number, field) {
+            (3, Field::Bytes(layer)) => decode_layer(layer),
+            _ => panic!("A tile only has layers"),
+        })
+        .collect()
+}
+
+fn decode_layer(bytes: &[u8]) -> DecodedLayer {
+    let mut name = String::new();
+    let mut extent = 0;
+    let mut keys = vec![];
+    let mut values = vec![];
+    let mut features = vec![];
+    for (number, field) in read_message(bytes) {
+        match (number, field) {
+            (1, Field::Bytes(b)) => name = String::from_utf8(b.to_vec()).unwrap(),
+            (2, Field::Bytes(b)) => features.push(b),
+            (3, Field::Bytes(b)) => keys.push(String::from_utf8(b.to_vec()).unwrap()),
+            (4, Field::Bytes(b)) => values.push(decode_value(b)),
+            (5, Field::Varint(v)) => extent = v,
+            (15, Field::Varint(version)) => assert_eq!(version, 2),
+            _ => panic!("Unexpected layer field {}", number),
+        }
+    }
+
+    let features = features
+        .iter()
+        .map(|bytes| {
+            let mut feature = DecodedFeature {
+                id: None,
+                geom_type: 0,
+                properties: HashMap::new(),
+                parts: vec![],
+            };
+            for (number, field) in read_message(bytes) {
+                match (number, field) {
+                    (1, Field::Varint(id)) => feature.id = Some(id),
+                    (2, Field::Bytes(tags)) => {
+                        for pair in read_packed(tags).chunks(2) {
+                            feature.properties.insert(
+                                keys[pair[0] as usize].clone(),
+                                values[pair[1] as usize].clone(),
+                            );
+                        }
+                    }
+                    (3, Field::Varint(geom_type)) => feature.geom_type = geom_type,
+                    (4, Field::Bytes(geometry)) => {
+                        feature.parts = decode_geometry(&read_packed(geometry))
+                    }
+                    _ => panic!("Unexpected feature field {}", number),
+                }
+            }
+            feature
+        })
+        .collect();
+
+    DecodedLayer {
+        name,
+        keys,
+        extent,
+        features,
+    }
+}
+
+fn decode_value(bytes: &[u8]) -> Value {
+    match read_message(bytes).pop() {
+        Some((1, Field::Bytes(s))) => Value::String(String::from_utf8(s.to_vec()).unwrap()),
+        Some((3, Field::Fixed64(bits))) => Value::Double(f64::from_bits(bits)),
+        Some((5, Field::Varint(u))) => Value::Int(u as i64),
+        Some((6, Field::Varint(s))) => Value::Int(((s >> 1) as i64) ^ -((s & 1) as i64)),
+        Some((7, Field::Varint(b))) => Value::Bool(b != 0),
+        _ => panic!("Unexpected value"),
+    }
+}
+
+/// Twice the area of a ring by the surveyor's formula, in tile coordinates.
+fn ring_area(ring: &[[i32; 2]]) -> i64 {
+    (0..ring.len())
+        .map(|i| {
+            let a = ring[i];
+            let b = ring[(i + 1) % ring.len()];
+            i64::from(a[0]) * i64::from(b[1]) - i64::from(b[0]) * i64::from(a[1])
+        })
+        .sum()
+}
+
+fn tree() -> RTree<Feature> {
+    // The latitude that is half way down the tiles of zoom 1 south of the
+    // equator.
+    let lat = -(std::f64::consts::FRAC_PI_2.sinh().atan().to_degrees());
+    let geojson = format!(
+        r#"{{
+"type": "FeatureCollection",
+"features": [
+{{ "type": "Feature", "id": 7, "properties": {{ "name": "a", "rank": -2, "score": 0.5, "open": true, "note": null }}, "geometry": {{ "type": "Point", "coordinates": [90.0, 0.0] }} }},
+{{ "type": "Feature", "properties": {{ "name": "far" }}, "geometry": {{ "type": "Point", "coordinates": [-90.0, 45.0] }} }},
+{{ "type": "Feature", "id": "road", "properties": {{ "name": "road" }}, "geometry": {{ "type": "LineString", "coordinates": [[-90.0, {lat}], [90.0, {lat}]] }} }},
+{{ "type": "Feature", "properties": {{ "name": "park" }}, "geometry": {{ "type": "Polygon", "coordinates": [[[45.0, -10.0], [135.0, -10.0], [135.0, -30.0], [45.0, -30.0], [45.0, -10.0]]] }} }}
+]
+}}"#,
+        lat = lat
+    );
+
+    match geojson.parse::<GeoJson>() {
+        Ok(GeoJson::FeatureCollection(collection)) => RTree::bulk_load(
+            collection
+                .features
+                .into_iter()
+                .map(|f| f.try_into().expect("The feature is valid"))
+                .collect(),
+        ),
+        _ => panic!("The geojson did not parse correctly"),
+    }
+}
+
+fn name(feature: &DecodedFeature) -> &Value {
+    &feature.properties["name"]
+}
+
+#[test]
+fn test_encode_tile_decodes() {
+    let options = TileOptions {
+        layer_name: "places".into(),
+        ..TileOptions::default()
+    };
+    let layers = decode_tile(&encode_tile(&tree(), TileAddress::new(1, 1, 1), &options));
+
+    assert_eq!(layers.len(), 1);
+    let layer = &layers[0];
+    assert_eq!(layer.name, "places");
+    assert_eq!(layer.extent, 4096);
+    assert_eq!(layer.features.len(), 3);
+
+    let point = layer
+        .features
+        .iter()
+        .find(|f| name(f) == &Value::String("a".into()))
+        .expect("The point is in the tile");
+    assert_eq!(point.id, Some(7));
+    assert_eq!(point.geom_type, 1);
+    assert_eq!(point.parts, vec![(vec![[2048, 0]], false)]);
+    assert_eq!(point.properties["rank"], Value::Int(-2));
+    assert_eq!(point.properties["score"], Value::Double(0.5));
+    assert_eq!(point.properties["open"], Value::Bool(true));
+    assert!(!point.properties.contains_key("note"));
+
+    // The line starts west of the tile and is cut at the edge of the buffer.
+    let road = layer
+        .features
+        .iter()
+        .find(|f| name(f) == &Value::String("road".into()))
+        .expect("The line is in the tile");
+    assert_eq!(road.id, None);
+    assert_eq!(road.geom_type, 2);
+    assert_eq!(road.parts, vec![(vec![[-64, 2048], [2048, 2048]], false)]);
+
+    let park = layer
+        .features
+        .iter()
+        .find(|f| name(f) == &Value::String("park".into()))
+        .expect("The polygon is in the tile");
+    assert_eq!(park.geom_type, 3);
+    assert_eq!(park.parts.len(), 1);
+    let (ring, closed) = &park.parts[0];
+    assert!(closed);
+    assert_eq!(ring.len(), 4);
+    assert!(
+        ring_area(ring) > 0,
+        "An exterior ring is clockwise in tile coordinates"
+    );
+    assert!(ring.iter().all(|p| p[0] >= 1024 && p[0] <= 3072));
+}
+
+#[test]
+fn test_features_clipped_away_add_no_tags() {
+    // The line's bbox reaches into the tile but the line passes north of it.
+    let kite = r#"{ "type": "Feature", "properties": { "name": "kite", "string": "long" }, "geometry": { "type": "LineString", "coordinates": [[-20.0, -5.0], [5.0, 20.0]] } }"#;
+    let mut tree = tree();
+    match kite.parse::<GeoJson>() {
+        Ok(GeoJson::Feature(feature)) => tree.insert(feature.try_into().unwrap()),
+        _ => panic!("The geojson did not parse correctly"),
+    }
+
+    let tile = encode_tile(&tree, TileAddress::new(1, 1, 1), &TileOptions::default());
+    let layers = decode_tile(&tile);
+    assert!(layers[0]
+        .features
+        .iter()
+        .all(|f| name(f) != &Value::String("kite".into())));
+    assert!(!layers[0].keys.contains(&"string".to_string()));
+}
+
+#[test]
+fn test_encode_tile_without_features_is_empty() {
+    let tile = encode_tile(&tree(), TileAddress::new(3, 0, 0), &TileOptions::default());
+    assert!(tile.is_empty());
+}
+
+#[test]
+fn test_tiles_covering_envelope() {
+    let around_origin = AABB::from_corners([-10.0, -10.0], [10.0, 10.0]);
+    assert_eq!(
+        TileAddress::covering(&around_origin, 1).collect::<Vec<_>>(),
+        vec![
+            TileAddress::new(1, 0, 0),
+            TileAddress::new(1, 1, 0),
+            TileAddress::new(1, 0, 1),
+            TileAddress::new(1, 1, 1),
+        ]
+    );
+
+    let north_east = AABB::from_corners([10.0, 10.0], [20.0, 20.0]);
+    assert_eq!(
+        TileAddress::covering(&north_east, 1).collect::<Vec<_>>(),
+        vec![TileAddress::new(1, 1, 0)]
+    );
+
+    let world = AABB::from_corners([-180.0, -90.0], [180.0, 90.0]);
+    assert_eq!(TileAddress::covering(&world, 2).count(), 16);
+}
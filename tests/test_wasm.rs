@@ -0,0 +1,59 @@
+//! Runs natively with `cargo test --features wasm`, and under Node with
+//! `wasm-pack test --node -- --features wasm`. Errors are only checked under
+//! Node, as `JsError` needs a JavaScript host.
+#![cfg(feature = "wasm")]
+
+extern crate geojson;
+extern crate geojson_rstar;
+#[cfg(target_arch = "wasm32")]
+extern crate wasm_bindgen_test;
+
+use geojson::GeoJson;
+use geojson_rstar::wasm::WasmIndex;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const PARCELS: &str = r#"{ "type": "FeatureCollection", "features": [
+    { "type": "Feature", "id": "a", "properties": {}, "geometry": { "type": "Polygon", "coordinates": [[[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0], [0.0, 0.0]]] } },
+    { "type": "Feature", "id": "b", "properties": {}, "geometry": { "type": "Point", "coordinates": [2.0, 2.0] } },
+    { "type": "Feature", "id": "c", "properties": {}, "geometry": { "type": "LineString", "coordinates": [[5.0, 0.0], [5.0, 20.0]] } }
+] }"#;
+
+/// The ids of the features of a FeatureCollection string.
+fn ids(collection: &str) -> Vec<String> {
+    match collection.parse::<GeoJson>() {
+        Ok(GeoJson::FeatureCollection(collection)) => collection
+            .features
+            .into_iter()
+            .map(|feature| match feature.id {
+                Some(geojson::feature::Id::String(id)) => id,
+                id => panic!("Every parcel has a string id, not {:?}", id),
+            })
+            .collect(),
+        _ => panic!("A query returns a FeatureCollection"),
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_wasm_queries() {
+    let index = WasmIndex::new(PARCELS).unwrap_or_else(|_| panic!("The parcels load"));
+    assert_eq!(index.size(), 3);
+
+    assert_eq!(ids(&index.nearest(6.0, 1.0, 1)), vec!["c"]);
+    let mut in_box = ids(&index.bbox(1.0, 1.0, 3.0, 3.0));
+    in_box.sort();
+    assert_eq!(in_box, vec!["a", "b"]);
+    assert_eq!(ids(&index.point_in_polygon(2.0, 2.0)), vec!["a"]);
+    assert!(ids(&index.point_in_polygon(5.0, 1.0)).is_empty());
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+fn test_wasm_errors() {
+    assert!(WasmIndex::new("not json").is_err());
+    assert!(WasmIndex::new(
+        r#"{ "type": "Feature", "properties": {}, "geometry": { "type": "Point", "coordinates": [1.0] } }"#
+    )
+    .is_err());
+}
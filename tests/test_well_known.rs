@@ -0,0 +1,136 @@
+extern crate geojson;
+extern crate geojson_rstar;
+extern crate serde_json;
+
+use geojson::feature::Id;
+use geojson_rstar::{
+    well_known::wkb_to_value, Feature, GeoJsonConversionError, GeometryKind, PointFeature,
+    PolygonFeature, WellKnown,
+};
+use serde_json::json;
+
+fn hex_to_bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("The hex is valid"))
+        .collect()
+}
+
+#[test]
+fn test_polygon_from_wkt_round_trip() {
+    let wkt = "POLYGON((0 0,4 0,4 4,0 4,0 0),(1 1,2 1,2 2,1 1))";
+    let properties = json!({ "name": "park" }).as_object().cloned();
+
+    let polygon = PolygonFeature::<f64>::from_wkt(wkt, Some(Id::Number(3.into())), properties)
+        .expect("The polygon is valid WKT");
+
+    assert_eq!(polygon.id, Some(Id::Number(3.into())));
+    assert_eq!(
+        polygon.properties.as_ref().unwrap().get("name"),
+        Some(&json!("park"))
+    );
+    assert_eq!(polygon.polygon().len(), 2);
+    assert_eq!(polygon.to_wkt(), wkt);
+}
+
+#[test]
+fn test_feature_wkb_round_trip() {
+    let wkt = "MULTIPOLYGON(((0 0,1 0,1 1,0 0)),((5 5,6 5,6 6,5 5)))";
+    let feature = Feature::<f64>::from_wkt(wkt, None, None).expect("The WKT is valid");
+    assert_eq!(feature.kind(), GeometryKind::MultiPolygon);
+
+    let decoded =
+        Feature::<f64>::from_wkb(&feature.to_wkb(), None, None).expect("The WKB is valid");
+
+    assert_eq!(decoded, feature);
+    assert_eq!(decoded.to_wkt(), wkt);
+}
+
+#[test]
+fn test_point_from_ewkb_and_big_endian_wkb() {
+    // PostGIS `SRID=4326;POINT(1 2)`.
+    let ewkb = hex_to_bytes("0101000020E6100000000000000000F03F0000000000000040");
+    let point = PointFeature::<f32>::from_wkb(&ewkb, None, None).expect("The EWKB is valid");
+    assert_eq!(point.point(), vec![1.0, 2.0]);
+
+    let big_endian = hex_to_bytes("00000000013FF00000000000004000000000000000");
+    let point = PointFeature::<f64>::from_wkb(&big_endian, None, None).expect("The WKB is valid");
+    assert_eq!(point.point(), vec![1.0, 2.0]);
+    assert_eq!(
+        point.to_wkb(),
+        hex_to_bytes("0101000000000000000000F03F0000000000000040")
+    );
+}
+
+#[test]
+fn test_invalid_well_known_geometries() {
+    assert!(matches!(
+        PointFeature::<f64>::from_wkt("POINT(1 2", None, None),
+        Err(GeoJsonConversionError::InvalidWkt(_))
+    ));
+    assert!(matches!(
+        PointFeature::<f64>::from_wkt("POINT EMPTY", Some(Id::String("a".into())), None),
+        Err(GeoJsonConversionError::MalformedGeometry(Some(Id::String(
+            _
+        ))))
+    ));
+    assert!(matches!(
+        PointFeature::<f64>::from_wkt("POINT Z (1 2 3)", None, None),
+        Err(GeoJsonConversionError::MalformedGeometry(None))
+    ));
+    assert!(matches!(
+        PointFeature::<f64>::from_wkt("LINESTRING(0 0,1 1)", None, None),
+        Err(GeoJsonConversionError::IncorrectGeometryValue(_))
+    ));
+    assert!(matches!(
+        PointFeature::<f64>::from_wkb(&hex_to_bytes("0101000000000000000000F03F"), None, None),
+        Err(GeoJsonConversionError::InvalidWkb(_))
+    ));
+    // An empty WKB point has NaN coordinates.
+    assert!(matches!(
+        PointFeature::<f64>::from_wkb(
+            &hex_to_bytes("0101000000000000000000F87F000000000000F87F"),
+            None,
+            None
+        ),
+        Err(GeoJsonConversionError::NonFiniteCoordinate(None))
+    ));
+}
+
+#[test]
+fn test_empty_collections_are_rejected() {
+    for wkt in &[
+        "GEOMETRYCOLLECTION EMPTY",
+        "GEOMETRYCOLLECTION(POINT(1 2),GEOMETRYCOLLECTION EMPTY)",
+    ] {
+        assert!(matches!(
+            Feature::<f64>::from_wkt(wkt, None, None),
+            Err(GeoJsonConversionError::MalformedGeometry(None))
+        ));
+    }
+    // A little endian collection of no geometries, and one holding it.
+    for wkb in &["010700000000000000", "010700000001000000010700000000000000"] {
+        assert!(matches!(
+            Feature::<f64>::from_wkb(&hex_to_bytes(wkb), None, None),
+            Err(GeoJsonConversionError::MalformedGeometry(None))
+        ));
+    }
+}
+
+#[test]
+fn test_deeply_nested_wkb_collections() {
+    // Each level is a little endian collection of one geometry, around an
+    // empty collection.
+    let nested = |levels: usize| {
+        let mut bytes = hex_to_bytes("010700000001000000").repeat(levels);
+        bytes.extend(hex_to_bytes("010700000000000000"));
+        bytes
+    };
+    assert!(wkb_to_value(&nested(63)).is_ok());
+    match wkb_to_value(&nested(1_000_000)) {
+        Err(GeoJsonConversionError::InvalidWkb(message)) => {
+            assert_eq!(message, "Geometry collections nested too deeply")
+        }
+        other => panic!("The nesting is rejected, not {:?}", other.map(|_| ())),
+    }
+}